@@ -224,6 +224,22 @@ impl State {
         Ok(())
     }
 
+    #[gml::function]
+    pub fn ds_list_set(&mut self, id: i32, pos: i32, val: vm::ValueRef) ->
+        Result<(), vm::ErrorKind>
+    {
+        let list = self.lists.get_mut(&id).ok_or(Error::Resource(Type::List, id))?;
+        if pos < 0 {
+            return Ok(());
+        }
+        let pos = pos as usize;
+        if list.len() <= pos {
+            list.resize_with(pos + 1, || vm::Value::from(0));
+        }
+        list[pos] = val.clone();
+        Ok(())
+    }
+
     // ds_map
 
     #[gml::function]
@@ -294,6 +310,15 @@ impl State {
         Ok(())
     }
 
+    #[gml::function]
+    pub fn ds_map_set(&mut self, id: i32, key: vm::ValueRef, val: vm::ValueRef) ->
+        Result<(), vm::ErrorKind>
+    {
+        let map = self.maps.get_mut(&id).ok_or(Error::Resource(Type::Map, id))?;
+        map.insert(MapKey(key.clone()), val.clone());
+        Ok(())
+    }
+
     #[gml::function]
     pub fn ds_map_delete(&mut self, id: i32, key: vm::ValueRef) -> Result<(), vm::ErrorKind> {
         let map = self.maps.get_mut(&id).ok_or(Error::Resource(Type::Map, id))?;
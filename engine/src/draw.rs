@@ -0,0 +1,63 @@
+use gml::{self, vm};
+
+#[derive(Default)]
+pub struct State;
+
+#[gml::bind(Api)]
+impl State {
+    // Colors are stored as 0xBBGGRR.
+
+    #[gml::constant]
+    pub const C_AQUA: u32 = 0xffff00;
+    #[gml::constant]
+    pub const C_BLACK: u32 = 0x000000;
+    #[gml::constant]
+    pub const C_BLUE: u32 = 0xff0000;
+    #[gml::constant]
+    pub const C_DKGRAY: u32 = 0x404040;
+    #[gml::constant]
+    pub const C_FUCHSIA: u32 = 0xff00ff;
+    #[gml::constant]
+    pub const C_GRAY: u32 = 0x808080;
+    #[gml::constant]
+    pub const C_GREEN: u32 = 0x008000;
+    #[gml::constant]
+    pub const C_LIME: u32 = 0x00ff00;
+    #[gml::constant]
+    pub const C_LTGRAY: u32 = 0xc0c0c0;
+    #[gml::constant]
+    pub const C_MAROON: u32 = 0x000080;
+    #[gml::constant]
+    pub const C_NAVY: u32 = 0x800000;
+    #[gml::constant]
+    pub const C_OLIVE: u32 = 0x008080;
+    #[gml::constant]
+    pub const C_ORANGE: u32 = 0x40a0ff;
+    #[gml::constant]
+    pub const C_PURPLE: u32 = 0x800080;
+    #[gml::constant]
+    pub const C_RED: u32 = 0x0000ff;
+    #[gml::constant]
+    pub const C_SILVER: u32 = 0xc0c0c0;
+    #[gml::constant]
+    pub const C_TEAL: u32 = 0x808000;
+    #[gml::constant]
+    pub const C_WHITE: u32 = 0xffffff;
+    #[gml::constant]
+    pub const C_YELLOW: u32 = 0x00ffff;
+
+    // Text alignment, for `draw_set_halign` and `draw_set_valign`.
+
+    #[gml::constant]
+    pub const FA_LEFT: u32 = 0;
+    #[gml::constant]
+    pub const FA_CENTER: u32 = 1;
+    #[gml::constant]
+    pub const FA_RIGHT: u32 = 2;
+    #[gml::constant]
+    pub const FA_TOP: u32 = 0;
+    #[gml::constant]
+    pub const FA_MIDDLE: u32 = 1;
+    #[gml::constant]
+    pub const FA_BOTTOM: u32 = 2;
+}
@@ -0,0 +1,150 @@
+use gml::{self, vm};
+use project::event_type;
+
+#[derive(Default)]
+pub struct State;
+
+#[gml::bind(Api)]
+impl State {
+    // Event types.
+
+    #[gml::constant]
+    pub const EV_CREATE: u32 = event_type::CREATE;
+    #[gml::constant]
+    pub const EV_DESTROY: u32 = event_type::DESTROY;
+    #[gml::constant]
+    pub const EV_ALARM: u32 = event_type::ALARM;
+    #[gml::constant]
+    pub const EV_STEP: u32 = event_type::STEP;
+    #[gml::constant]
+    pub const EV_COLLISION: u32 = event_type::COLLISION;
+    #[gml::constant]
+    pub const EV_KEYBOARD: u32 = event_type::KEYBOARD;
+    #[gml::constant]
+    pub const EV_MOUSE: u32 = event_type::MOUSE;
+    #[gml::constant]
+    pub const EV_OTHER: u32 = event_type::OTHER;
+    #[gml::constant]
+    pub const EV_DRAW: u32 = event_type::DRAW;
+    #[gml::constant]
+    pub const EV_KEYPRESS: u32 = event_type::KEY_PRESS;
+    #[gml::constant]
+    pub const EV_KEYRELEASE: u32 = event_type::KEY_RELEASE;
+    #[gml::constant]
+    pub const EV_TRIGGER: u32 = event_type::TRIGGER;
+
+    // Step event kinds.
+
+    #[gml::constant]
+    pub const EV_STEP_NORMAL: u32 = 0;
+    #[gml::constant]
+    pub const EV_STEP_BEGIN: u32 = 1;
+    #[gml::constant]
+    pub const EV_STEP_END: u32 = 2;
+
+    // Mouse event kinds.
+
+    #[gml::constant]
+    pub const EV_LEFT_BUTTON: u32 = 0;
+    #[gml::constant]
+    pub const EV_RIGHT_BUTTON: u32 = 1;
+    #[gml::constant]
+    pub const EV_MIDDLE_BUTTON: u32 = 2;
+    #[gml::constant]
+    pub const EV_NO_BUTTON: u32 = 3;
+    #[gml::constant]
+    pub const EV_LEFT_PRESS: u32 = 4;
+    #[gml::constant]
+    pub const EV_RIGHT_PRESS: u32 = 5;
+    #[gml::constant]
+    pub const EV_MIDDLE_PRESS: u32 = 6;
+    #[gml::constant]
+    pub const EV_LEFT_RELEASE: u32 = 7;
+    #[gml::constant]
+    pub const EV_RIGHT_RELEASE: u32 = 8;
+    #[gml::constant]
+    pub const EV_MIDDLE_RELEASE: u32 = 9;
+    #[gml::constant]
+    pub const EV_MOUSE_ENTER: u32 = 10;
+    #[gml::constant]
+    pub const EV_MOUSE_LEAVE: u32 = 11;
+    #[gml::constant]
+    pub const EV_GLOBAL_LEFT_BUTTON: u32 = 50;
+    #[gml::constant]
+    pub const EV_GLOBAL_RIGHT_BUTTON: u32 = 51;
+    #[gml::constant]
+    pub const EV_GLOBAL_MIDDLE_BUTTON: u32 = 52;
+    #[gml::constant]
+    pub const EV_GLOBAL_LEFT_PRESS: u32 = 53;
+    #[gml::constant]
+    pub const EV_GLOBAL_RIGHT_PRESS: u32 = 54;
+    #[gml::constant]
+    pub const EV_GLOBAL_MIDDLE_PRESS: u32 = 55;
+    #[gml::constant]
+    pub const EV_GLOBAL_LEFT_RELEASE: u32 = 56;
+    #[gml::constant]
+    pub const EV_GLOBAL_RIGHT_RELEASE: u32 = 57;
+    #[gml::constant]
+    pub const EV_GLOBAL_MIDDLE_RELEASE: u32 = 58;
+    #[gml::constant]
+    pub const EV_MOUSE_WHEEL_UP: u32 = 60;
+    #[gml::constant]
+    pub const EV_MOUSE_WHEEL_DOWN: u32 = 61;
+
+    // Other event kinds.
+
+    #[gml::constant]
+    pub const EV_OUTSIDE: u32 = 0;
+    #[gml::constant]
+    pub const EV_BOUNDARY: u32 = 1;
+    #[gml::constant]
+    pub const EV_GAME_START: u32 = 2;
+    #[gml::constant]
+    pub const EV_GAME_END: u32 = 3;
+    #[gml::constant]
+    pub const EV_ROOM_START: u32 = 4;
+    #[gml::constant]
+    pub const EV_ROOM_END: u32 = 5;
+    #[gml::constant]
+    pub const EV_NO_MORE_LIVES: u32 = 6;
+    #[gml::constant]
+    pub const EV_ANIMATION_END: u32 = 7;
+    #[gml::constant]
+    pub const EV_END_OF_PATH: u32 = 8;
+    #[gml::constant]
+    pub const EV_NO_MORE_HEALTH: u32 = 9;
+    #[gml::constant]
+    pub const EV_USER0: u32 = 10;
+    #[gml::constant]
+    pub const EV_USER1: u32 = 11;
+    #[gml::constant]
+    pub const EV_USER2: u32 = 12;
+    #[gml::constant]
+    pub const EV_USER3: u32 = 13;
+    #[gml::constant]
+    pub const EV_USER4: u32 = 14;
+    #[gml::constant]
+    pub const EV_USER5: u32 = 15;
+    #[gml::constant]
+    pub const EV_USER6: u32 = 16;
+    #[gml::constant]
+    pub const EV_USER7: u32 = 17;
+    #[gml::constant]
+    pub const EV_USER8: u32 = 18;
+    #[gml::constant]
+    pub const EV_USER9: u32 = 19;
+    #[gml::constant]
+    pub const EV_USER10: u32 = 20;
+    #[gml::constant]
+    pub const EV_USER11: u32 = 21;
+    #[gml::constant]
+    pub const EV_USER12: u32 = 22;
+    #[gml::constant]
+    pub const EV_USER13: u32 = 23;
+    #[gml::constant]
+    pub const EV_USER14: u32 = 24;
+    #[gml::constant]
+    pub const EV_USER15: u32 = 25;
+    #[gml::constant]
+    pub const EV_CLOSE_BUTTON: u32 = 30;
+}
@@ -0,0 +1,122 @@
+use gml::{self, vm};
+
+#[derive(Default)]
+pub struct State;
+
+#[gml::bind(Api)]
+impl State {
+    // Key codes are Windows virtual-key codes.
+
+    #[gml::constant]
+    pub const VK_NOKEY: u32 = 0;
+    #[gml::constant]
+    pub const VK_ANYKEY: u32 = 1;
+    #[gml::constant]
+    pub const VK_BACKSPACE: u32 = 8;
+    #[gml::constant]
+    pub const VK_TAB: u32 = 9;
+    #[gml::constant]
+    pub const VK_RETURN: u32 = 13;
+    #[gml::constant]
+    pub const VK_ENTER: u32 = 13;
+    #[gml::constant]
+    pub const VK_SHIFT: u32 = 16;
+    #[gml::constant]
+    pub const VK_CONTROL: u32 = 17;
+    #[gml::constant]
+    pub const VK_ALT: u32 = 18;
+    #[gml::constant]
+    pub const VK_PAUSE: u32 = 19;
+    #[gml::constant]
+    pub const VK_ESCAPE: u32 = 27;
+    #[gml::constant]
+    pub const VK_SPACE: u32 = 32;
+    #[gml::constant]
+    pub const VK_PAGEUP: u32 = 33;
+    #[gml::constant]
+    pub const VK_PAGEDOWN: u32 = 34;
+    #[gml::constant]
+    pub const VK_END: u32 = 35;
+    #[gml::constant]
+    pub const VK_HOME: u32 = 36;
+    #[gml::constant]
+    pub const VK_LEFT: u32 = 37;
+    #[gml::constant]
+    pub const VK_UP: u32 = 38;
+    #[gml::constant]
+    pub const VK_RIGHT: u32 = 39;
+    #[gml::constant]
+    pub const VK_DOWN: u32 = 40;
+    #[gml::constant]
+    pub const VK_PRINTSCREEN: u32 = 44;
+    #[gml::constant]
+    pub const VK_INSERT: u32 = 45;
+    #[gml::constant]
+    pub const VK_DELETE: u32 = 46;
+    #[gml::constant]
+    pub const VK_NUMPAD0: u32 = 96;
+    #[gml::constant]
+    pub const VK_NUMPAD1: u32 = 97;
+    #[gml::constant]
+    pub const VK_NUMPAD2: u32 = 98;
+    #[gml::constant]
+    pub const VK_NUMPAD3: u32 = 99;
+    #[gml::constant]
+    pub const VK_NUMPAD4: u32 = 100;
+    #[gml::constant]
+    pub const VK_NUMPAD5: u32 = 101;
+    #[gml::constant]
+    pub const VK_NUMPAD6: u32 = 102;
+    #[gml::constant]
+    pub const VK_NUMPAD7: u32 = 103;
+    #[gml::constant]
+    pub const VK_NUMPAD8: u32 = 104;
+    #[gml::constant]
+    pub const VK_NUMPAD9: u32 = 105;
+    #[gml::constant]
+    pub const VK_MULTIPLY: u32 = 106;
+    #[gml::constant]
+    pub const VK_ADD: u32 = 107;
+    #[gml::constant]
+    pub const VK_SUBTRACT: u32 = 109;
+    #[gml::constant]
+    pub const VK_DECIMAL: u32 = 110;
+    #[gml::constant]
+    pub const VK_DIVIDE: u32 = 111;
+    #[gml::constant]
+    pub const VK_F1: u32 = 112;
+    #[gml::constant]
+    pub const VK_F2: u32 = 113;
+    #[gml::constant]
+    pub const VK_F3: u32 = 114;
+    #[gml::constant]
+    pub const VK_F4: u32 = 115;
+    #[gml::constant]
+    pub const VK_F5: u32 = 116;
+    #[gml::constant]
+    pub const VK_F6: u32 = 117;
+    #[gml::constant]
+    pub const VK_F7: u32 = 118;
+    #[gml::constant]
+    pub const VK_F8: u32 = 119;
+    #[gml::constant]
+    pub const VK_F9: u32 = 120;
+    #[gml::constant]
+    pub const VK_F10: u32 = 121;
+    #[gml::constant]
+    pub const VK_F11: u32 = 122;
+    #[gml::constant]
+    pub const VK_F12: u32 = 123;
+    #[gml::constant]
+    pub const VK_LSHIFT: u32 = 160;
+    #[gml::constant]
+    pub const VK_RSHIFT: u32 = 161;
+    #[gml::constant]
+    pub const VK_LCONTROL: u32 = 162;
+    #[gml::constant]
+    pub const VK_RCONTROL: u32 = 163;
+    #[gml::constant]
+    pub const VK_LALT: u32 = 164;
+    #[gml::constant]
+    pub const VK_RALT: u32 = 165;
+}
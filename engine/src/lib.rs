@@ -1,7 +1,6 @@
 use std::collections::HashMap;
-use std::io;
 
-use gml::{self, symbol::Symbol, vm};
+use gml::{self, symbol::Symbol, diagnostic::Diagnostic, vm};
 
 pub use crate::world::World;
 
@@ -12,6 +11,9 @@ pub mod motion;
 pub mod instance;
 pub mod show;
 pub mod data;
+pub mod draw;
+pub mod keyboard;
+pub mod event;
 
 #[derive(Default)]
 pub struct Assets {
@@ -19,12 +21,29 @@ pub struct Assets {
 }
 
 /// Build a Game Maker project.
-pub fn build<'a, F: FnMut() -> E, E: io::Write + 'static>(
-    game: &'a project::Game, engine: &HashMap<Symbol, gml::Item<World, Assets>>, errors: F
-) -> Result<(Assets, vm::Debug), u32> {
+pub fn build<'a>(
+    game: &'a project::Game, engine: &HashMap<Symbol, gml::Item<World, Assets>>
+) -> Result<(Assets, vm::Debug, Vec<Diagnostic>), Vec<Diagnostic>> {
+    build_with(game, engine, &gml::Options::default())
+}
+
+/// Build a Game Maker project, with non-default `gml::Options`.
+pub fn build_with<'a>(
+    game: &'a project::Game, engine: &HashMap<Symbol, gml::Item<World, Assets>>,
+    options: &gml::Options
+) -> Result<(Assets, vm::Debug, Vec<Diagnostic>), Vec<Diagnostic>> {
     let assets = Assets::default();
-    match gml::build(game, engine, errors) {
-        Ok((code, debug)) => Ok((Assets { code, ..assets }, debug)),
-        Err(count) => Err(count),
+    match gml::build_with(game, engine, options) {
+        Ok((code, debug, diagnostics)) => Ok((Assets { code, ..assets }, debug, diagnostics)),
+        Err(diagnostics) => Err(diagnostics),
     }
 }
+
+/// Restore the code of a Game Maker project, saved by `gml::save` from an earlier build.
+pub fn load(
+    data: &[u8], game: &project::Game, engine: &HashMap<Symbol, gml::Item<World, Assets>>,
+    options: &gml::Options
+) -> Result<(Assets, vm::Debug), vm::serialize::Error> {
+    let (code, debug) = gml::load(data, game, engine, options)?;
+    Ok((Assets { code }, debug))
+}
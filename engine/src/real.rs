@@ -18,6 +18,9 @@ impl Default for State {
 
 #[gml::bind(Api)]
 impl State {
+    #[gml::constant]
+    pub const PI: f64 = f64::consts::PI;
+
     /// Emulate Delphi's LCG to advance the current random state.
     fn random_next(&mut self) -> u32 {
         self.random_seed = self.random_seed * Wrapping(0x8088405) + Wrapping(1);
@@ -4,7 +4,6 @@ use gml::{self, symbol::Symbol, vm};
 
 use crate::*;
 
-#[derive(Default)]
 pub struct World {
     pub world: vm::World,
     pub real: real::State,
@@ -13,6 +12,27 @@ pub struct World {
     pub instance: instance::State,
     pub show: show::State,
     pub data: data::State,
+    pub draw: draw::State,
+    pub keyboard: keyboard::State,
+    pub event: event::State,
+}
+
+impl World {
+    /// Create the world for a project, with its object hierarchy.
+    pub fn new(objects: &[project::Object<'_>]) -> World {
+        World {
+            world: vm::World::new(objects),
+            real: Default::default(),
+            string: Default::default(),
+            motion: Default::default(),
+            instance: Default::default(),
+            show: Default::default(),
+            data: Default::default(),
+            draw: Default::default(),
+            keyboard: Default::default(),
+            event: Default::default(),
+        }
+    }
 }
 
 impl vm::Api<'_, Assets> for World {
@@ -49,6 +69,18 @@ impl data::Api<'_, Assets> for World {
     fn fields(&mut self, _: &mut Assets) -> (&mut data::State,) { (&mut self.data,) }
 }
 
+impl draw::Api<'_, Assets> for World {
+    fn fields(&mut self, _: &mut Assets) -> () {}
+}
+
+impl keyboard::Api<'_, Assets> for World {
+    fn fields(&mut self, _: &mut Assets) -> () {}
+}
+
+impl event::Api<'_, Assets> for World {
+    fn fields(&mut self, _: &mut Assets) -> () {}
+}
+
 impl World {
     pub fn register(items: &mut HashMap<Symbol, gml::Item<Self, Assets>>) {
         real::Api::register(items);
@@ -57,5 +89,8 @@ impl World {
         instance::Api::register(items);
         show::Api::register(items);
         data::Api::register(items);
+        draw::Api::register(items);
+        keyboard::Api::register(items);
+        event::Api::register(items);
     }
 }
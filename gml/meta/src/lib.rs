@@ -7,7 +7,7 @@ use proc_macro::TokenStream;
 use proc_macro2;
 use syn::{
     self, parse_quote, parenthesized, punctuated,
-    ItemImpl, ImplItemMethod, Attribute, Signature, FnArg, PatType, ReturnType,
+    ItemImpl, ImplItemMethod, ImplItemConst, Attribute, Signature, FnArg, PatType, ReturnType,
     Type, TypeReference, Path, Ident
 };
 use syn::parse::{Parse, ParseStream, Result, Error};
@@ -18,6 +18,7 @@ use quote::quote;
 struct ItemBindings {
     functions: Vec<Function>,
     members: HashMap<Ident, Member>,
+    constants: Vec<Ident>,
 
     /// Ordered map from receiver types to their variable names.
     ///
@@ -73,6 +74,7 @@ impl ItemBindings {
         let mut bindings = ItemBindings {
             functions: Vec::new(),
             members: HashMap::new(),
+            constants: Vec::new(),
 
             receivers: IndexMap::default(),
         };
@@ -88,6 +90,7 @@ impl ItemBindings {
                 function: parse_quote!(gml::function),
                 get: parse_quote!(gml::get),
                 set: parse_quote!(gml::set),
+                constant: parse_quote!(gml::constant),
             };
             syn::visit_mut::visit_item_impl_mut(&mut visit, item);
         }
@@ -108,6 +111,7 @@ struct VisitBindings<'a> {
     function: Path,
     get: Path,
     set: Path,
+    constant: Path,
 }
 
 impl VisitMut for VisitBindings<'_> {
@@ -115,6 +119,18 @@ impl VisitMut for VisitBindings<'_> {
         let sig = &item.sig;
         item.attrs.retain(|attr| !self.process_attribute(attr, &sig));
     }
+
+    fn visit_impl_item_const_mut(&mut self, item: &mut ImplItemConst) {
+        let name = &item.ident;
+        let (bindings, constant) = (&mut *self.bindings, &self.constant);
+        item.attrs.retain(|attr| {
+            if attr.path != *constant {
+                return true;
+            }
+            bindings.constants.push(name.clone());
+            false
+        });
+    }
 }
 
 impl VisitBindings<'_> {
@@ -376,6 +392,10 @@ pub fn bind(attr: TokenStream, input: TokenStream) -> TokenStream {
         }
     });
 
+    let constant = bindings.constants.iter();
+    let constant_name = bindings.constants.iter()
+        .map(|constant| constant.to_string().to_lowercase());
+
     let member = bindings.members.iter().map(|(name, _)| name);
 
     let getter = bindings.members.iter().map(|(_, member)| member.getter.as_ref());
@@ -432,6 +452,11 @@ pub fn bind(attr: TokenStream, input: TokenStream) -> TokenStream {
                     gml::symbol::Symbol::intern(stringify!(#member).as_bytes()),
                     gml::Item::Member(#get_option, #set_option),
                 );)*
+
+                #(items.insert(
+                    gml::symbol::Symbol::intern(#constant_name.as_bytes()),
+                    gml::Item::Constant(vm::Value::from(#self_ty::#constant)),
+                );)*
             }
 
             #(unsafe fn #api(
@@ -110,3 +110,106 @@ impl Liveness {
         Liveness { in_, out }
     }
 }
+
+/// Dominator tree of a function's reachable blocks.
+///
+/// A block `a` dominates a block `b` if every path from the entry block to `b` passes through
+/// `a`. Blocks unreachable from the entry block have no dominators.
+pub struct Dominators {
+    idom: HandleMap<ssa::Label, Option<ssa::Label>>,
+}
+
+impl Dominators {
+    /// Computes immediate dominators with the iterative algorithm of Cooper, Harvey, and Kennedy.
+    ///
+    /// Blocks are visited in reverse postorder until no block's immediate dominator changes. Two
+    /// candidates are merged by walking up the partially-built tree to their common ancestor,
+    /// using postorder numbers to decide which side to advance.
+    pub fn compute(program: &ssa::Function, control_flow: &ControlFlow) -> Dominators {
+        let len = program.blocks.len();
+
+        // Number the reachable blocks in postorder, with an explicit stack of successor cursors.
+        let mut postorder = Vec::with_capacity(len);
+        let mut visited = BitVec::new();
+        let mut stack = vec![(ssa::ENTRY, 0)];
+        visited.set(ssa::ENTRY.index());
+        while let Some(&mut (block, ref mut next)) = stack.last_mut() {
+            match program.successors(block).get(*next) {
+                Some(&succ) => {
+                    *next += 1;
+                    if !visited.set(succ.index()) {
+                        stack.push((succ, 0));
+                    }
+                }
+                None => {
+                    postorder.push(block);
+                    stack.pop();
+                }
+            }
+        }
+        let mut number = HandleMap::with_capacity_default(len, usize::max_value());
+        for (i, &block) in postorder.iter().enumerate() {
+            number[block] = i;
+        }
+
+        let mut idom = HandleMap::with_capacity_default(len, None);
+        idom[ssa::ENTRY] = Some(ssa::ENTRY);
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for &block in postorder.iter().rev().skip(1) {
+                let mut new_idom = None;
+                for &pred in control_flow.pred.get(block).into_iter().flatten() {
+                    if idom[pred].is_none() {
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        None => pred,
+                        Some(other) => Self::intersect(&idom, &number, pred, other),
+                    });
+                }
+
+                if idom[block] != new_idom {
+                    idom[block] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        Dominators { idom }
+    }
+
+    fn intersect(
+        idom: &HandleMap<ssa::Label, Option<ssa::Label>>, number: &HandleMap<ssa::Label, usize>,
+        mut a: ssa::Label, mut b: ssa::Label
+    ) -> ssa::Label {
+        while a != b {
+            while number[a] < number[b] {
+                a = idom[a].unwrap();
+            }
+            while number[b] < number[a] {
+                b = idom[b].unwrap();
+            }
+        }
+        a
+    }
+
+    pub fn is_reachable(&self, block: ssa::Label) -> bool {
+        self.idom[block].is_some()
+    }
+
+    /// Whether `a` dominates `b`. Every reachable block dominates itself.
+    pub fn dominates(&self, a: ssa::Label, mut b: ssa::Label) -> bool {
+        loop {
+            if a == b {
+                return true;
+            }
+            match self.idom[b] {
+                Some(idom) if idom != b => b = idom,
+                _ => return false,
+            }
+        }
+    }
+}
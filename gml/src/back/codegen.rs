@@ -1,4 +1,4 @@
-use std::{i8, u8, u16, u32, slice};
+use std::{cmp, i8, u8, u16, u32, slice};
 use std::collections::{hash_map::Entry, HashMap, VecDeque};
 
 use crate::bit_vec::BitVec;
@@ -15,6 +15,8 @@ pub struct Codegen<'p> {
 
     registers: HandleMap<ssa::Value, usize>,
     register_count: usize,
+    register_limit: usize,
+    spill_count: usize,
 
     visited: BitVec,
     block_offsets: HashMap<ssa::Label, usize>,
@@ -22,10 +24,22 @@ pub struct Codegen<'p> {
     edge_block: usize,
 
     constants: HashMap<vm::Value, usize>,
+
+    too_large: bool,
 }
 
+/// A function that needs more registers, spill slots, constants, instructions, or scripts than
+/// instruction operands can address.
+#[derive(Debug)]
+pub struct TooLarge;
+
 impl<'p> Codegen<'p> {
-    pub fn new(prototypes: &'p HashMap<Symbol, ssa::Prototype>) -> Codegen {
+    /// Create a code generator whose instructions address at most `register_limit` registers,
+    /// which is clamped to `code::REGISTER_LIMIT`.
+    pub fn new(
+        prototypes: &'p HashMap<Symbol, ssa::Prototype>, register_limit: usize
+    ) -> Codegen<'p> {
+        let register_limit = cmp::min(register_limit, code::REGISTER_LIMIT);
         Codegen {
             function: code::Function::new(),
             locations: code::Locations::default(),
@@ -34,6 +48,8 @@ impl<'p> Codegen<'p> {
 
             registers: HandleMap::new(),
             register_count: 0,
+            register_limit,
+            spill_count: 0,
 
             visited: BitVec::new(),
             block_offsets: HashMap::new(),
@@ -41,27 +57,45 @@ impl<'p> Codegen<'p> {
             edge_block: 0,
 
             constants: HashMap::new(),
+
+            too_large: false,
         }
     }
 
-    pub fn compile(mut self, program: &ssa::Function) -> (code::Function, code::Locations) {
+    pub fn compile(
+        mut self, program: &ssa::Function
+    ) -> Result<(code::Function, code::Locations), TooLarge> {
         let control_flow = ControlFlow::compute(program);
         let liveness = Liveness::compute(program, &control_flow);
         let interference = Interference::build(program, &liveness);
-        let (registers, param_count, register_count) = interference.color();
+        let (registers, param_count, register_count, spill_count) = interference
+            .color(self.register_limit)
+            .ok_or(TooLarge)?;
 
         self.registers = registers;
         self.register_count = register_count;
+        self.spill_count = spill_count;
 
         self.edge_block = program.blocks.len();
 
         self.emit_blocks(program, ssa::ENTRY);
         self.fixup_jumps();
 
+        // Operands that did not fit in 16 bits were truncated, so the code cannot be used.
+        let operands = u16::MAX as usize;
+        if self.too_large ||
+            self.spill_count > operands + 1 ||
+            self.function.constants.len() > operands + 1 ||
+            self.function.instructions.len() > operands
+        {
+            return Err(TooLarge);
+        }
+
         self.function.params = param_count as u32;
         self.function.locals = self.register_count as u32;
+        self.function.spills = self.spill_count as u32;
 
-        (self.function, self.locations)
+        Ok((self.function, self.locations))
     }
 
     fn emit_blocks(&mut self, program: &ssa::Function, block: ssa::Label) {
@@ -85,8 +119,7 @@ impl<'p> Codegen<'p> {
             if let Unary { op: ssa::Opcode::Return, arg } = program.values[value] {
                 self.emit_phis(slice::from_ref(&program.return_def), slice::from_ref(&arg));
 
-                let inst = inst(code::Op::Ret).encode();
-                self.function.instructions.push(inst);
+                inst(code::Op::Ret).encode(&mut self.function.instructions);
 
                 continue;
             }
@@ -98,10 +131,10 @@ impl<'p> Codegen<'p> {
                     Some(&ssa::Prototype::Script { id }) => id as usize,
                     _ => self.emit_string(a),
                 };
+                self.too_large |= a > u16::MAX as usize;
                 let b = self.registers[parameters[0]];
                 let c = args.len();
-                let inst = inst(op).index(a).index(b).index(c).encode();
-                self.function.instructions.push(inst);
+                inst(op).index(a).index(b).index(c).encode(&mut self.function.instructions);
 
                 self.emit_phis(slice::from_ref(&value), &parameters[..1]);
                 continue;
@@ -123,11 +156,11 @@ impl<'p> Codegen<'p> {
                 let edge_block = ssa::Label::new(self.edge_block);
                 self.edge_block += 1;
 
-                self.jump_offsets.insert(self.function.instructions.len(), edge_block);
+                let a = self.use_register(args[0], 0);
+                inst(code::Op::BranchFalse).index(a).encode(&mut self.function.instructions);
 
-                let a = args[0];
-                let inst = inst(code::Op::BranchFalse).index(self.registers[a]).encode();
-                self.function.instructions.push(inst);
+                let offset = self.function.instructions.len() - 1;
+                self.jump_offsets.insert(offset, edge_block);
 
                 let true_start = 1;
                 let true_end = true_start + true_args as usize;
@@ -144,12 +177,20 @@ impl<'p> Codegen<'p> {
             let op = code::Op::from(program.op(value));
             let mut inst = inst(op);
 
+            // Spilled operands go through scratch registers, one for each field.
+            let mut spills = Vec::new();
             for def in program.defs(value) {
-                inst.index(self.registers[def]);
+                let location = self.registers[def];
+                let register = self.def_register(def, inst.filled - 1);
+                if register != location {
+                    spills.push((location, register));
+                }
+                inst.index(register);
             }
 
             for &arg in program.uses(value) {
-                inst.index(self.registers[arg]);
+                let register = self.use_register(arg, inst.filled - 1);
+                inst.index(register);
             }
 
             match program.values[value] {
@@ -168,7 +209,10 @@ impl<'p> Codegen<'p> {
                 _ => {}
             }
 
-            self.function.instructions.push(inst.encode());
+            inst.encode(&mut self.function.instructions);
+            for (location, register) in spills {
+                self.emit_move(location, register);
+            }
         }
     }
 
@@ -180,9 +224,7 @@ impl<'p> Codegen<'p> {
 
         if self.visited.get(target.index()) {
             self.jump_offsets.insert(self.function.instructions.len(), target);
-
-            let inst = inst(code::Op::Jump).encode();
-            self.function.instructions.push(inst);
+            inst(code::Op::Jump).encode(&mut self.function.instructions);
 
             return;
         }
@@ -239,8 +281,7 @@ impl<'p> Codegen<'p> {
             .collect();
         loop {
             while let Some((target, source)) = work.pop_front() {
-                let inst = inst(code::Op::Move).index(target).index(source).encode();
-                self.function.instructions.push(inst);
+                self.emit_move(target, source);
 
                 if let Entry::Occupied(mut entry) = uses.entry(source) {
                     *entry.get_mut() -= 1;
@@ -256,16 +297,14 @@ impl<'p> Codegen<'p> {
             }
 
             // TODO: move this logic to live range splitting
-            let temp = self.register_count;
-            self.register_count += 1;
+            let temp = self.temporary();
 
             // pick an arbitrary phi to break the cycle
             // there should only be one use left - a phi can't be in more than one cycle
             let (&used, &count) = uses.iter().nth(0).unwrap();
             assert_eq!(count, 1);
 
-            let inst = inst(code::Op::Move).index(temp).index(used).encode();
-            self.function.instructions.push(inst);
+            self.emit_move(temp, used);
 
             // TODO: track edges to make this quicker? there can only be one use by this point
             uses.remove(&used);
@@ -277,6 +316,60 @@ impl<'p> Codegen<'p> {
         }
     }
 
+    /// Move a value between locations, which may be registers or spill slots.
+    fn emit_move(&mut self, target: usize, source: usize) {
+        use crate::vm::code::Op::*;
+
+        let limit = self.register_limit;
+        let code = &mut self.function.instructions;
+        match (target < limit, source < limit) {
+            (true, true) => inst(Move).index(target).index(source).encode(code),
+            (true, false) => inst(Reload).index(target).wide_index(source - limit).encode(code),
+            (false, true) => inst(Spill).index(source).wide_index(target - limit).encode(code),
+            (false, false) => {
+                let scratch = limit - SCRATCH_REGISTERS;
+                inst(Reload).index(scratch).wide_index(source - limit).encode(code);
+                inst(Spill).index(scratch).wide_index(target - limit).encode(code);
+            }
+        }
+    }
+
+    /// The register to read `value` from, reloading it into a scratch register if it was spilled.
+    fn use_register(&mut self, value: ssa::Value, scratch: usize) -> usize {
+        let location = self.registers[value];
+        if location < self.register_limit {
+            return location;
+        }
+
+        let register = self.register_limit - SCRATCH_REGISTERS + scratch;
+        self.emit_move(register, location);
+        register
+    }
+
+    /// The register to write `value` to, which must then be spilled if it is a scratch register.
+    fn def_register(&self, value: ssa::Value, scratch: usize) -> usize {
+        let location = self.registers[value];
+        if location < self.register_limit {
+            return location;
+        }
+
+        self.register_limit - SCRATCH_REGISTERS + scratch
+    }
+
+    /// Allocate a new location for a value that is not in the interference graph.
+    fn temporary(&mut self) -> usize {
+        if self.register_count < self.register_limit - SCRATCH_REGISTERS {
+            let register = self.register_count;
+            self.register_count += 1;
+            register
+        } else {
+            let slot = self.spill_count;
+            self.spill_count += 1;
+            self.register_count = self.register_limit;
+            self.register_limit + slot
+        }
+    }
+
     fn emit_real(&mut self, real: f64) -> usize {
         let constant = vm::Value::from(real);
         self.emit_constant(constant)
@@ -302,14 +395,16 @@ impl<'p> Codegen<'p> {
                 (code::Op::Jump, 0, 0, 0) => {
                     let target = self.block_offsets[&block];
 
-                    let inst = inst(code::Op::Jump).wide_index(target).encode();
+                    let inst = inst(code::Op::Jump).wide_index(target).encode_narrow();
                     self.function.instructions[offset] = inst;
                 }
 
+                // Any `Wide` prefix is left in place, as the target fields never need it.
                 (code::Op::BranchFalse, cond, 0, 0) => {
                     let target = self.block_offsets[&block];
 
-                    let inst = inst(code::Op::BranchFalse).index(cond).wide_index(target).encode();
+                    let inst = inst(code::Op::BranchFalse).index(cond).wide_index(target)
+                        .encode_narrow();
                     self.function.instructions[offset] = inst;
                 }
 
@@ -320,30 +415,32 @@ impl<'p> Codegen<'p> {
 }
 
 struct InstBuilder {
-    fields: [u8; 4],
+    fields: [usize; 4],
     filled: usize,
 }
 
 fn inst(op: code::Op) -> InstBuilder {
     InstBuilder {
-        fields: [op as u8, 0, 0, 0],
+        fields: [op as usize, 0, 0, 0],
         filled: 1,
     }
 }
 
 impl InstBuilder {
+    /// Add a register or constant index, which may need a `Wide` prefix.
+    ///
+    /// Indices past 16 bits are truncated here, and rejected by `Codegen::compile`.
     fn index(&mut self, index: usize) -> &mut Self {
-        assert!(index <= u8::MAX as usize);
-        self.fields[self.filled] = index as u8;
+        self.fields[self.filled] = index;
         self.filled += 1;
         self
     }
 
+    /// Add a 16-bit operand that spans two fields, such as a jump target.
     fn wide_index(&mut self, index: usize) -> &mut Self {
-        assert!(index <= u16::MAX as usize);
-        self.fields[self.filled] = index as u8;
+        self.fields[self.filled] = index & 0xff;
         self.filled += 1;
-        self.fields[self.filled] = (index >> 8) as u8;
+        self.fields[self.filled] = index >> 8;
         self.filled += 1;
         self
     }
@@ -351,19 +448,34 @@ impl InstBuilder {
     fn scope(&mut self, scope: f64) -> &mut Self {
         assert!(scope <= i8::MAX as f64);
         assert!(scope >= i8::MIN as f64);
-        self.fields[self.filled] = scope as i8 as u8;
+        self.fields[self.filled] = scope as i8 as u8 as usize;
         self.filled += 1;
         self
     }
 
-    fn encode(&mut self) -> code::Inst {
-        code::Inst(
-            (self.fields[0] as u32) |
-            (self.fields[1] as u32) << 8 |
-            (self.fields[2] as u32) << 16 |
-            (self.fields[3] as u32) << 24
-        )
+    /// Append the instruction, preceded by a `Wide` prefix if any of its fields need one.
+    fn encode(&mut self, instructions: &mut Vec<code::Inst>) {
+        if self.fields[1..].iter().any(|&field| field > u8::MAX as usize) {
+            let [_, a, b, c] = self.fields;
+            instructions.push(encode([code::Op::Wide as usize, a >> 8, b >> 8, c >> 8]));
+        }
+        instructions.push(self.encode_narrow());
     }
+
+    /// Encode the instruction without its `Wide` prefix.
+    fn encode_narrow(&mut self) -> code::Inst {
+        let [op, a, b, c] = self.fields;
+        encode([op, a & 0xff, b & 0xff, c & 0xff])
+    }
+}
+
+fn encode(fields: [usize; 4]) -> code::Inst {
+    code::Inst(
+        (fields[0] as u32) |
+        (fields[1] as u32) << 8 |
+        (fields[2] as u32) << 16 |
+        (fields[3] as u32) << 24
+    )
 }
 
 impl From<ssa::Opcode> for code::Op {
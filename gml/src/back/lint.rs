@@ -0,0 +1,144 @@
+use std::collections::HashSet;
+
+use crate::ErrorPrinter;
+use crate::symbol::Symbol;
+use crate::front::Span;
+use crate::back::{ssa, analysis::*};
+
+/// What the front end knows about a function's source that its SSA does not record.
+#[derive(Default, Debug)]
+pub struct Syntax {
+    /// Each statement, in source order, and the block its code begins in.
+    pub statements: Vec<(ssa::Label, Span)>,
+    /// The first `var` declaration of each local variable.
+    pub locals: Vec<(Symbol, Span)>,
+    /// The comparison that selects each `case` of a `switch`, and the span of its label.
+    pub cases: Vec<(ssa::Value, Span)>,
+}
+
+/// Warn about likely mistakes in a function that still compiles.
+pub fn lint(program: &ssa::Function, syntax: &Syntax, errors: &mut ErrorPrinter<'_>) {
+    let control_flow = ControlFlow::compute(program);
+    let dominators = Dominators::compute(program, &control_flow);
+
+    check_reachable(&dominators, syntax, errors);
+    check_conditions(program, &dominators, errors);
+    check_cases(program, &dominators, syntax, errors);
+    check_locals(program, syntax, errors);
+}
+
+/// Warn about the first statement of each run of unreachable code.
+fn check_reachable(dominators: &Dominators, syntax: &Syntax, errors: &mut ErrorPrinter<'_>) {
+    let mut reachable = true;
+    for &(block, span) in &syntax.statements {
+        if !dominators.is_reachable(block) && reachable {
+            errors.warning(span, format_args!("unreachable code"));
+        }
+        reachable = dominators.is_reachable(block);
+    }
+}
+
+/// Warn about conditions like `a = b`, which compare in GML but usually mean a mistyped `==`.
+fn check_conditions(
+    program: &ssa::Function, dominators: &Dominators, errors: &mut ErrorPrinter<'_>
+) {
+    for block in program.blocks.keys() {
+        if !dominators.is_reachable(block) {
+            continue;
+        }
+        let condition = match branch(program, block) {
+            Some((condition, _)) => condition,
+            None => continue,
+        };
+        if let ssa::Instruction::Binary { op: ssa::Opcode::Eq, .. } = program.values[condition] {
+            // `=`, `:=` and `==` all compile to the same operator, so look at how it was written.
+            let low = program.locations[condition];
+            let source = &errors.lines.source;
+            if source.get(low) == Some(&b'=') && source.get(low + 1) != Some(&b'=') {
+                errors.warning(Span { low, high: low + 1 },
+                    format_args!("assignment used as a comparison; did you mean `==`?"));
+            }
+        }
+    }
+}
+
+/// Warn about `case` labels with the same constant value as an earlier one in the `switch`.
+///
+/// Each `case` is only compared when the ones before it fail. A comparison duplicates an earlier
+/// one when it tests the same value against the same constant, and the failure of the earlier one
+/// dominates it.
+fn check_cases(
+    program: &ssa::Function, dominators: &Dominators, syntax: &Syntax,
+    errors: &mut ErrorPrinter<'_>
+) {
+    let mut cases = Vec::with_capacity(syntax.cases.len());
+    for block in program.blocks.keys() {
+        let (condition, [_, next]) = match branch(program, block) {
+            Some(branch) => branch,
+            None => continue,
+        };
+        let span = match syntax.cases.iter().find(|&&(value, _)| value == condition) {
+            Some(&(_, span)) => span,
+            None => continue,
+        };
+        let (switch, label) = match program.values[condition] {
+            ssa::Instruction::Binary { op: ssa::Opcode::Eq, args: [switch, label] } =>
+                (switch, label),
+            _ => continue,
+        };
+        if let Some(label) = constant(program, label) {
+            cases.push((block, next, switch, label, span));
+        }
+    }
+    cases.sort_by_key(|&(.., span)| span);
+
+    for &(block, _, switch, label, span) in &cases {
+        let duplicate = cases.iter().any(|&(earlier, next, earlier_switch, earlier_label, _)| {
+            earlier != block && earlier_switch == switch && earlier_label == label &&
+                dominators.dominates(next, block)
+        });
+        if duplicate {
+            errors.warning(span, format_args!("duplicate case label"));
+        }
+    }
+}
+
+/// Warn about local variables that are declared but never read.
+fn check_locals(program: &ssa::Function, syntax: &Syntax, errors: &mut ErrorPrinter<'_>) {
+    let read: HashSet<_> = program.blocks.keys()
+        .flat_map(|block| program.blocks[block].instructions.iter())
+        .filter_map(|&value| match program.values[value] {
+            ssa::Instruction::BinarySymbol { op: ssa::Opcode::Read, symbol, .. } => Some(symbol),
+            _ => None,
+        })
+        .collect();
+    for &(symbol, span) in &syntax.locals {
+        if !read.contains(&symbol) {
+            errors.warning(span, format_args!("unused local variable {}", symbol));
+        }
+    }
+}
+
+/// The condition and targets of the branch that ends a block, if it ends in one.
+fn branch(program: &ssa::Function, block: ssa::Label) -> Option<(ssa::Value, [ssa::Label; 2])> {
+    let &terminator = program.blocks[block].instructions.last()?;
+    match program.values[terminator] {
+        ssa::Instruction::Branch { targets, ref args, .. } => Some((args[0], targets)),
+        _ => None,
+    }
+}
+
+/// The value of a constant or a negated real constant.
+fn constant(program: &ssa::Function, value: ssa::Value) -> Option<ssa::Constant> {
+    match program.values[value] {
+        ssa::Instruction::UnaryReal { op: ssa::Opcode::Constant, real } =>
+            Some(ssa::Constant::Real(real)),
+        ssa::Instruction::UnarySymbol { op: ssa::Opcode::Constant, symbol } =>
+            Some(ssa::Constant::String(symbol)),
+        ssa::Instruction::Unary { op: ssa::Opcode::Negate, arg } => match constant(program, arg)? {
+            ssa::Constant::Real(real) => Some(ssa::Constant::Real(-real)),
+            ssa::Constant::String(_) => None,
+        },
+        _ => None,
+    }
+}
@@ -1,8 +1,12 @@
-pub use crate::back::codegen::Codegen;
+pub use crate::back::codegen::{Codegen, TooLarge};
 pub use crate::back::analysis::ControlFlow;
+pub use crate::back::optimize::PassManager;
 
 pub mod ssa;
+pub mod verify;
+pub mod lint;
 
 mod analysis;
+mod optimize;
 mod regalloc;
 mod codegen;
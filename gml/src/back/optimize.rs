@@ -0,0 +1,464 @@
+use std::mem;
+use std::collections::{HashMap, HashSet};
+
+use crate::bit_vec::BitVec;
+use crate::handle_map::{Handle, HandleMap};
+use crate::symbol::Symbol;
+use crate::back::{ssa, analysis::*};
+use crate::vm::{self, to_bool, to_i32};
+
+/// A transformation of an SSA function. Returns whether it changed anything.
+pub type Pass = fn(&mut ssa::Function) -> bool;
+
+/// An ordered list of passes, run repeatedly until none of them makes further progress.
+#[derive(Default)]
+pub struct PassManager {
+    passes: Vec<(&'static str, Pass)>,
+}
+
+impl PassManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The default optimization pipeline.
+    pub fn standard() -> Self {
+        let mut passes = Self::new();
+        passes.add("fold-constants", fold_constants);
+        passes.add("prune-unreachable", prune_unreachable);
+        passes.add("propagate-copies", propagate_copies);
+        passes.add("remove-aliases", remove_aliases);
+        passes.add("eliminate-dead-code", eliminate_dead_code);
+        passes
+    }
+
+    pub fn add(&mut self, name: &'static str, pass: Pass) {
+        self.passes.push((name, pass));
+    }
+
+    /// Run the passes over a function until it reaches a fixed point.
+    ///
+    /// Because `remove-aliases` only reports progress when it finds an alias to remove, a function
+    /// leaves the standard pipeline without any uses of `Alias` values.
+    ///
+    /// `inspect` is called with the name of each pass that changes the function, and its result.
+    pub fn run<F>(&self, function: &mut ssa::Function, mut inspect: F) where
+        F: FnMut(&'static str, &ssa::Function)
+    {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &(name, pass) in &self.passes {
+                if pass(function) {
+                    inspect(name, function);
+                    changed = true;
+                }
+            }
+        }
+    }
+}
+
+/// Evaluate instructions whose operands are all constants.
+///
+/// Folding never changes behavior, so operations that would fail at runtime (mismatched types,
+/// division by zero, out-of-range shifts) are left in place to report their errors. Results are
+/// written over the folded instruction rather than shared with an existing constant: the VM moves
+/// values out of registers across block edges, so a value must not gain new uses elsewhere.
+///
+/// Branches on a constant condition become jumps, leaving their other target to
+/// `prune_unreachable`.
+pub fn fold_constants(function: &mut ssa::Function) -> bool {
+    let mut changed = false;
+    for block in function.blocks.keys() {
+        for i in 0..function.blocks[block].instructions.len() {
+            let value = function.blocks[block].instructions[i];
+            if let Some(instruction) = fold(function, value) {
+                function.values[value] = instruction;
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+fn fold(function: &ssa::Function, value: ssa::Value) -> Option<ssa::Instruction> {
+    use crate::back::ssa::Instruction::*;
+
+    match function.values[value] {
+        Unary { op, arg } => {
+            let a = constant(function, arg)?;
+            instruction(fold_unary(op, a.borrow())?)
+        }
+
+        Binary { op, args: [a, b] } => {
+            let a = constant(function, a)?;
+            let b = constant(function, b)?;
+            instruction(fold_binary(op, a.borrow(), b.borrow())?)
+        }
+
+        Branch { targets: [true_block, false_block], arg_lens: [true_args, _], ref args, .. } => {
+            let condition = match constant(function, args[0])?.borrow().decode() {
+                vm::Data::Real(condition) => to_bool(condition),
+                _ => return None,
+            };
+
+            let true_end = 1 + true_args as usize;
+            let (target, args) = if condition {
+                (true_block, args[1..true_end].to_vec())
+            } else {
+                (false_block, args[true_end..].to_vec())
+            };
+            Some(Jump { op: ssa::Opcode::Jump, target, args })
+        }
+
+        _ => None,
+    }
+}
+
+/// Mirrors the interpreter's unary operations on scalars.
+fn fold_unary(op: ssa::Opcode, a: vm::ValueRef<'_>) -> Option<vm::Value> {
+    use crate::vm::Data::*;
+    use crate::back::ssa::Opcode::*;
+
+    let value = match (op, a.decode()) {
+        (Negate, Real(a)) => vm::Value::from(-a),
+        (Invert, Real(a)) => vm::Value::from(!to_bool(a)),
+        (BitInvert, Real(a)) => vm::Value::from(!to_i32(a)),
+        (ToScalar, Real(_)) | (ToScalar, String(_)) => a.clone(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Mirrors the interpreter's binary operations on scalars.
+fn fold_binary(op: ssa::Opcode, a: vm::ValueRef<'_>, b: vm::ValueRef<'_>) -> Option<vm::Value> {
+    use crate::vm::Data::*;
+    use crate::back::ssa::Opcode::*;
+
+    let value = match (op, a.decode(), b.decode()) {
+        (Lt, Real(a), Real(b)) => vm::Value::from(a < b),
+        (Lt, String(a), String(b)) => vm::Value::from(a < b),
+        (Le, Real(a), Real(b)) => vm::Value::from(a <= b),
+        (Le, String(a), String(b)) => vm::Value::from(a <= b),
+        (Eq, _, _) => vm::Value::from(a == b),
+        (Ne, _, _) => vm::Value::from(a != b),
+        (Ge, Real(a), Real(b)) => vm::Value::from(a >= b),
+        (Ge, String(a), String(b)) => vm::Value::from(a >= b),
+        (Gt, Real(a), Real(b)) => vm::Value::from(a > b),
+        (Gt, String(a), String(b)) => vm::Value::from(a > b),
+
+        (Add, Real(a), Real(b)) => vm::Value::from(a + b),
+        (Add, String(a), String(b)) => vm::Value::from(Symbol::intern(&[a, b].concat())),
+        (Subtract, Real(a), Real(b)) => vm::Value::from(a - b),
+        (Multiply, Real(a), Real(b)) => vm::Value::from(a * b),
+        (Divide, Real(a), Real(b)) if b != 0.0 => vm::Value::from(a / b),
+        (Div, Real(a), Real(b)) if b != 0.0 => vm::Value::from(to_i32(a / b)),
+        (Mod, Real(a), Real(b)) if b != 0.0 => vm::Value::from(a % b),
+
+        (And, Real(a), Real(b)) => vm::Value::from(to_bool(a) && to_bool(b)),
+        (Or, Real(a), Real(b)) => vm::Value::from(to_bool(a) || to_bool(b)),
+        (Xor, Real(a), Real(b)) => vm::Value::from(to_bool(a) != to_bool(b)),
+
+        (BitAnd, Real(a), Real(b)) => vm::Value::from(to_i32(a) & to_i32(b)),
+        (BitOr, Real(a), Real(b)) => vm::Value::from(to_i32(a) | to_i32(b)),
+        (BitXor, Real(a), Real(b)) => vm::Value::from(to_i32(a) ^ to_i32(b)),
+        (ShiftLeft, Real(a), Real(b)) =>
+            vm::Value::from(to_i32(a).checked_shl(to_i32(b) as u32)?),
+        (ShiftRight, Real(a), Real(b)) =>
+            vm::Value::from(to_i32(a).checked_shr(to_i32(b) as u32)?),
+
+        // Writing over a scalar replaces it entirely.
+        (Write, _, Real(_)) | (Write, _, String(_)) => a.clone(),
+
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Remove basic blocks that cannot be reached from the entry block.
+///
+/// The remaining blocks are renumbered in their original order. This may remove `ssa::EXIT` when
+/// nothing jumps to it, as after an infinite loop.
+pub fn prune_unreachable(function: &mut ssa::Function) -> bool {
+    let mut reachable = BitVec::new();
+    let mut work = vec![ssa::ENTRY];
+    reachable.set(ssa::ENTRY.index());
+    while let Some(block) = work.pop() {
+        for &succ in function.successors(block) {
+            if !reachable.set(succ.index()) {
+                work.push(succ);
+            }
+        }
+    }
+    if function.blocks.keys().all(|block| reachable.get(block.index())) {
+        return false;
+    }
+
+    let mut labels = HashMap::new();
+    let mut blocks = HandleMap::new();
+    for block in function.blocks.keys().filter(|block| reachable.get(block.index())) {
+        let parameters = mem::take(&mut function.blocks[block].parameters);
+        let instructions = mem::take(&mut function.blocks[block].instructions);
+        labels.insert(block, blocks.push(ssa::Block { parameters, instructions }));
+    }
+    function.blocks = blocks;
+
+    for block in function.blocks.keys() {
+        let terminator = function.terminator(block);
+        match function.values[terminator] {
+            ssa::Instruction::Jump { ref mut target, .. } => {
+                *target = labels[&*target];
+            }
+            ssa::Instruction::Branch { ref mut targets, .. } => {
+                for target in targets {
+                    *target = labels[&*target];
+                }
+            }
+            _ => {}
+        }
+    }
+
+    true
+}
+
+/// Replace block parameters that always receive the same value with that value.
+///
+/// Parameters of the entry block are the function's arguments, and are left alone. Uses of the
+/// removed parameters are forwarded through `Alias`es.
+pub fn propagate_copies(function: &mut ssa::Function) -> bool {
+    let control_flow = ControlFlow::compute(function);
+
+    let mut changed = false;
+    for block in function.blocks.keys().filter(|&block| block != ssa::ENTRY) {
+        let preds = predecessors(&control_flow, block);
+
+        let mut i = 0;
+        while i < function.blocks[block].parameters.len() {
+            let parameter = function.blocks[block].parameters[i];
+
+            // Arguments from loop back edges may be the parameter itself.
+            let mut unique = None;
+            let copy = preds.iter()
+                .flat_map(|&pred| arguments(function, pred, block, i))
+                .map(|arg| resolve(function, arg))
+                .filter(|&arg| arg != parameter)
+                .all(|arg| *unique.get_or_insert(arg) == arg);
+
+            match unique {
+                Some(arg) if copy => {
+                    remove_parameter(function, &preds, block, i);
+                    function.values[parameter] = ssa::Instruction::Alias { arg };
+                    changed = true;
+                }
+                _ => i += 1,
+            }
+        }
+    }
+
+    changed
+}
+
+/// Update uses of `Alias` values to refer to their targets.
+pub fn remove_aliases(function: &mut ssa::Function) -> bool {
+    let mut changed = false;
+    for block in function.blocks.keys() {
+        for i in 0..function.blocks[block].instructions.len() {
+            let value = function.blocks[block].instructions[i];
+            for j in 0..function.uses(value).len() {
+                let arg = function.uses(value)[j];
+                let resolved = resolve(function, arg);
+                if arg != resolved {
+                    function.uses_mut(value)[j] = resolved;
+                    changed = true;
+                }
+            }
+        }
+    }
+    changed
+}
+
+/// Remove side-effect-free instructions and block parameters whose results are never used.
+///
+/// Liveness starts from the operands of instructions with side effects and branch conditions. It
+/// flows from live block parameters to their arguments, so parameters that only feed each other
+/// around a loop are removed as well.
+pub fn eliminate_dead_code(function: &mut ssa::Function) -> bool {
+    let control_flow = ControlFlow::compute(function);
+
+    let mut parameters = HashMap::new();
+    for block in function.blocks.keys().filter(|&block| block != ssa::ENTRY) {
+        for (index, &parameter) in function.blocks[block].parameters.iter().enumerate() {
+            parameters.insert(parameter, (block, index));
+        }
+    }
+
+    let mut work = Vec::new();
+    for block in function.blocks.keys() {
+        for &value in &function.blocks[block].instructions {
+            match function.values[value] {
+                ssa::Instruction::Jump { .. } => {}
+                ssa::Instruction::Branch { ref args, .. } => work.push(args[0]),
+                _ if is_pure(function, value) => {}
+                _ => work.extend(function.uses(value)),
+            }
+        }
+    }
+
+    let mut live = HashSet::new();
+    while let Some(value) = work.pop() {
+        let value = resolve(function, value);
+        if !live.insert(value) {
+            continue;
+        }
+
+        if let Some(&(block, index)) = parameters.get(&value) {
+            for pred in predecessors(&control_flow, block) {
+                work.extend(arguments(function, pred, block, index));
+            }
+        } else if is_pure(function, value) {
+            work.extend(function.uses(value));
+        }
+    }
+
+    let mut changed = false;
+    for block in function.blocks.keys() {
+        if block != ssa::ENTRY {
+            let preds = predecessors(&control_flow, block);
+            for i in (0..function.blocks[block].parameters.len()).rev() {
+                let parameter = function.blocks[block].parameters[i];
+                if !live.contains(&parameter) {
+                    remove_parameter(function, &preds, block, i);
+                    changed = true;
+                }
+            }
+        }
+
+        let mut instructions = mem::take(&mut function.blocks[block].instructions);
+        let len = instructions.len();
+        instructions.retain(|&value| !is_pure(function, value) || live.contains(&value));
+        changed |= instructions.len() != len;
+        function.blocks[block].instructions = instructions;
+    }
+
+    changed
+}
+
+/// Whether an instruction can be removed when its results are unused.
+fn is_pure(function: &ssa::Function, value: ssa::Value) -> bool {
+    use crate::back::ssa::Instruction::*;
+    use crate::back::ssa::Opcode::*;
+
+    match function.values[value] {
+        UnaryReal { op: Constant, .. } | UnarySymbol { op: Constant, .. } => true,
+        UnarySymbol { op: Lookup, .. } => true,
+        UnaryReal { op: LoadScope, real } =>
+            [vm::SELF, vm::OTHER, vm::GLOBAL].contains(&to_i32(real)),
+        Binary { op: Eq, .. } | Binary { op: Ne, .. } => true,
+
+        // Reading a local that is known to be initialized has no effect.
+        BinarySymbol { op: Read, arg, .. } => match constant(function, arg) {
+            Some(flag) => matches!(flag.borrow().decode(), vm::Data::Real(flag) if to_bool(flag)),
+            None => false,
+        },
+
+        _ => false,
+    }
+}
+
+/// Follow a chain of `Alias`es to the value it refers to.
+fn resolve(function: &ssa::Function, mut value: ssa::Value) -> ssa::Value {
+    while let ssa::Instruction::Alias { arg } = function.values[value] {
+        value = arg;
+    }
+    value
+}
+
+/// The value of a `Constant` instruction.
+fn constant(function: &ssa::Function, value: ssa::Value) -> Option<vm::Value> {
+    match function.values[resolve(function, value)] {
+        ssa::Instruction::UnaryReal { op: ssa::Opcode::Constant, real } =>
+            Some(vm::Value::from(real)),
+        ssa::Instruction::UnarySymbol { op: ssa::Opcode::Constant, symbol } =>
+            Some(vm::Value::from(symbol)),
+        _ => None,
+    }
+}
+
+/// A `Constant` instruction producing a folded value.
+fn instruction(value: vm::Value) -> Option<ssa::Instruction> {
+    let op = ssa::Opcode::Constant;
+    match value.borrow().decode() {
+        vm::Data::Real(real) => Some(ssa::Instruction::UnaryReal { op, real }),
+        vm::Data::String(symbol) => Some(ssa::Instruction::UnarySymbol { op, symbol }),
+        vm::Data::Array(_) => None,
+    }
+}
+
+/// The distinct predecessors of a block.
+///
+/// A branch with both targets in the same block is recorded as two adjacent edges.
+fn predecessors(control_flow: &ControlFlow, block: ssa::Label) -> Vec<ssa::Label> {
+    let mut preds = control_flow.pred.get(block).cloned().unwrap_or_default();
+    preds.dedup();
+    preds
+}
+
+/// The arguments `pred` passes to parameter `index` of `block`, one for each edge between them.
+fn arguments(
+    function: &ssa::Function, pred: ssa::Label, block: ssa::Label, index: usize
+) -> Vec<ssa::Value> {
+    let terminator = function.terminator(pred);
+    match function.values[terminator] {
+        ssa::Instruction::Jump { target, ref args, .. } if target == block => vec![args[index]],
+        ssa::Instruction::Branch {
+            targets: [true_block, false_block],
+            arg_lens: [true_args, _],
+            ref args,
+            ..
+        } => {
+            let mut arguments = Vec::with_capacity(2);
+            if true_block == block {
+                arguments.push(args[1 + index]);
+            }
+            if false_block == block {
+                arguments.push(args[1 + true_args as usize + index]);
+            }
+            arguments
+        }
+        _ => vec![],
+    }
+}
+
+/// Remove parameter `index` of `block` along with the arguments its predecessors pass to it.
+fn remove_parameter(
+    function: &mut ssa::Function, preds: &[ssa::Label], block: ssa::Label, index: usize
+) {
+    function.blocks[block].parameters.remove(index);
+    for &pred in preds {
+        let terminator = function.terminator(pred);
+        match function.values[terminator] {
+            ssa::Instruction::Jump { ref mut args, .. } => {
+                args.remove(index);
+            }
+
+            // Remove the false edge's argument first, so the true edge's index stays valid.
+            ssa::Instruction::Branch {
+                targets: [true_block, false_block],
+                arg_lens: [ref mut true_args, ref mut false_args],
+                ref mut args,
+                ..
+            } => {
+                if false_block == block {
+                    args.remove(1 + *true_args as usize + index);
+                    *false_args -= 1;
+                }
+                if true_block == block {
+                    args.remove(1 + index);
+                    *true_args -= 1;
+                }
+            }
+
+            _ => unreachable!("corrupt function"),
+        }
+    }
+}
@@ -4,6 +4,9 @@ use std::collections::HashSet;
 use crate::handle_map::HandleMap;
 use crate::back::{ssa, analysis::*};
 
+/// The number of registers kept free for reloading spilled values, one per instruction operand.
+pub const SCRATCH_REGISTERS: usize = 3;
+
 /// A value interference graph.
 ///
 /// Used values are nodes in the graph, and values that are live simultaneously share an edge. This
@@ -98,7 +101,18 @@ impl Interference {
     ///
     /// It also precolors program parameters and call arguments to match the VM's calling
     /// convention, with parameters at the start of the frame and arguments at the end.
-    pub fn color(self) -> (HandleMap<ssa::Value, usize>, usize, usize) {
+    ///
+    /// Instructions can only address `limit` registers. When the coloring needs more than that,
+    /// the highest colors are spilled: their values are assigned the locations `limit + slot`,
+    /// and the top `SCRATCH_REGISTERS` registers are left free for reloading them. Call arguments
+    /// are never spilled, so room is also left for the largest argument list.
+    ///
+    /// Returns the locations of the values, the number of parameters, the number of registers,
+    /// and the number of spill slots, or `None` if the parameters or the largest argument list do
+    /// not fit in `limit` registers.
+    pub fn color(
+        self, limit: usize
+    ) -> Option<(HandleMap<ssa::Value, usize>, usize, usize, usize)> {
         let mut colors = HandleMap::with_capacity(self.adjacency.len());
         for &value in Iterator::chain(self.vertices.iter(), self.precolored.iter()) {
             colors[value] = usize::max_value();
@@ -107,9 +121,18 @@ impl Interference {
         let mut color_count;
         let param_count;
 
+        let arguments = self.groups[1..].windows(2)
+            .map(|group| group[1] - group[0])
+            .max()
+            .unwrap_or(0);
+        let available = limit.checked_sub(SCRATCH_REGISTERS + arguments)?;
+
         // Program parameters must be in order at the start of the stack frame.
         let (start, end) = (self.groups[0], self.groups[1]);
         let parameters = &self.precolored[start..end];
+        if parameters.len() > available {
+            return None;
+        }
         for (color, &value) in Iterator::zip(0.., parameters) {
             colors[value] = color;
         }
@@ -117,6 +140,7 @@ impl Interference {
         param_count = color_count;
 
         // Regular values are allocated greedily in perfect/simplical elimination order
+        let mut spilled = Vec::new();
         for value in Self::perfect_elimination_order(&self.adjacency, self.vertices, &self.precolored) {
             let neighbors: HashSet<_> = self.adjacency[value].iter()
                 .map(|&neighbor| colors[neighbor])
@@ -128,7 +152,18 @@ impl Interference {
 
             colors[value] = color;
             color_count = cmp::max(color_count, color + 1);
+
+            if color >= available {
+                spilled.push(value);
+            }
+        }
+
+        // Values that share a color do not interfere, so they can also share a spill slot.
+        let spill_count = color_count.saturating_sub(available);
+        for value in spilled {
+            colors[value] = limit + (colors[value] - available);
         }
+        color_count = cmp::min(color_count, available);
 
         // Call arguments must be in order at the end of the (live) stack frame.
         for group in self.groups[1..].windows(2) {
@@ -137,6 +172,7 @@ impl Interference {
 
             let neighbors: HashSet<_> = self.adjacency[arguments[0]].iter()
                 .map(|&neighbor| colors[neighbor])
+                .filter(|&color| color < limit)
                 .collect();
             let color = (0..color_count).rev()
                 .take_while(|&color| !neighbors.contains(&color))
@@ -149,7 +185,11 @@ impl Interference {
             color_count = cmp::max(color_count, color + arguments.len());
         }
 
-        (colors, param_count, color_count)
+        if spill_count > 0 {
+            color_count = limit;
+        }
+
+        Some((colors, param_count, color_count, spill_count))
     }
 
     /// Computes a chordal graph's perfect elimination order using maximum cardinality search.
@@ -1,4 +1,5 @@
-use std::{u32, slice, fmt, ops::Range};
+use std::{u32, cmp, slice, fmt, ops::Range};
+use std::collections::HashMap;
 
 use crate::handle_map::{Handle, HandleMap};
 use crate::symbol::Symbol;
@@ -190,7 +191,7 @@ pub enum Opcode {
 ///
 /// This contains just enough information to generate code for the caller.
 // TODO: gms tracks function arity
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub enum Prototype {
     /// A GML script.
     Script { id: i32 },
@@ -198,6 +199,17 @@ pub enum Prototype {
     Native { arity: usize, variadic: bool },
     /// A built-in member accessed via getter and setter.
     Member,
+    /// A named constant, such as a resource index, folded into an immediate.
+    Constant(Constant),
+    /// A GMS enum, whose members are folded into immediates like constants.
+    Enum(HashMap<Symbol, f64>),
+}
+
+/// The value of a named constant.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Constant {
+    Real(f64),
+    String(Symbol),
 }
 
 impl Function {
@@ -405,70 +417,124 @@ impl ExactSizeIterator for ValueRange {
     }
 }
 
+/// Prints a function with values named by their indices.
 impl fmt::Debug for Function {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, &|value| Some(value.index()))
+    }
+}
+
+/// Prints a function in a stable textual form, suitable for snapshot tests.
+///
+/// Values are renumbered in order of definition, so the output depends only on the function's
+/// structure and not on the order its values were allocated in. Uses of values that are not
+/// defined in any block are printed as `undef`.
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use self::Instruction::*;
+
+        let mut names = HashMap::new();
+        for block in self.blocks.keys() {
+            let block = &self.blocks[block];
+            let instructions = block.instructions.iter()
+                .filter(|&&value| {
+                    !matches!(self.values[value], Alias { .. } | Project { .. } | Parameter)
+                })
+                .flat_map(|&value| self.defs(value));
+            for value in block.parameters.iter().cloned().chain(instructions) {
+                let name = names.len();
+                names.entry(value).or_insert(name);
+            }
+        }
+
+        self.write(f, &|value| names.get(&value).cloned())
+    }
+}
+
+impl Function {
+    fn write(
+        &self, f: &mut fmt::Formatter<'_>, name: &dyn Fn(Value) -> Option<usize>
+    ) -> fmt::Result {
+        use self::Instruction::*;
+
         for block in self.blocks.keys() {
             write!(f, "b{}(", block.index())?;
-            write_values(f, self.blocks[block].parameters.iter().cloned())?;
+            write_values(f, name, &self.blocks[block].parameters)?;
             writeln!(f, "):")?;
 
             for &value in &self.blocks[block].instructions {
-                use crate::back::ssa::Instruction::*;
-
                 write!(f, "    ")?;
 
-                let defs = self.defs(value);
-                write_values(f, defs.clone())?;
-                if defs.len() > 0 {
-                    write!(f, " <- ")?;
-                }
-
-                write!(f, "{:?} ", self.op(value))?;
-
-                if let Call { symbol, ref args, .. } = self.values[value] {
-                    write!(f, "{}(", symbol)?;
-                    write_values(f, args.iter().cloned())?;
-                    writeln!(f, ")")?;
+                // These only appear in corrupt functions, which are printed to report errors.
+                if let Alias { .. } | Project { .. } | Parameter = self.values[value] {
+                    writeln!(f, "{:?}", self.values[value])?;
                     continue;
                 }
-                if let Jump { target, ref args, .. } = self.values[value] {
-                    write!(f, "b{}(", target.index())?;
-                    write_values(f, args.iter().cloned())?;
-                    writeln!(f, ")")?;
-                    continue;
-                }
-                if let Branch {
-                    targets: [true_block, false_block],
-                    arg_lens: [true_args, false_args],
-                    ref args,
-                    ..
-                } = self.values[value] {
-                    write!(f, "v{}", args[0].index())?;
-
-                    let true_start = 1;
-                    let true_end = true_start + true_args as usize;
-                    write!(f, ", b{}(", true_block.index())?;
-                    write_values(f, args[true_start..true_end].iter().cloned())?;
-                    write!(f, ")")?;
-
-                    let false_start = true_end;
-                    let false_end = false_start + false_args as usize;
-                    write!(f, ", b{}(", false_block.index())?;
-                    write_values(f, args[false_start..false_end].iter().cloned())?;
-                    write!(f, ")")?;
-
-                    writeln!(f)?;
-                    continue;
+
+                let defs: Vec<_> = self.defs(value).collect();
+                if !defs.is_empty() {
+                    write_values(f, name, &defs)?;
+                    write!(f, " = ")?;
                 }
+                write!(f, "{:?}", self.op(value))?;
 
-                write_values(f, self.uses(value).iter().cloned())?;
                 match self.values[value] {
-                    UnaryReal { real, .. } => write!(f, "{}", real)?,
-                    UnarySymbol { symbol, .. } => write!(f, "{}", symbol)?,
-                    BinaryReal { real, .. } => write!(f, ", {}", real)?,
-                    BinarySymbol { symbol, .. } => write!(f, ", {}", symbol)?,
-                    TernarySymbol { symbol, .. } => write!(f, ", {}", symbol)?,
-                    _ => (),
+                    Call { symbol, ref args, .. } => {
+                        write!(f, " {}(", symbol)?;
+                        write_values(f, name, args)?;
+                        write!(f, ")")?;
+                    }
+
+                    Jump { target, ref args, .. } => {
+                        write!(f, " ")?;
+                        write_edge(f, name, target, args)?;
+                    }
+
+                    Branch {
+                        targets: [true_block, false_block],
+                        arg_lens: [true_args, false_args],
+                        ref args,
+                        ..
+                    } => {
+                        // Clamp the argument ranges so malformed branches can still be printed.
+                        let true_start = cmp::min(1, args.len());
+                        let true_end = cmp::min(true_start + true_args as usize, args.len());
+                        let false_end = cmp::min(true_end + false_args as usize, args.len());
+
+                        write!(f, " ")?;
+                        write_values(f, name, &args[..true_start])?;
+                        write!(f, ", ")?;
+                        write_edge(f, name, true_block, &args[true_start..true_end])?;
+                        write!(f, ", ")?;
+                        write_edge(f, name, false_block, &args[true_end..false_end])?;
+                        if false_end < args.len() {
+                            write!(f, ", ")?;
+                            write_values(f, name, &args[false_end..])?;
+                        }
+                    }
+
+                    _ => {
+                        let uses = self.uses(value);
+                        if !uses.is_empty() {
+                            write!(f, " ")?;
+                            write_values(f, name, uses)?;
+                        }
+
+                        let separator = if uses.is_empty() { " " } else { ", " };
+                        match self.values[value] {
+                            UnaryReal { real, .. } |
+                            BinaryReal { real, .. } => write!(f, "{}{:?}", separator, real)?,
+
+                            UnarySymbol { symbol, .. } |
+                            BinarySymbol { symbol, .. } |
+                            TernarySymbol { symbol, .. } => {
+                                let symbol = String::from_utf8_lossy(&symbol);
+                                write!(f, "{}{:?}", separator, symbol)?
+                            }
+
+                            _ => (),
+                        }
+                    }
                 }
                 writeln!(f)?;
             }
@@ -478,16 +544,78 @@ impl fmt::Debug for Function {
     }
 }
 
-fn write_values<I>(f: &mut fmt::Formatter<'_>, values: I) -> fmt::Result where
-    I: IntoIterator<Item = Value>
-{
-    let mut has_values = false;
-    for value in values {
-        if has_values {
+fn write_edge(
+    f: &mut fmt::Formatter<'_>, name: &dyn Fn(Value) -> Option<usize>,
+    target: Label, args: &[Value]
+) -> fmt::Result {
+    write!(f, "b{}(", target.index())?;
+    write_values(f, name, args)?;
+    write!(f, ")")
+}
+
+fn write_values(
+    f: &mut fmt::Formatter<'_>, name: &dyn Fn(Value) -> Option<usize>, values: &[Value]
+) -> fmt::Result {
+    for (i, &value) in values.iter().enumerate() {
+        if i > 0 {
             write!(f, ", ")?;
         }
-        write!(f, "v{}", value.index())?;
-        has_values = true;
+        match name(value) {
+            Some(index) => write!(f, "v{}", index)?,
+            None => write!(f, "undef")?,
+        }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::Instruction::*;
+
+    /// Print values numbered in definition order, regardless of allocation order.
+    #[test]
+    fn display() {
+        let mut program = Function::new();
+        let body = program.make_block();
+
+        let op = Opcode::Constant;
+        let string = Symbol::intern(b"say \"hi\"");
+        let string = UnarySymbol { op, symbol: string };
+        let x = program.emit_parameter(body);
+        let string = program.emit_instruction(ENTRY, string, 0);
+        let one = program.emit_instruction(ENTRY, UnaryReal { op, real: 1.0 }, 0);
+        let branch = Branch {
+            op: Opcode::Branch,
+            targets: [body, EXIT],
+            arg_lens: [1, 0],
+            args: vec![one, one],
+        };
+        program.emit_instruction(ENTRY, branch, 0);
+
+        let symbol = Symbol::intern(b"a");
+        program.emit_instruction(body, BinarySymbol { op: Opcode::Read, arg: one, symbol }, 0);
+        let add = program.emit_instruction(body, Binary { op: Opcode::Add, args: [x, one] }, 0);
+        let symbol = Symbol::intern(b"show");
+        let args = vec![add, string];
+        let call = Call { op: Opcode::CallApi, symbol, args, parameters: vec![] };
+        program.emit_instruction(body, call, 0);
+        program.emit_instruction(body, Jump { op: Opcode::Jump, target: EXIT, args: vec![] }, 0);
+
+        program.emit_instruction(EXIT, Unary { op: Opcode::Return, arg: string }, 0);
+
+        assert_eq!(program.to_string(), "\
+b0():
+    v0 = Constant \"say \\\"hi\\\"\"
+    v1 = Constant 1.0
+    Branch v1, b2(v1), b1()
+b1():
+    Return v0
+b2(v2):
+    Read v1, \"a\"
+    v3 = Add v2, v1
+    v4 = CallApi show(v3, v0)
+    Jump b1()
+");
+    }
+}
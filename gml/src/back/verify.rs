@@ -0,0 +1,385 @@
+use std::fmt;
+use std::collections::HashMap;
+
+use crate::handle_map::Handle;
+use crate::back::{ssa, analysis::*};
+
+/// A violation of the SSA form's invariants.
+///
+/// `block` and `value` locate the offending instruction by index, matching the `Debug` output of
+/// `ssa::Function`.
+#[derive(Debug)]
+pub struct Error {
+    pub block: ssa::Label,
+    pub value: Option<ssa::Value>,
+    pub kind: ErrorKind,
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    /// A block does not end in a jump, branch, return, or scope error.
+    MissingTerminator,
+    /// A terminator appears before the end of its block.
+    EarlyTerminator,
+    /// An `Alias`, `Project`, or `Parameter` appears in a block's instruction list.
+    NotInstruction,
+    /// A jump or branch targets a block that does not exist.
+    UnknownTarget(ssa::Label),
+    /// A branch's `arg_lens` do not account for all of its arguments.
+    BranchArguments,
+    /// An edge passes a different number of arguments than its target has parameters.
+    ArgumentCount { target: ssa::Label, expected: usize, found: usize },
+    /// A multi-valued instruction is not followed by its projections.
+    Projection(ssa::Value),
+    /// A value is a parameter or result in more than one place.
+    Redefinition(ssa::Value),
+    /// A value is used but not defined in any block.
+    Undefined(ssa::Value),
+    /// A value is used after being replaced by an `Alias`.
+    AliasUse(ssa::Value),
+    /// A value is used somewhere its definition does not dominate.
+    Undominated(ssa::Value),
+}
+
+/// Check a function for structural errors.
+///
+/// This checks that blocks are terminated, that edges pass block parameters the right number of
+/// arguments, that projections follow their instructions, and that every use in a reachable block
+/// is dominated by its definition. Uses of values in unreachable blocks are not checked.
+///
+/// Passes may leave uses of `Alias`es for a later pass to clean up. With `allow_aliases`, such uses
+/// are checked against the aliased value instead of being reported.
+pub fn verify(program: &ssa::Function, allow_aliases: bool) -> Result<(), Vec<Error>> {
+    let mut errors = Vec::new();
+
+    // Block structure and definitions.
+    let mut defs = HashMap::new();
+    for block in program.blocks.keys() {
+        let mut define = |errors: &mut Vec<_>, value, position| {
+            if defs.insert(value, (block, position)).is_some() {
+                let kind = ErrorKind::Redefinition(value);
+                errors.push(Error { block, value: Some(value), kind });
+            }
+        };
+
+        for &parameter in &program.blocks[block].parameters {
+            define(&mut errors, parameter, 0);
+        }
+
+        let instructions = &program.blocks[block].instructions;
+        let last = instructions.len().checked_sub(1);
+        match instructions.last() {
+            Some(&value) if is_terminator(program, value) => {}
+            _ => errors.push(Error { block, value: None, kind: ErrorKind::MissingTerminator }),
+        }
+
+        for (i, &value) in instructions.iter().enumerate() {
+            let error = |kind| Error { block, value: Some(value), kind };
+
+            if let ssa::Instruction::Alias { .. } |
+                ssa::Instruction::Project { .. } |
+                ssa::Instruction::Parameter = program.values[value]
+            {
+                errors.push(error(ErrorKind::NotInstruction));
+                continue;
+            }
+            if Some(i) != last && is_terminator(program, value) {
+                errors.push(error(ErrorKind::EarlyTerminator));
+            }
+
+            let results = program.defs(value);
+            let multiple = results.len() > 1;
+            for (index, def) in results.enumerate() {
+                if multiple {
+                    let projection = ssa::Instruction::Project { arg: value, index: index as u8 };
+                    if program.values.get(def) != Some(&projection) {
+                        errors.push(error(ErrorKind::Projection(def)));
+                    }
+                }
+                define(&mut errors, def, i + 1);
+            }
+        }
+    }
+
+    // Control flow cannot be analyzed without well-formed terminators.
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    // Edges and their arguments.
+    for block in program.blocks.keys() {
+        let value = program.terminator(block);
+        let error = |kind| Error { block, value: Some(value), kind };
+
+        let edge = |errors: &mut Vec<_>, target: ssa::Label, found: usize| {
+            match program.blocks.get(target) {
+                None => errors.push(error(ErrorKind::UnknownTarget(target))),
+                Some(ssa::Block { parameters, .. }) if parameters.len() != found => {
+                    let expected = parameters.len();
+                    errors.push(error(ErrorKind::ArgumentCount { target, expected, found }));
+                }
+                Some(_) => {}
+            }
+        };
+
+        match program.values[value] {
+            ssa::Instruction::Jump { target, ref args, .. } => {
+                edge(&mut errors, target, args.len());
+            }
+            ssa::Instruction::Branch { targets, arg_lens, ref args, .. } => {
+                let [true_args, false_args] = arg_lens;
+                if args.len() != 1 + true_args as usize + false_args as usize {
+                    errors.push(error(ErrorKind::BranchArguments));
+                }
+                edge(&mut errors, targets[0], true_args as usize);
+                edge(&mut errors, targets[1], false_args as usize);
+            }
+            _ => {}
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    // Uses and dominance.
+    let control_flow = ControlFlow::compute(program);
+    let dominators = Dominators::compute(program, &control_flow);
+    for block in program.blocks.keys().filter(|&block| dominators.is_reachable(block)) {
+        for (i, &value) in program.blocks[block].instructions.iter().enumerate() {
+            let position = i + 1;
+            for &arg in program.uses(value) {
+                let error = |kind| Error { block, value: Some(value), kind };
+
+                let mut arg = arg;
+                if let ssa::Instruction::Alias { .. } = program.values[arg] {
+                    if !allow_aliases {
+                        errors.push(error(ErrorKind::AliasUse(arg)));
+                        continue;
+                    }
+                    for _ in 0..program.values.len() {
+                        match program.values[arg] {
+                            ssa::Instruction::Alias { arg: original } => arg = original,
+                            _ => break,
+                        }
+                    }
+                }
+                match defs.get(&arg) {
+                    None => errors.push(error(ErrorKind::Undefined(arg))),
+                    Some(&(def_block, def_position)) => {
+                        let dominated = if def_block == block {
+                            def_position < position
+                        } else {
+                            dominators.dominates(def_block, block)
+                        };
+                        if !dominated {
+                            errors.push(error(ErrorKind::Undominated(arg)));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
+
+fn is_terminator(program: &ssa::Function, value: ssa::Value) -> bool {
+    matches!(program.values[value],
+        ssa::Instruction::Jump { .. } |
+        ssa::Instruction::Branch { .. } |
+        ssa::Instruction::Unary { op: ssa::Opcode::Return, .. } |
+        ssa::Instruction::Unary { op: ssa::Opcode::ScopeError, .. })
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "b{}: ", self.block.index())?;
+        if let Some(value) = self.value {
+            write!(f, "v{}: ", value.index())?;
+        }
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ErrorKind::MissingTerminator => write!(f, "block does not end in a terminator"),
+            ErrorKind::EarlyTerminator => write!(f, "terminator before the end of the block"),
+            ErrorKind::NotInstruction => write!(f, "not an instruction"),
+            ErrorKind::UnknownTarget(target) =>
+                write!(f, "jump to nonexistent block b{}", target.index()),
+            ErrorKind::BranchArguments =>
+                write!(f, "branch argument lengths do not match its arguments"),
+            ErrorKind::ArgumentCount { target, expected, found } =>
+                write!(f, "passes {} arguments to b{}, which takes {}",
+                    found, target.index(), expected),
+            ErrorKind::Projection(value) =>
+                write!(f, "result v{} is not a projection of its instruction", value.index()),
+            ErrorKind::Redefinition(value) =>
+                write!(f, "v{} is defined more than once", value.index()),
+            ErrorKind::Undefined(value) =>
+                write!(f, "use of v{}, which is not defined in any block", value.index()),
+            ErrorKind::AliasUse(value) =>
+                write!(f, "use of v{}, which has been replaced by an alias", value.index()),
+            ErrorKind::Undominated(value) =>
+                write!(f, "use of v{} is not dominated by its definition", value.index()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use project::Version;
+    use crate::{FunctionDisplay, ErrorPrinter};
+    use crate::symbol::Symbol;
+    use crate::handle_map::Handle;
+    use crate::front::{self, Lines};
+    use crate::back::{ssa, PassManager};
+    use super::verify;
+
+    fn messages(program: &ssa::Function, allow_aliases: bool) -> Vec<String> {
+        match verify(program, allow_aliases) {
+            Ok(()) => vec![],
+            Err(errors) => errors.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    fn constant(program: &mut ssa::Function, block: ssa::Label, real: f64) -> ssa::Value {
+        let instruction = ssa::Instruction::UnaryReal { op: ssa::Opcode::Constant, real };
+        program.emit_instruction(block, instruction, 0)
+    }
+
+    fn jump(
+        program: &mut ssa::Function, block: ssa::Label, target: ssa::Label, args: Vec<ssa::Value>
+    ) {
+        let instruction = ssa::Instruction::Jump { op: ssa::Opcode::Jump, target, args };
+        program.emit_instruction(block, instruction, 0);
+    }
+
+    fn ret(program: &mut ssa::Function, block: ssa::Label, arg: ssa::Value) {
+        let instruction = ssa::Instruction::Unary { op: ssa::Opcode::Return, arg };
+        program.emit_instruction(block, instruction, 0);
+    }
+
+    /// Accept everything the front end and optimizer produce.
+    #[test]
+    fn front_end() {
+        let source = b"{
+            var i, s;
+            s = 0
+            for (i = 0; i < argument0; i += 1) {
+                switch (i mod 3) {
+                    case 0: s += i; break
+                    case 1: continue
+                    default: s -= 1
+                }
+            }
+            with (other) {
+                s += x
+            }
+            while (1) {
+                if s > 10 exit
+                s += 1
+            }
+        }";
+
+        let prototypes = HashMap::new();
+        let lines = Lines::from_code(source);
+        let name = FunctionDisplay::Script { script: Symbol::intern(b"<test>") };
+        let mut errors = ErrorPrinter::new(name, &lines, Vec::new());
+        let reader = front::Lexer::new(source, 0, Version::GameMaker8);
+        let program = front::Parser::new(reader, &mut errors).parse_program();
+        let (mut program, _) = front::Codegen::new(&prototypes, Version::GameMaker8, &mut errors)
+            .compile_program(&program);
+        assert_eq!(errors.count, 0);
+
+        assert_eq!(messages(&program, false), Vec::<String>::new());
+        PassManager::standard().run(&mut program, |pass, program| {
+            assert_eq!(messages(program, true), Vec::<String>::new(), "after {}", pass);
+        });
+        assert_eq!(messages(&program, false), Vec::<String>::new());
+    }
+
+    /// Report structural errors in hand-built functions.
+    #[test]
+    fn errors() {
+        // A block without a terminator.
+        let mut program = ssa::Function::new();
+        jump(&mut program, ssa::ENTRY, ssa::EXIT, vec![]);
+        assert_eq!(messages(&program, false), [
+            "b1: block does not end in a terminator",
+        ]);
+
+        // A terminator in the middle of a block.
+        let mut program = ssa::Function::new();
+        let a = constant(&mut program, ssa::ENTRY, 1.0);
+        ret(&mut program, ssa::ENTRY, a);
+        jump(&mut program, ssa::ENTRY, ssa::EXIT, vec![]);
+        ret(&mut program, ssa::EXIT, a);
+        assert_eq!(messages(&program, false), [
+            "b0: v2: terminator before the end of the block",
+        ]);
+
+        // Edges that pass the wrong number of arguments.
+        let mut program = ssa::Function::new();
+        let block = program.make_block();
+        program.emit_parameter(block);
+        let a = constant(&mut program, ssa::ENTRY, 1.0);
+        let branch = ssa::Instruction::Branch {
+            op: ssa::Opcode::Branch,
+            targets: [block, ssa::EXIT],
+            arg_lens: [0, 1],
+            args: vec![a],
+        };
+        program.emit_instruction(ssa::ENTRY, branch, 0);
+        jump(&mut program, block, ssa::EXIT, vec![a]);
+        ret(&mut program, ssa::EXIT, a);
+        assert_eq!(messages(&program, false), [
+            "b0: v3: branch argument lengths do not match its arguments",
+            "b0: v3: passes 0 arguments to b2, which takes 1",
+            "b0: v3: passes 1 arguments to b1, which takes 0",
+            "b2: v4: passes 1 arguments to b1, which takes 0",
+        ]);
+
+        // A use of an alias, which may be allowed between passes.
+        let mut program = ssa::Function::new();
+        let a = constant(&mut program, ssa::ENTRY, 1.0);
+        let alias = program.values.push(ssa::Instruction::Alias { arg: a });
+        jump(&mut program, ssa::ENTRY, ssa::EXIT, vec![]);
+        ret(&mut program, ssa::EXIT, alias);
+        assert_eq!(messages(&program, false), [
+            "b1: v4: use of v2, which has been replaced by an alias",
+        ]);
+        assert_eq!(messages(&program, true), Vec::<String>::new());
+
+        // Uses not dominated by their definitions, in another block and earlier in the same block.
+        let mut program = ssa::Function::new();
+        let then_block = program.make_block();
+        let else_block = program.make_block();
+        let condition = constant(&mut program, ssa::ENTRY, 1.0);
+        let branch = ssa::Instruction::Branch {
+            op: ssa::Opcode::Branch,
+            targets: [then_block, else_block],
+            arg_lens: [0, 0],
+            args: vec![condition],
+        };
+        program.emit_instruction(ssa::ENTRY, branch, 0);
+        let a = constant(&mut program, then_block, 1.0);
+        jump(&mut program, then_block, ssa::EXIT, vec![]);
+        jump(&mut program, else_block, ssa::EXIT, vec![]);
+        let negate = ssa::Instruction::Unary { op: ssa::Opcode::Negate, arg: ssa::Value::new(7) };
+        program.emit_instruction(ssa::EXIT, negate, 0);
+        let b = constant(&mut program, ssa::EXIT, 2.0);
+        assert_eq!(b, ssa::Value::new(7));
+        let add = ssa::Instruction::Binary { op: ssa::Opcode::Add, args: [a, b] };
+        let c = program.emit_instruction(ssa::EXIT, add, 0);
+        ret(&mut program, ssa::EXIT, c);
+        assert_eq!(messages(&program, false), [
+            "b1: v6: use of v7 is not dominated by its definition",
+            "b1: v8: use of v3 is not dominated by its definition",
+        ]);
+    }
+}
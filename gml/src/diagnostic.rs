@@ -0,0 +1,124 @@
+use std::{fmt, io};
+
+use crate::FunctionDisplay;
+use crate::front::{Position, Span};
+
+/// A message about a problem in a script, event, or constant.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub function: FunctionDisplay,
+    pub position: Position,
+    pub span: Span,
+    pub message: String,
+    pub notes: Vec<String>,
+
+    /// The line of source code containing `span.low`, if it has one.
+    pub snippet: Option<Snippet>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A line of source code, and the columns of a diagnostic's span within it.
+#[derive(Clone, Debug)]
+pub struct Snippet {
+    pub text: String,
+    pub columns: (usize, usize),
+}
+
+/// A destination for diagnostics as they are reported.
+pub trait Sink {
+    fn report(&mut self, diagnostic: Diagnostic);
+}
+
+impl Sink for Vec<Diagnostic> {
+    fn report(&mut self, diagnostic: Diagnostic) { self.push(diagnostic); }
+}
+
+impl<S: Sink + ?Sized> Sink for &mut S {
+    fn report(&mut self, diagnostic: Diagnostic) { (**self).report(diagnostic); }
+}
+
+/// Formats diagnostics as text.
+///
+/// By default, each diagnostic is a single line of the form `error in script foo:3:5: message`.
+/// With `snippets` set, the offending line of source is shown below it, with the span underlined.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Renderer {
+    pub snippets: bool,
+}
+
+/// A sink that renders diagnostics as text as soon as they are reported.
+pub struct Printer<W: ?Sized = dyn io::Write> {
+    pub renderer: Renderer,
+    pub write: W,
+}
+
+impl Diagnostic {
+    pub fn is_error(&self) -> bool { self.severity == Severity::Error }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+impl Renderer {
+    pub fn render<W: io::Write + ?Sized>(
+        &self, diagnostic: &Diagnostic, write: &mut W
+    ) -> io::Result<()> {
+        let Position { action, argument, line, column } = diagnostic.position;
+        write!(write, "{} in {}", diagnostic.severity, diagnostic.function)?;
+        if let Some(action) = action {
+            write!(write, ", action {}", action)?;
+        }
+        if let (Some(argument), None) = (argument, line) {
+            write!(write, ", argument {}", argument)?;
+        }
+        if let Some(line) = line {
+            write!(write, ":{}", line)?;
+        }
+        if let Some(column) = column {
+            write!(write, ":{}", column)?;
+        }
+        writeln!(write, ": {}", diagnostic.message)?;
+
+        let gutter = line.map_or(0, |line| line.to_string().len());
+        if let (true, Some(snippet), Some(line)) = (self.snippets, &diagnostic.snippet, line) {
+            let Snippet { ref text, columns: (low, high) } = *snippet;
+
+            // Keep tabs in the underline so it stays aligned with the text above it.
+            let indent: String = text.chars().take(low)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let underline = "^".repeat(usize::max(1, high - low));
+
+            writeln!(write, "{:w$} |", "", w = gutter)?;
+            writeln!(write, "{} | {}", line, text)?;
+            writeln!(write, "{:w$} | {}{}", "", indent, underline, w = gutter)?;
+        }
+        for note in &diagnostic.notes {
+            writeln!(write, "{:w$} = note: {}", "", note, w = gutter)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: io::Write> Printer<W> {
+    pub fn new(write: W) -> Self { Printer { renderer: Renderer::default(), write } }
+}
+
+impl<W: io::Write + ?Sized> Sink for Printer<W> {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        let _ = self.renderer.render(&diagnostic, &mut self.write);
+    }
+}
@@ -1,6 +1,6 @@
 use std::{slice, str, str::FromStr};
 
-use project::{Action, action_kind, action_type, argument_type};
+use project::{Action, Version, action_kind, action_type, argument_type};
 
 use crate::ErrorPrinter;
 use crate::symbol::Symbol;
@@ -9,6 +9,7 @@ use crate::front::{ast, Lexer, Parser, Span};
 pub struct ActionParser<'s, 'e, 'f> {
     reader: slice::Iter<'s, Action<'s>>,
     errors: &'e mut ErrorPrinter<'f>,
+    version: Version,
 
     current: Option<&'s Action<'s>>,
     span: Span,
@@ -18,10 +19,12 @@ impl<'s, 'e, 'f> ActionParser<'s, 'e, 'f> {
     pub fn new(
         reader: slice::Iter<'s, Action<'s>>,
         errors: &'e mut ErrorPrinter<'f>,
+        version: Version,
     ) -> ActionParser<'s, 'e, 'f> {
         let mut parser = ActionParser {
             reader,
             errors,
+            version,
 
             current: None,
             span: Span { low: 0, high: 0 },
@@ -102,7 +105,7 @@ impl<'s, 'e, 'f> ActionParser<'s, 'e, 'f> {
             }
 
             action_type::CODE => {
-                let reader = Lexer::new(&action.code, offset);
+                let reader = Lexer::new(&action.code, offset, self.version);
                 let mut parser = Parser::new(reader, self.errors);
                 let program = Box::new(parser.parse_program());
                 offset += action.code.len();
@@ -169,7 +172,7 @@ impl<'s, 'e, 'f> ActionParser<'s, 'e, 'f> {
 
         let argument = match param {
             argument_type::EXPR => {
-                let reader = Lexer::new(source, offset);
+                let reader = Lexer::new(source, offset, self.version);
                 let mut parser = Parser::new(reader, self.errors);
                 ast::Argument::Expr(Box::new(parser.parse_expression(0)))
             }
@@ -182,7 +185,7 @@ impl<'s, 'e, 'f> ActionParser<'s, 'e, 'f> {
             argument_type::BOTH => {
                 match source.first().copied() {
                     Some(b'"') => {
-                        let reader = Lexer::new(source, offset);
+                        let reader = Lexer::new(source, offset, self.version);
                         let mut parser = Parser::new(reader, self.errors);
                         ast::Argument::Expr(Box::new(parser.parse_expression(0)))
                     }
@@ -322,7 +325,7 @@ impl<'s, 'e, 'f> ActionParser<'s, 'e, 'f> {
         let (&parameter, &source) = arguments.next().unwrap();
         let count = match parameter {
             argument_type::EXPR => {
-                let reader = Lexer::new(source, offset);
+                let reader = Lexer::new(source, offset, self.version);
                 let mut parser = Parser::new(reader, self.errors);
                 Box::new(parser.parse_expression(0))
             }
@@ -378,7 +381,7 @@ impl<'s, 'e, 'f> ActionParser<'s, 'e, 'f> {
         let (&parameter, &source) = arguments.next().unwrap();
         let variable = match parameter {
             argument_type::STRING => {
-                let reader = Lexer::new(source, offset);
+                let reader = Lexer::new(source, offset, self.version);
                 let mut parser = Parser::new(reader, self.errors);
                 Box::new(parser.parse_expression(0))
             }
@@ -393,7 +396,7 @@ impl<'s, 'e, 'f> ActionParser<'s, 'e, 'f> {
         let (&parameter, &source) = arguments.next().unwrap();
         let value = match parameter {
             argument_type::EXPR => {
-                let reader = Lexer::new(source, 0);
+                let reader = Lexer::new(source, 0, self.version);
                 let mut parser = Parser::new(reader, self.errors);
                 Box::new(parser.parse_expression(0))
             }
@@ -438,7 +441,7 @@ impl<'s, 'e, 'f> ActionParser<'s, 'e, 'f> {
         let (&parameter, &source) = arguments.next().unwrap();
         let code = match parameter {
             argument_type::STRING => {
-                let reader = Lexer::new(source, offset);
+                let reader = Lexer::new(source, offset, self.version);
                 let mut parser = Parser::new(reader, self.errors);
                 Box::new(parser.parse_program())
             }
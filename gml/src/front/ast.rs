@@ -2,6 +2,7 @@ use crate::symbol::Symbol;
 use crate::front::Span;
 
 pub use crate::front::action_ast::*;
+pub use crate::front::token::Accessor;
 
 #[derive(PartialEq, Debug)]
 pub enum Stmt {
@@ -9,7 +10,14 @@ pub enum Stmt {
 
     Assign((Option<Op>, Span), Box<(Expr, Span)>, Box<(Expr, Span)>),
     Invoke(Call),
-    Declare(Declare, Box<[(Symbol, Span)]>),
+    /// `x++` or `++x` (or `--`), with `Op::Add` or `Op::Subtract`.
+    Increment((Op, Span), Box<(Expr, Span)>),
+    /// `var name, name = value`, where initializers are only accepted from GMS on.
+    Declare(Declare, Box<[((Symbol, Span), Option<(Expr, Span)>)]>),
+    /// `#macro NAME value`
+    Macro((Symbol, Span), Box<(Expr, Span)>),
+    /// `enum Name { member, member = value }`
+    Enum((Symbol, Span), Box<[((Symbol, Span), Option<(Expr, Span)>)]>),
     Block(Box<[(Stmt, Span)]>),
 
     If(Box<(Expr, Span)>, Box<(Stmt, Span)>, Option<Box<(Stmt, Span)>>),
@@ -46,7 +54,13 @@ pub enum Expr {
     Binary((Binary, Span), Box<(Expr, Span)>, Box<(Expr, Span)>),
     Field(Box<(Expr, Span)>, (Symbol, Span)),
     Index(Box<(Expr, Span)>, Box<[(Expr, Span)]>),
+    Accessor(Accessor, Box<(Expr, Span)>, Box<[(Expr, Span)]>),
     Call(Call),
+    /// `++x` or `--x`, with `Op::Add` or `Op::Subtract`.
+    Prefix((Op, Span), Box<(Expr, Span)>),
+    /// `x++` or `x--`, with `Op::Add` or `Op::Subtract`.
+    Postfix((Op, Span), Box<(Expr, Span)>),
+    Ternary(Box<(Expr, Span)>, Box<(Expr, Span)>, Box<(Expr, Span)>),
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
@@ -1,10 +1,13 @@
 use std::{mem, cmp, iter};
 use std::collections::HashMap;
 
+use project::Version;
+
 use crate::ErrorPrinter;
+use crate::diagnostic::Severity;
 use crate::symbol::{Symbol, keyword};
 use crate::front::{self, ast, Span};
-use crate::back::ssa;
+use crate::back::{ssa, lint};
 
 pub struct Codegen<'p, 'e, 'f> {
     function: ssa::Function,
@@ -12,10 +15,14 @@ pub struct Codegen<'p, 'e, 'f> {
     errors: &'e mut ErrorPrinter<'f>,
 
     prototypes: &'p HashMap<Symbol, ssa::Prototype>,
+    /// The dialect of GML being compiled.
+    version: Version,
 
     /// GML `var` declarations are static and independent of control flow. All references to a
     /// `var`-declared name after its declaration in the source text are treated as local.
     locals: HashMap<Symbol, Local>,
+    /// What `back::lint` needs to know about the source.
+    syntax: lint::Syntax,
     /// The number of script arguments that have been created so far.
     arguments: u32,
     /// The return value of the program.
@@ -38,8 +45,8 @@ pub struct Codegen<'p, 'e, 'f> {
 /// A location that can be read from or written to.
 ///
 /// Pre-studio GML arrays are not first class values, and are instead tied to variable bindings.
-/// To accomodate this, `Place` uses a `Path` rather than an `ssa::Value`. To support GMS arrays
-/// and data structure accessors, `Path` might gain a `Value` variant.
+/// To accomodate this, `Place` uses a `Path` rather than an `ssa::Value`. To support GMS arrays,
+/// `Path` might gain a `Value` variant.
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 struct Place {
     path: Path,
@@ -55,6 +62,8 @@ enum Path {
     Field(ssa::Value, Symbol),
     /// A prefixed variable dynamically referencing an instance or object.
     Scope(ssa::Value, Symbol),
+    /// An element of a data structure, read and written through its accessor functions.
+    Accessor(ast::Accessor, ssa::Value, Vec<ssa::Value>),
 }
 
 #[derive(Debug)]
@@ -91,7 +100,9 @@ const LOCAL: f64 = -7.0;
 
 impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
     pub fn new(
-        prototypes: &'p HashMap<Symbol, ssa::Prototype>, errors: &'e mut ErrorPrinter<'f>
+        prototypes: &'p HashMap<Symbol, ssa::Prototype>,
+        version: Version,
+        errors: &'e mut ErrorPrinter<'f>,
     ) -> Self {
         let function = ssa::Function::new();
 
@@ -104,8 +115,10 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
             errors,
 
             prototypes,
+            version,
 
             locals: HashMap::new(),
+            syntax: lint::Syntax::default(),
             arguments: 0,
             return_value: return_value,
 
@@ -122,20 +135,20 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
         }
     }
 
-    pub fn compile_event(self, event: &(ast::Action, Span)) -> ssa::Function {
+    pub fn compile_event(self, event: &(ast::Action, Span)) -> (ssa::Function, lint::Syntax) {
         let (_, event_span) = *event;
         self.with_program(event_span.high, move |self_| {
             self_.emit_action(event);
         })
     }
 
-    pub fn compile_program(self, program: &(ast::Stmt, Span)) -> ssa::Function {
+    pub fn compile_program(self, program: &(ast::Stmt, Span)) -> (ssa::Function, lint::Syntax) {
         self.with_program(end_loc(program), move |self_| {
             self_.emit_statement(program);
         })
     }
 
-    fn with_program<F>(mut self, end_loc: usize, program: F) -> ssa::Function where
+    fn with_program<F>(mut self, end_loc: usize, program: F) -> (ssa::Function, lint::Syntax) where
         F: FnOnce(&mut Self)
     {
         let entry_block = self.current_block;
@@ -165,7 +178,7 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
             None => self.function.values.push(ssa::Instruction::Parameter),
         };
 
-        self.function
+        (self.function, self.syntax)
     }
 
     fn emit_action(&mut self, action: &(ast::Action, Span)) {
@@ -297,6 +310,12 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
 
     fn emit_statement(&mut self, statement: &(ast::Stmt, Span)) {
         let (ref statement, statement_span) = *statement;
+        match *statement {
+            // Labels and declarations evaluated before code generation run no code of their own.
+            ast::Stmt::Case(_) | ast::Stmt::Macro(..) | ast::Stmt::Enum(..) |
+            ast::Stmt::Error(_) => {}
+            _ => self.syntax.statements.push((self.current_block, statement_span)),
+        }
         match *statement {
             ast::Stmt::Assign(op, box ref place, box ref value) => {
                 self.emit_assign(op, place, value);
@@ -309,27 +328,39 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
                 self.emit_value_call(symbol, args);
             }
 
+            ast::Stmt::Increment(op, box ref place) => {
+                let _ = self.emit_increment(op, place);
+            }
+
             ast::Stmt::Declare(scope, box ref names) => {
-                let names: Vec<_> = names.iter().filter_map(|&(name, name_span)| {
+                let names: Vec<_> = names.iter().filter(|&&((name, name_span), _)| {
                     if name.is_argument() {
                         self.errors.error(name_span,
                             format_args!("cannot redeclare a builtin variable"));
-                        return None;
+                        return false;
                     }
 
-                    Some((name, name_span))
+                    true
                 }).collect();
 
                 match scope {
                     ast::Declare::Local => {
-                        for (symbol, _symbol_span) in names {
+                        for &((symbol, symbol_span), ref value) in names {
+                            if !self.locals.contains_key(&symbol) {
+                                self.syntax.locals.push((symbol, symbol_span));
+                            }
                             let local = self.emit_local(None);
                             self.locals.insert(symbol, local);
+
+                            if let Some(ref value) = *value {
+                                let place = ast::Expr::Value(ast::Value::Ident(symbol));
+                                self.emit_assign((None, symbol_span), &(place, symbol_span), value);
+                            }
                         }
                     }
 
                     ast::Declare::Global => {
-                        for (name, name_span) in names {
+                        for &((name, name_span), _) in names {
                             self.emit_unary_symbol(ssa::Opcode::DeclareGlobal, name, name_span.low);
                         }
                     }
@@ -482,6 +513,7 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
                 let switch = self.current_switch.expect("corrupt switch state");
                 let value = self.emit_value(expr);
                 let value = self.emit_binary(ssa::Opcode::Eq, [switch, value], loc(expr));
+                self.syntax.cases.push((value, expr.1));
                 self.emit_branch(value, case_block, expr_block, loc(expr));
                 self.seal_block(case_block);
                 self.seal_block(expr_block);
@@ -536,6 +568,9 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
                 self.seal_block(dead_block);
             }
 
+            // Macros and enums are collected project-wide before code generation.
+            ast::Stmt::Macro(..) | ast::Stmt::Enum(..) => {}
+
             ast::Stmt::Error(_) => {}
         }
     }
@@ -565,6 +600,24 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
         self.emit_store(place, value, op_span.low);
     }
 
+    /// Add or subtract one from a place, producing its old and new values.
+    fn emit_increment(
+        &mut self, op: (ast::Op, Span), place: &(ast::Expr, Span)
+    ) -> Result<(ssa::Value, ssa::Value), PlaceError> {
+        let (op, op_span) = op;
+
+        let (_, place_span) = *place;
+        let place = self.emit_place(place)?;
+
+        let old = self.emit_load(place.clone(), place_span);
+        let one = self.emit_real(1.0, op_span.low);
+        let op = ast::Binary::Op(op);
+        let new = self.emit_binary(ssa::Opcode::from(op), [old, one], op_span.low);
+
+        self.emit_store(place, new, op_span.low);
+        Ok((old, new))
+    }
+
     fn emit_if<T, F>(
         &mut self,
         value: (ssa::Value, usize),
@@ -689,6 +742,24 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
             ast::Expr::Value(ast::Value::Ident(keyword::NoOne)) => self.emit_real(NOONE, expr_loc),
             ast::Expr::Value(ast::Value::Ident(keyword::Global)) => self.emit_real(GLOBAL, expr_loc),
             ast::Expr::Value(ast::Value::Ident(keyword::Local)) => self.emit_real(LOCAL, expr_loc),
+            ast::Expr::Value(ast::Value::Ident(symbol)) if self.constant(symbol).is_some() => {
+                let constant = self.constant(symbol).unwrap();
+                self.emit_constant(constant, expr_loc)
+            }
+            ast::Expr::Field(
+                box (ast::Expr::Value(ast::Value::Ident(symbol)), _),
+                (member, member_span)
+            ) if self.enumeration(symbol).is_some() => {
+                let enumeration = self.enumeration(symbol).unwrap();
+                match enumeration.get(&member) {
+                    Some(&value) => self.emit_real(value, expr_loc),
+                    None => {
+                        self.errors.error(member_span,
+                            format_args!("unknown member of enum {}: {}", symbol, member));
+                        self.emit_real(0.0, expr_loc)
+                    }
+                }
+            }
 
             ast::Expr::Unary((ast::Unary::Positive, _), box ref expr) => self.emit_value(expr),
             ast::Expr::Unary((op, op_span), box ref expr) => {
@@ -702,6 +773,29 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
                 self.emit_unary(op, expr, op_span.low)
             }
 
+            // GMS skips the right operand when the left one already decides the result.
+            ast::Expr::Binary((op @ ast::Binary::And, op_span), box ref left, box ref right) |
+            ast::Expr::Binary((op @ ast::Binary::Or, op_span), box ref left, box ref right) if
+                self.version >= Version::GameMakerStudio
+            => {
+                let result = self.builder.emit_local();
+                let value = self.emit_value(left);
+                let short = self.emit_real(if op == ast::Binary::Or { 1.0 } else { 0.0 }, loc(left));
+                let full = move |self_: &mut Self| {
+                    let args = [value, self_.emit_value(right)];
+                    let value = self_.emit_binary(ssa::Opcode::from(op), args, op_span.low);
+                    self_.write_local(result, value);
+                };
+                let skip = move |self_: &mut Self| self_.write_local(result, short);
+                let value = (value, op_span.low);
+                if op == ast::Binary::Or {
+                    self.emit_if(value, (skip, loc(left)), Some((full, loc(right))));
+                } else {
+                    self.emit_if(value, (full, loc(right)), Some((skip, loc(left))));
+                }
+                self.read_local(result)
+            }
+
             ast::Expr::Binary((op, op_span), box ref left, box ref right) => {
                 let left = self.emit_value(left);
                 let right = self.emit_value(right);
@@ -715,11 +809,39 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
                 self.emit_value_call(symbol, args)
             }
 
-            _ => {
-                let place = self.emit_place(expression)
-                    .expect("_ is not a valid expression");
-                self.emit_load(place, expr_span)
+            ast::Expr::Prefix(op, box ref place) => match self.emit_increment(op, place) {
+                Ok((_, new)) => new,
+                Err(PlaceError) => self.emit_real(0.0, expr_loc),
+            },
+            ast::Expr::Postfix(op, box ref place) => match self.emit_increment(op, place) {
+                Ok((old, _)) => old,
+                Err(PlaceError) => self.emit_real(0.0, expr_loc),
+            },
+
+            ast::Expr::Ternary(box ref expr, box ref true_expr, box ref false_expr) => {
+                let result = self.builder.emit_local();
+                let value = self.emit_value(expr);
+                self.emit_if(
+                    (value, loc(expr)),
+                    (|self_: &mut Self| {
+                        let value = self_.emit_value(true_expr);
+                        self_.write_local(result, value);
+                    }, loc(true_expr)),
+                    Some((|self_: &mut Self| {
+                        let value = self_.emit_value(false_expr);
+                        self_.write_local(result, value);
+                    }, loc(false_expr)))
+                );
+                self.read_local(result)
             }
+
+            // The parser has already reported this.
+            ast::Expr::Error => self.emit_real(0.0, expr_loc),
+
+            _ => match self.emit_place(expression) {
+                Ok(place) => self.emit_load(place, expr_span),
+                Err(PlaceError) => self.emit_real(0.0, expr_loc),
+            },
         }
     }
 
@@ -739,8 +861,10 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
         };
 
         if args.len() < arity || (!variadic && args.len() > arity) {
-            self.errors.error(symbol_span,
-                format_args!("wrong number of arguments to function or script"));
+            let expected = if variadic { "at least " } else { "" };
+            let note = format!("{} takes {}{} arguments", symbol, expected, arity);
+            self.errors.report(Severity::Error, symbol_span,
+                format_args!("wrong number of arguments to function or script"), vec![note]);
             return self.emit_real(0.0, symbol_span.low);
         }
 
@@ -755,6 +879,19 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
     fn emit_place(&mut self, expression: &(ast::Expr, Span)) -> Result<Place, PlaceError> {
         let (ref expression, expression_span) = *expression;
         match *expression {
+            ast::Expr::Value(ast::Value::Ident(symbol)) if self.constant(symbol).is_some() => {
+                self.errors.error(expression_span,
+                    format_args!("{} is a constant, not a variable", symbol));
+                Err(PlaceError)
+            }
+            ast::Expr::Field(
+                box (ast::Expr::Value(ast::Value::Ident(symbol)), _),
+                (member, _member_span)
+            ) if self.enumeration(symbol).is_some() => {
+                self.errors.error(expression_span,
+                    format_args!("{}.{} is a constant, not a variable", symbol, member));
+                Err(PlaceError)
+            }
             ast::Expr::Value(ast::Value::Ident(symbol)) if !symbol.is_keyword() => {
                 if let Some(argument) = symbol.as_argument() {
                     for argument in self.arguments..argument + 1 {
@@ -810,12 +947,19 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
                 Ok(Place { path: Path::Scope(scope, field), index: None })
             }
 
-            ast::Expr::Index(box ref expr, box ref indices) => {
+            ast::Expr::Index(box ref expr, box ref indices) |
+            ast::Expr::Accessor(ast::Accessor::Array, box ref expr, box ref indices) => {
                 if indices.len() < 1 || 2 < indices.len() {
                     self.errors.error(expression_span,
                         format_args!("invalid number of array indices"));
                 }
 
+                if self.is_constant(expr) {
+                    let (_, expr_span) = *expr;
+                    self.errors.error(expr_span, format_args!("cannot index a constant"));
+                    return Err(PlaceError);
+                }
+
                 let array = self.emit_place(expr)?;
                 let zero = self.emit_real(0.0, loc(expr));
                 let mut indices = indices.iter().rev()
@@ -826,13 +970,34 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
                 let i = indices.next().unwrap();
 
                 match array {
-                    Place { path, index: None } => Ok(Place { path, index: Some([i, j]) }),
-                    Place { index: Some(_), .. } => {
+                    Place { path: Path::Accessor(..), .. } | Place { index: Some(_), .. } => {
                         let (_, expr_span) = *expr;
                         self.errors.error(expr_span, format_args!("expected a variable"));
                         Err(PlaceError)
                     }
+                    Place { path, index: None } => Ok(Place { path, index: Some([i, j]) }),
+                }
+            }
+
+            ast::Expr::Accessor(accessor, box ref expr, box ref keys) => {
+                let arity = match accessor {
+                    ast::Accessor::Grid => 2,
+                    _ => 1,
+                };
+                if keys.len() != arity {
+                    self.errors.error(expression_span,
+                        format_args!("invalid number of accessor keys"));
                 }
+
+                let ds = self.emit_value(expr);
+                let zero = self.emit_real(0.0, loc(expr));
+                let keys = keys.iter()
+                    .map(|key| self.emit_value(key))
+                    .chain(iter::repeat(zero))
+                    .take(arity)
+                    .collect();
+
+                Ok(Place { path: Path::Accessor(accessor, ds, keys), index: None })
             }
 
             _ => {
@@ -927,6 +1092,14 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
                     Some(index) => self.emit_load_index(value, index, place_span.low),
                 }
             }
+
+            // A data structure element: call its getter, which already produces a scalar.
+            Place { path: Path::Accessor(accessor, ds, keys), .. } => {
+                let (getter, _) = accessor_functions(accessor);
+                let mut args = vec![ds];
+                args.extend(keys);
+                return self.emit_value_call((getter, place_span), args);
+            }
         };
 
         // TODO: this only happens pre-gms
@@ -1059,6 +1232,16 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
                     self_.emit_store_field(entity, field, index, value, location);
                 });
             }
+
+            // A data structure element: call its setter.
+            Place { path: Path::Accessor(accessor, ds, keys), .. } => {
+                let (_, setter) = accessor_functions(accessor);
+                let mut args = vec![ds];
+                args.extend(keys);
+                args.push(value);
+                let span = Span { low: location, high: location };
+                self.emit_value_call((setter, span), args);
+            }
         }
     }
 
@@ -1193,6 +1376,39 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
         }
     }
 
+    /// The value of a named constant, unless it is shadowed by a local.
+    fn constant(&self, symbol: Symbol) -> Option<ssa::Constant> {
+        if self.locals.contains_key(&symbol) {
+            return None;
+        }
+        match self.prototypes.get(&symbol) {
+            Some(&ssa::Prototype::Constant(constant)) => Some(constant),
+            Some(&ssa::Prototype::Script { id }) => Some(ssa::Constant::Real(id as f64)),
+            _ => None,
+        }
+    }
+
+    /// The members of an enum, unless it is shadowed by a local.
+    fn enumeration(&self, symbol: Symbol) -> Option<&'p HashMap<Symbol, f64>> {
+        if self.locals.contains_key(&symbol) {
+            return None;
+        }
+        match self.prototypes.get(&symbol) {
+            Some(&ssa::Prototype::Enum(ref enumeration)) => Some(enumeration),
+            _ => None,
+        }
+    }
+
+    /// Whether an expression names a constant or an enum member rather than a variable.
+    fn is_constant(&self, expression: &(ast::Expr, Span)) -> bool {
+        match expression.0 {
+            ast::Expr::Value(ast::Value::Ident(symbol)) => self.constant(symbol).is_some(),
+            ast::Expr::Field(box (ast::Expr::Value(ast::Value::Ident(symbol)), _), _) =>
+                self.enumeration(symbol).is_some(),
+            _ => false,
+        }
+    }
+
     // TODO: move into peephole optimizer
     fn entity_is_global(&self, entity: ssa::Value) -> bool {
         match self.function.values[entity] {
@@ -1260,6 +1476,13 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
         self.emit_unary_symbol(ssa::Opcode::Constant, string, location)
     }
 
+    fn emit_constant(&mut self, constant: ssa::Constant, location: usize) -> ssa::Value {
+        match constant {
+            ssa::Constant::Real(real) => self.emit_real(real, location),
+            ssa::Constant::String(string) => self.emit_string(string, location),
+        }
+    }
+
     fn emit_nullary(&mut self, op: ssa::Opcode, location: usize) -> ssa::Value {
         let instruction = ssa::Instruction::Nullary { op };
         self.function.emit_instruction(self.current_block, instruction, location)
@@ -1360,6 +1583,17 @@ fn loc(&(_, span): &(ast::Expr, Span)) -> usize {
     span.low
 }
 
+/// The getter and setter functions behind a data structure accessor.
+fn accessor_functions(accessor: ast::Accessor) -> (Symbol, Symbol) {
+    let (getter, setter): (&[u8], &[u8]) = match accessor {
+        ast::Accessor::List => (b"ds_list_find_value", b"ds_list_set"),
+        ast::Accessor::Map => (b"ds_map_find_value", b"ds_map_set"),
+        ast::Accessor::Grid => (b"ds_grid_get", b"ds_grid_set"),
+        ast::Accessor::Array => unreachable!(),
+    };
+    (Symbol::intern(getter), Symbol::intern(setter))
+}
+
 impl From<ast::Binary> for ssa::Opcode {
     fn from(op: ast::Binary) -> ssa::Opcode {
         match op {
@@ -0,0 +1,443 @@
+use std::collections::HashMap;
+
+use crate::{ErrorPrinter, FunctionDisplay};
+use crate::diagnostic::Sink;
+use crate::symbol::{Symbol, keyword};
+use crate::front::{ast, Lines, Span};
+use crate::back::ssa::{self, Constant};
+use crate::vm::{to_i32, to_bool};
+
+/// Evaluate the expression of a project constant.
+///
+/// Only literals, other constants, and operators are allowed. Operators follow the same rules as
+/// the VM, so folding an expression here gives the same result as evaluating it at runtime.
+pub fn evaluate(
+    prototypes: &HashMap<Symbol, ssa::Prototype>,
+    errors: &mut ErrorPrinter<'_>,
+    expression: &(ast::Expr, Span)
+) -> Constant {
+    Evaluator { prototypes, errors }.evaluate(expression)
+}
+
+/// The macros and enums declared across a project.
+///
+/// Declarations are visible throughout the project and may refer to each other in any order, so
+/// they are all collected before any of them is evaluated.
+#[derive(Default)]
+pub struct Declarations<'a> {
+    declarations: Vec<Declaration<'a>>,
+}
+
+struct Declaration<'a> {
+    name: (Symbol, Span),
+    kind: DeclarationKind<'a>,
+    function: FunctionDisplay,
+    lines: &'a Lines,
+}
+
+#[derive(Copy, Clone)]
+enum DeclarationKind<'a> {
+    Macro(&'a (ast::Expr, Span)),
+    Enum(&'a [Member]),
+}
+
+/// An enum member, and the value it is explicitly given, if any.
+type Member = ((Symbol, Span), Option<(ast::Expr, Span)>);
+
+#[derive(Copy, Clone, PartialEq)]
+enum State {
+    Pending,
+    Evaluating,
+    Done,
+}
+
+impl<'a> Declarations<'a> {
+    /// Collect the macros and enums declared in a script or code action.
+    pub fn declare(
+        &mut self, function: FunctionDisplay, lines: &'a Lines, program: &'a (ast::Stmt, Span)
+    ) {
+        let (ref stmt, _) = *program;
+        let (name, kind) = match *stmt {
+            ast::Stmt::Macro(name, box ref value) => (name, DeclarationKind::Macro(value)),
+            ast::Stmt::Enum(name, box ref members) => (name, DeclarationKind::Enum(members)),
+
+            ast::Stmt::Block(box ref stmts) => {
+                for stmt in stmts.iter() {
+                    self.declare(function, lines, stmt);
+                }
+                return;
+            }
+
+            _ => return,
+        };
+        self.declarations.push(Declaration { name, kind, function, lines });
+    }
+
+    /// Collect the macros and enums declared in the code actions of an event.
+    pub fn declare_event(
+        &mut self, function: FunctionDisplay, lines: &'a Lines, event: &'a (ast::Action, Span)
+    ) {
+        let (ref action, _) = *event;
+        match *action {
+            ast::Action::Normal { ref question, ref execution, .. } => {
+                if let ast::Exec::Code(box ref program) = *execution {
+                    self.declare(function, lines, program);
+                }
+                if let Some(box ast::Question { ref true_action, ref false_action, .. }) =
+                    *question
+                {
+                    self.declare_event(function, lines, true_action);
+                    if let Some(ref false_action) = *false_action {
+                        self.declare_event(function, lines, false_action);
+                    }
+                }
+            }
+
+            ast::Action::Block { box ref body } => {
+                for action in body.iter() {
+                    self.declare_event(function, lines, action);
+                }
+            }
+
+            ast::Action::Repeat { box ref body, .. } => self.declare_event(function, lines, body),
+            ast::Action::Code { box ref code, .. } => self.declare(function, lines, code),
+
+            _ => {}
+        }
+    }
+
+    /// Evaluate every declaration and add it to `prototypes`, returning the number of errors.
+    ///
+    /// Each macro and enum member is evaluated after those it refers to, so members may refer to
+    /// other members of the same enum. A definition that refers back to itself is reported, and
+    /// its value is 0.
+    pub fn evaluate(
+        &self, prototypes: &mut HashMap<Symbol, ssa::Prototype>, sink: &mut dyn Sink
+    ) -> u32 {
+        let len = |declaration: &Declaration<'_>| match declaration.kind {
+            DeclarationKind::Macro(_) => 1,
+            DeclarationKind::Enum(members) => members.len(),
+        };
+        let mut resolver = Resolver {
+            declarations: &self.declarations,
+            names: HashMap::with_capacity(self.declarations.len()),
+            states: self.declarations.iter().map(|d| vec![State::Pending; len(d)]).collect(),
+            values: self.declarations.iter().map(|d| vec![0.0; len(d)]).collect(),
+            prototypes,
+            sink,
+            count: 0,
+        };
+
+        // The first of several declarations with the same name is the one that is kept.
+        for (index, declaration) in self.declarations.iter().enumerate() {
+            let (name, name_span) = declaration.name;
+            if resolver.prototypes.contains_key(&name) || resolver.names.contains_key(&name) {
+                resolver.with_errors(index, |_, errors| {
+                    errors.error(name_span, format_args!("{} is already defined", name));
+                });
+                for state in &mut resolver.states[index] {
+                    *state = State::Done;
+                }
+                continue;
+            }
+            resolver.names.insert(name, index);
+
+            // Members are added as they are evaluated.
+            if let DeclarationKind::Enum(members) = declaration.kind {
+                let enumeration = HashMap::with_capacity(members.len());
+                resolver.prototypes.insert(name, ssa::Prototype::Enum(enumeration));
+            }
+        }
+
+        for (index, declaration) in self.declarations.iter().enumerate() {
+            for member in 0..len(declaration) {
+                resolver.resolve(index, member);
+            }
+        }
+        resolver.count
+    }
+}
+
+struct Resolver<'d, 'a, 'p, 's> {
+    declarations: &'d [Declaration<'a>],
+    names: HashMap<Symbol, usize>,
+    /// The state of each macro, or of each member of each enum.
+    states: Vec<Vec<State>>,
+    /// The value of each enum member, for the implicit value of the next one.
+    values: Vec<Vec<f64>>,
+    prototypes: &'p mut HashMap<Symbol, ssa::Prototype>,
+    sink: &'s mut dyn Sink,
+    count: u32,
+}
+
+impl Resolver<'_, '_, '_, '_> {
+    /// Report errors in the script or event that a declaration comes from.
+    fn with_errors<T, F>(&mut self, index: usize, f: F) -> T
+        where F: FnOnce(&HashMap<Symbol, ssa::Prototype>, &mut ErrorPrinter<'_>) -> T
+    {
+        let Declaration { function, lines, .. } = self.declarations[index];
+        let mut errors = ErrorPrinter::new(function, lines, &mut *self.sink);
+        let value = f(self.prototypes, &mut errors);
+        self.count += errors.count;
+        value
+    }
+
+    /// Evaluate a macro, or a member of an enum, after everything it refers to.
+    fn resolve(&mut self, index: usize, member: usize) {
+        if self.states[index][member] != State::Pending {
+            return;
+        }
+        self.states[index][member] = State::Evaluating;
+
+        let declaration = &self.declarations[index];
+        let mut references = Vec::new();
+        let mut dependencies = Vec::new();
+        match declaration.kind {
+            DeclarationKind::Macro(value) => collect_references(value, &mut references),
+            DeclarationKind::Enum(members) => match members[member] {
+                (_, Some(ref value)) => collect_references(value, &mut references),
+                // A member without a value follows the one before it.
+                ((_, span), None) if member > 0 => dependencies.push((index, member - 1, span)),
+                (_, None) => {}
+            },
+        }
+        for (symbol, field, span) in references {
+            let dependency = match self.names.get(&symbol) {
+                Some(&dependency) => dependency,
+                None => continue,
+            };
+            let dependency_member = match (self.declarations[dependency].kind, field) {
+                (DeclarationKind::Macro(_), _) => 0,
+                (DeclarationKind::Enum(members), Some(field)) =>
+                    match members.iter().position(|&((member, _), _)| member == field) {
+                        Some(member) => member,
+                        None => continue,
+                    },
+                (DeclarationKind::Enum(_), None) => continue,
+            };
+            dependencies.push((dependency, dependency_member, span));
+        }
+
+        let mut cycle = false;
+        for (dependency, dependency_member, span) in dependencies {
+            match self.states[dependency][dependency_member] {
+                State::Pending => self.resolve(dependency, dependency_member),
+                State::Evaluating => {
+                    let (name, _) = self.declarations[dependency].name;
+                    let kind = self.declarations[dependency].kind;
+                    self.with_errors(index, |_, errors| match kind {
+                        DeclarationKind::Macro(_) =>
+                            errors.error(span, format_args!("circular definition of {}", name)),
+                        DeclarationKind::Enum(members) => {
+                            let ((member, _), _) = members[dependency_member];
+                            errors.error(span,
+                                format_args!("circular definition of {}.{}", name, member));
+                        }
+                    });
+                    cycle = true;
+                }
+                State::Done => {}
+            }
+        }
+
+        let (name, _) = declaration.name;
+        match declaration.kind {
+            DeclarationKind::Macro(value) => {
+                let constant = if cycle {
+                    Constant::Real(0.0)
+                } else {
+                    self.with_errors(index, |prototypes, errors| evaluate(prototypes, errors, value))
+                };
+                self.prototypes.insert(name, ssa::Prototype::Constant(constant));
+            }
+
+            DeclarationKind::Enum(members) => {
+                let ((symbol, symbol_span), ref value) = members[member];
+                let value = match *value {
+                    _ if cycle => 0.0,
+                    Some(ref value) => self.with_errors(index, |prototypes, errors| {
+                        match evaluate(prototypes, errors, value) {
+                            Constant::Real(real) => real,
+                            Constant::String(_) => {
+                                let (_, value_span) = *value;
+                                errors.error(value_span,
+                                    format_args!("enum values must be real"));
+                                0.0
+                            }
+                        }
+                    }),
+                    None if member > 0 => self.values[index][member - 1] + 1.0,
+                    None => 0.0,
+                };
+                self.values[index][member] = value;
+
+                // The first of several members with the same name is the one that is kept.
+                if members[..member].iter().any(|&((earlier, _), _)| earlier == symbol) {
+                    self.with_errors(index, |_, errors| {
+                        errors.error(symbol_span,
+                            format_args!("duplicate enum member {}", symbol));
+                    });
+                } else if let Some(ssa::Prototype::Enum(enumeration)) =
+                    self.prototypes.get_mut(&name)
+                {
+                    enumeration.insert(symbol, value);
+                }
+            }
+        }
+        self.states[index][member] = State::Done;
+    }
+}
+
+/// Collect the identifiers a constant expression refers to, with the member named after each one
+/// that is used as an enum.
+fn collect_references(
+    expression: &(ast::Expr, Span), references: &mut Vec<(Symbol, Option<Symbol>, Span)>
+) {
+    let (ref expr, expr_span) = *expression;
+    match *expr {
+        ast::Expr::Value(ast::Value::Ident(symbol)) => references.push((symbol, None, expr_span)),
+        ast::Expr::Field(box (ast::Expr::Value(ast::Value::Ident(symbol)), _), (member, _)) =>
+            references.push((symbol, Some(member), expr_span)),
+        ast::Expr::Field(box ref expr, _) | ast::Expr::Unary(_, box ref expr) =>
+            collect_references(expr, references),
+        ast::Expr::Binary(_, box ref left, box ref right) => {
+            collect_references(left, references);
+            collect_references(right, references);
+        }
+        _ => {}
+    }
+}
+
+struct Evaluator<'p, 'e, 'f> {
+    prototypes: &'p HashMap<Symbol, ssa::Prototype>,
+    errors: &'e mut ErrorPrinter<'f>,
+}
+
+impl Evaluator<'_, '_, '_> {
+    fn evaluate(&mut self, expression: &(ast::Expr, Span)) -> Constant {
+        let (ref expr, expr_span) = *expression;
+        match *expr {
+            ast::Expr::Value(ast::Value::Real(real)) => Constant::Real(real),
+            ast::Expr::Value(ast::Value::String(string)) => Constant::String(string),
+
+            ast::Expr::Value(ast::Value::Ident(keyword::True)) => Constant::Real(1.0),
+            ast::Expr::Value(ast::Value::Ident(keyword::False)) => Constant::Real(0.0),
+            ast::Expr::Value(ast::Value::Ident(symbol)) => match self.prototypes.get(&symbol) {
+                Some(&ssa::Prototype::Constant(constant)) => constant,
+                Some(&ssa::Prototype::Script { id }) => Constant::Real(id as f64),
+                _ => {
+                    self.errors.error(expr_span, format_args!("unknown constant: {}", symbol));
+                    Constant::Real(0.0)
+                }
+            }
+
+            ast::Expr::Field(
+                box (ast::Expr::Value(ast::Value::Ident(symbol)), _),
+                (member, member_span)
+            ) if enumeration(self.prototypes, symbol).is_some() => {
+                let enumeration = enumeration(self.prototypes, symbol).unwrap();
+                match enumeration.get(&member) {
+                    Some(&value) => Constant::Real(value),
+                    None => {
+                        self.errors.error(member_span,
+                            format_args!("unknown member of enum {}: {}", symbol, member));
+                        Constant::Real(0.0)
+                    }
+                }
+            }
+
+            ast::Expr::Unary((op, op_span), box ref expr) => {
+                let value = self.evaluate(expr);
+                match (op, value) {
+                    (ast::Unary::Positive, Constant::Real(a)) => Constant::Real(a),
+                    (ast::Unary::Negate, Constant::Real(a)) => Constant::Real(-a),
+                    (ast::Unary::Invert, Constant::Real(a)) => boolean(!to_bool(a)),
+                    (ast::Unary::BitInvert, Constant::Real(a)) => integer(!to_i32(a)),
+                    _ => {
+                        self.errors.error(op_span,
+                            format_args!("invalid operand to unary operator"));
+                        Constant::Real(0.0)
+                    }
+                }
+            }
+
+            ast::Expr::Binary((op, op_span), box ref left, box ref right) => {
+                let left = self.evaluate(left);
+                let right = self.evaluate(right);
+                match binary(op, left, right) {
+                    Ok(value) => value,
+                    Err(message) => {
+                        self.errors.error(op_span, format_args!("{}", message));
+                        Constant::Real(0.0)
+                    }
+                }
+            }
+
+            _ => {
+                self.errors.error(expr_span, format_args!("expected a constant expression"));
+                Constant::Real(0.0)
+            }
+        }
+    }
+}
+
+fn binary(op: ast::Binary, left: Constant, right: Constant) -> Result<Constant, &'static str> {
+    use self::Constant::{Real, String};
+
+    let value = match (op, left, right) {
+        (ast::Binary::Eq, a, b) => boolean(a == b),
+        (ast::Binary::Ne, a, b) => boolean(a != b),
+
+        (ast::Binary::Lt, Real(a), Real(b)) => boolean(a < b),
+        (ast::Binary::Le, Real(a), Real(b)) => boolean(a <= b),
+        (ast::Binary::Ge, Real(a), Real(b)) => boolean(a >= b),
+        (ast::Binary::Gt, Real(a), Real(b)) => boolean(a > b),
+        (ast::Binary::Lt, String(a), String(b)) => boolean(*a < *b),
+        (ast::Binary::Le, String(a), String(b)) => boolean(*a <= *b),
+        (ast::Binary::Ge, String(a), String(b)) => boolean(*a >= *b),
+        (ast::Binary::Gt, String(a), String(b)) => boolean(*a > *b),
+
+        (ast::Binary::And, Real(a), Real(b)) => boolean(to_bool(a) && to_bool(b)),
+        (ast::Binary::Or, Real(a), Real(b)) => boolean(to_bool(a) || to_bool(b)),
+        (ast::Binary::Xor, Real(a), Real(b)) => boolean(to_bool(a) != to_bool(b)),
+
+        (ast::Binary::Op(ast::Op::Add), Real(a), Real(b)) => Real(a + b),
+        (ast::Binary::Op(ast::Op::Add), String(a), String(b)) =>
+            String(Symbol::intern(&[&a[..], &b[..]].concat())),
+        (ast::Binary::Op(ast::Op::Subtract), Real(a), Real(b)) => Real(a - b),
+        (ast::Binary::Op(ast::Op::Multiply), Real(a), Real(b)) => Real(a * b),
+        (ast::Binary::Op(ast::Op::Multiply), Real(a), String(b)) =>
+            String(Symbol::intern(&b.repeat(a as usize))),
+        (ast::Binary::Op(ast::Op::Divide), Real(_), Real(b)) if b == 0.0 =>
+            return Err("division by zero"),
+        (ast::Binary::Op(ast::Op::Divide), Real(a), Real(b)) => Real(a / b),
+        (ast::Binary::Div, Real(_), Real(b)) if b == 0.0 => return Err("division by zero"),
+        (ast::Binary::Div, Real(a), Real(b)) => integer(to_i32(a / b)),
+        (ast::Binary::Mod, Real(_), Real(b)) if b == 0.0 => return Err("division by zero"),
+        (ast::Binary::Mod, Real(a), Real(b)) => Real(a % b),
+
+        (ast::Binary::Op(ast::Op::BitAnd), Real(a), Real(b)) => integer(to_i32(a) & to_i32(b)),
+        (ast::Binary::Op(ast::Op::BitOr), Real(a), Real(b)) => integer(to_i32(a) | to_i32(b)),
+        (ast::Binary::Op(ast::Op::BitXor), Real(a), Real(b)) => integer(to_i32(a) ^ to_i32(b)),
+        (ast::Binary::ShiftLeft, Real(a), Real(b)) =>
+            integer(to_i32(a).checked_shl(to_i32(b) as u32).ok_or("shift amount out of range")?),
+        (ast::Binary::ShiftRight, Real(a), Real(b)) =>
+            integer(to_i32(a).checked_shr(to_i32(b) as u32).ok_or("shift amount out of range")?),
+
+        _ => return Err("invalid operands to binary operator"),
+    };
+    Ok(value)
+}
+
+fn enumeration(
+    prototypes: &HashMap<Symbol, ssa::Prototype>, symbol: Symbol
+) -> Option<&HashMap<Symbol, f64>> {
+    match prototypes.get(&symbol) {
+        Some(&ssa::Prototype::Enum(ref enumeration)) => Some(enumeration),
+        _ => None,
+    }
+}
+
+fn boolean(value: bool) -> Constant { Constant::Real(if value { 1.0 } else { 0.0 }) }
+
+fn integer(value: i32) -> Constant { Constant::Real(f64::from(value)) }
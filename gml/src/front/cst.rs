@@ -0,0 +1,180 @@
+//! A lossless view of GML source, for tools that rewrite code in place.
+//!
+//! The parser only sees significant tokens, and the AST only records their spans. Here, every
+//! byte of the source belongs either to a token or to the trivia (whitespace and comments) that
+//! leads up to it, so writing the tokens back out reproduces the input exactly. Trivia at the end
+//! of the source is attached to the final `Token::Eof`.
+
+use project::Version;
+
+use crate::front::{Lexer, Span};
+use crate::front::token::Token;
+
+/// A token, along with the whitespace and comments that precede it.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SyntaxToken {
+    pub leading: Box<[Trivia]>,
+    pub token: Token,
+    pub span: Span,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub span: Span,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TriviaKind {
+    Whitespace,
+    LineComment,
+    BlockComment,
+}
+
+/// The full token stream of a script or code action.
+pub struct SyntaxTokens<'s> {
+    pub source: &'s [u8],
+    /// The tokens in source order, ending with `Token::Eof`.
+    pub tokens: Vec<SyntaxToken>,
+}
+
+impl<'s> SyntaxTokens<'s> {
+    pub fn lex(source: &'s [u8], version: Version) -> SyntaxTokens<'s> {
+        let mut lexer = Lexer::new(source, 0, version);
+        let mut tokens = Vec::new();
+
+        let mut position = 0;
+        let mut comments = 0;
+        loop {
+            let (token, span) = lexer.read_token();
+
+            // Everything between the last token and this one that is not a comment is whitespace.
+            let mut leading = Vec::new();
+            for &comment in &lexer.comments()[comments..] {
+                whitespace(&mut leading, position, comment.low);
+                let kind = match source[comment.low + 1] {
+                    b'/' => TriviaKind::LineComment,
+                    _ => TriviaKind::BlockComment,
+                };
+                leading.push(Trivia { kind, span: comment });
+                position = comment.high;
+            }
+            comments = lexer.comments().len();
+            whitespace(&mut leading, position, span.low);
+            position = span.high;
+
+            tokens.push(SyntaxToken { leading: leading.into_boxed_slice(), token, span });
+            if token == Token::Eof {
+                break;
+            }
+        }
+
+        SyntaxTokens { source, tokens }
+    }
+
+    pub fn text(&self, span: Span) -> &'s [u8] {
+        &self.source[span.low..span.high]
+    }
+
+    /// The token whose text contains `position`, or failing that the token which ends there.
+    pub fn token_at(&self, position: usize) -> Option<&SyntaxToken> {
+        self.tokens.iter()
+            .find(|token| token.span.low <= position && position < token.span.high)
+            .or_else(|| self.tokens.iter().find(|token| token.span.high == position))
+    }
+
+    /// Write out the source, replacing the text of tokens for which `replace` returns something.
+    ///
+    /// Trivia is always kept as it is, so e.g. renaming a variable leaves its comments in place.
+    pub fn rewrite<'r, F>(&self, mut replace: F) -> Vec<u8> where
+        F: FnMut(&SyntaxToken) -> Option<&'r [u8]>
+    {
+        let mut out = Vec::with_capacity(self.source.len());
+        for token in &self.tokens {
+            for trivia in &token.leading[..] {
+                out.extend_from_slice(self.text(trivia.span));
+            }
+            out.extend_from_slice(replace(token).unwrap_or_else(|| self.text(token.span)));
+        }
+        out
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.rewrite(|_| None)
+    }
+}
+
+fn whitespace(leading: &mut Vec<Trivia>, low: usize, high: usize) {
+    if low < high {
+        leading.push(Trivia { kind: TriviaKind::Whitespace, span: Span { low, high } });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use project::Version;
+    use crate::symbol::Symbol;
+    use crate::front::Span;
+    use crate::front::token::{Token, BinOp};
+    use super::{SyntaxTokens, Trivia, TriviaKind};
+
+    #[test]
+    fn lossless() {
+        let sources: &[&[u8]] = &[
+            b"",
+            b"  \r\n\t",
+            b"/* comment */ var foo; foo = 3 // trailing",
+            b"a = \"unterminated\n",
+            b"a = 1 /* unterminated",
+            b"#macro X @\x0b\xff\xfe { begin } end",
+            b"s = \"\\\"\"; t = 'it''s' ;;",
+        ];
+        for &source in sources {
+            for &version in &[Version::GameMaker8, Version::GameMakerStudio] {
+                let tokens = SyntaxTokens::lex(source, version);
+                assert_eq!(tokens.to_bytes(), source);
+                assert_eq!(tokens.tokens.last().map(|token| token.token), Some(Token::Eof));
+            }
+        }
+    }
+
+    #[test]
+    fn trivia() {
+        let source = b"a = 1; // one\n/* two */ b";
+        let tokens = SyntaxTokens::lex(source, Version::GameMaker8);
+
+        let b = &tokens.tokens[4];
+        assert_eq!(b.token, Token::Ident(Symbol::intern(b"b")));
+        assert_eq!(&b.leading[..], &[
+            Trivia { kind: TriviaKind::Whitespace, span: Span { low: 6, high: 7 } },
+            Trivia { kind: TriviaKind::LineComment, span: Span { low: 7, high: 13 } },
+            Trivia { kind: TriviaKind::Whitespace, span: Span { low: 13, high: 14 } },
+            Trivia { kind: TriviaKind::BlockComment, span: Span { low: 14, high: 23 } },
+            Trivia { kind: TriviaKind::Whitespace, span: Span { low: 23, high: 24 } },
+        ][..]);
+        assert!(tokens.tokens[5].leading.is_empty());
+    }
+
+    #[test]
+    fn rename() {
+        let source = b"var count; // count things\ncount = count+1";
+        let tokens = SyntaxTokens::lex(source, Version::GameMaker8);
+
+        let count = Token::Ident(Symbol::intern(b"count"));
+        let renamed = tokens.rewrite(|token| match token.token {
+            token if token == count => Some(&b"total"[..]),
+            _ => None,
+        });
+        assert_eq!(&renamed[..], &b"var total; // count things\ntotal = total+1"[..]);
+
+        let position = source.len() - 2;
+        let plus = Token::BinOp(BinOp::Plus);
+        assert_eq!(tokens.token_at(position).map(|token| token.token), Some(plus));
+        assert_eq!(tokens.token_at(position - 1).map(|token| token.span), Some(Span {
+            low: position - 5, high: position,
+        }));
+        let one = Token::Real(Symbol::intern(b"1"));
+        assert_eq!(tokens.token_at(source.len()).map(|token| token.token), Some(one));
+        assert_eq!(tokens.token_at(source.len() + 1), None);
+    }
+}
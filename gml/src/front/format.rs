@@ -0,0 +1,784 @@
+use std::cmp;
+
+use project::Version;
+
+use crate::ErrorPrinter;
+use crate::front::{ast, Lexer, Parser, Span};
+use crate::front::token::Token;
+
+/// Reformat a script or code action into canonical GML, keeping its comments.
+///
+/// Literals are copied from the source as written, so `$FF` stays in hex and strings keep their
+/// quotes and escapes. Sources with syntax errors are reported to `errors` and left alone.
+pub fn format(
+    source: &[u8], version: Version, errors: &mut ErrorPrinter<'_>
+) -> Option<Vec<u8>> {
+    let count = errors.count;
+    let (program, span) = Parser::new(Lexer::new(source, 0, version), errors).parse_program();
+    if errors.count > count {
+        return None;
+    }
+
+    // The parser drops comments, so collect them with a separate pass over the tokens.
+    let mut lexer = Lexer::new(source, 0, version);
+    while lexer.read_token().0 != Token::Eof {}
+
+    let crlf = source.windows(2).any(|pair| pair == b"\r\n");
+    let newline: &[u8] = if crlf { b"\r\n" } else { b"\n" };
+    let mut formatter = Formatter {
+        source, comments: lexer.comments(), newline,
+        out: Vec::new(), indent: 0, line_start: true, last: 0, block_start: true,
+    };
+
+    let statements = match program {
+        ast::Stmt::Block(ref statements) => statements,
+        _ => unreachable!(),
+    };
+    if source.get(span.low) == Some(&b'{') {
+        formatter.block(statements, span.low + 1, span.high, false);
+    } else {
+        for &(ref stmt, span) in &statements[..] {
+            formatter.statement(stmt, span, false);
+        }
+    }
+    formatter.comments_before(source.len());
+    formatter.end_line();
+
+    let mut out = formatter.out;
+    if out.ends_with(newline) {
+        out.truncate(out.len() - newline.len());
+    }
+    Some(out)
+}
+
+struct Formatter<'s> {
+    source: &'s [u8],
+    /// The comments not yet written out, in source order.
+    comments: &'s [Span],
+    newline: &'static [u8],
+
+    out: Vec<u8>,
+    indent: usize,
+    /// Whether nothing has been written to the current line yet.
+    line_start: bool,
+    /// The end of the last statement or comment written out.
+    last: usize,
+    /// Whether a blank line here would be out of place, at the start of a block.
+    block_start: bool,
+}
+
+impl<'s> Formatter<'s> {
+    fn statement(&mut self, stmt: &ast::Stmt, span: Span, switch: bool) {
+        self.comments_before(span.low);
+        self.end_line();
+        self.gap(span.low);
+
+        let case = if let ast::Stmt::Case(_) = *stmt { true } else { false };
+        let indent = if switch && !case { 1 } else { 0 };
+        self.indent += indent;
+        self.stmt(stmt, span);
+        self.indent -= indent;
+
+        // Comments inside a statement, or after it on the same line, trail behind it. A `for`
+        // statement's span stops short of its body, so take whichever ends later.
+        let high = cmp::max(self.last, span.high);
+        self.last = high;
+        while let Some(&comment) = self.comments.first() {
+            if comment.low >= high && self.has_newline(high, comment.low) {
+                break;
+            }
+            self.comment(comment, true);
+        }
+        self.end_line();
+    }
+
+    fn stmt(&mut self, stmt: &ast::Stmt, span: Span) {
+        if self.simple(stmt) {
+            self.write(b";");
+            return;
+        }
+
+        match *stmt {
+            ast::Stmt::Macro((name, _), ref value) => {
+                self.write(b"#macro ");
+                self.write(&name[..]);
+                self.write(b" ");
+                self.expr(value);
+            }
+
+            ast::Stmt::Enum((name, _), ref members) => {
+                self.write(b"enum ");
+                self.write(&name[..]);
+                self.write(b" {");
+                for (i, &((member, _), ref value)) in members.iter().enumerate() {
+                    self.write(if i == 0 { &b" "[..] } else { b", " });
+                    self.write(&member[..]);
+                    if let Some(ref value) = *value {
+                        self.write(b" = ");
+                        self.expr(value);
+                    }
+                }
+                self.write(b" }");
+            }
+
+            ast::Stmt::Block(ref statements) =>
+                self.block(statements, span.low + 1, span.high, false),
+
+            ast::Stmt::If(ref expr, ref true_branch, ref false_branch) => {
+                self.write(b"if (");
+                self.expr(expr);
+                self.write(b")");
+                let header = self.header_end(expr.1.high);
+                self.body(&true_branch.0, true_branch.1, header);
+
+                if let Some(box (ref false_branch, false_span)) = *false_branch {
+                    self.comments_before(false_span.low);
+                    self.write(if self.line_start { &b"else"[..] } else { b" else" });
+                    match *false_branch {
+                        ast::Stmt::If(..) => {
+                            self.write(b" ");
+                            self.stmt(false_branch, false_span);
+                        }
+                        _ => self.body(false_branch, false_span, false_span.low),
+                    }
+                }
+            }
+
+            ast::Stmt::Repeat(ref expr, ref body) => self.header(b"repeat", expr, body),
+            ast::Stmt::While(ref expr, ref body) => self.header(b"while", expr, body),
+            ast::Stmt::With(ref expr, ref body) => self.header(b"with", expr, body),
+
+            ast::Stmt::Do(ref body, ref expr) => {
+                self.write(b"do");
+                self.body(&body.0, body.1, span.low);
+                self.write(if self.line_start { &b"until ("[..] } else { b" until (" });
+                self.expr(expr);
+                self.write(b");");
+            }
+
+            ast::Stmt::For(ref init, ref cond, ref next, ref body) => {
+                self.write(b"for (");
+                if !self.simple(&init.0) {
+                    self.stmt(&init.0, init.1);
+                }
+                self.write(b"; ");
+                self.expr(cond);
+                self.write(b"; ");
+                if !self.simple(&next.0) {
+                    self.stmt(&next.0, next.1);
+                }
+                self.write(b")");
+                let header = self.header_end(cmp::max(cond.1.high, next.1.high));
+                self.body(&body.0, body.1, header);
+            }
+
+            ast::Stmt::Switch(ref expr, ref statements) => {
+                self.write(b"switch (");
+                self.expr(expr);
+                self.write(b")");
+                let header = self.header_end(expr.1.high);
+                let open = self.skip_comments(header);
+                self.header_comments(header, open);
+                if !self.line_start {
+                    self.write(b" ");
+                }
+                self.block(statements, header, span.high, true);
+            }
+
+            ast::Stmt::Jump(ast::Jump::Break) => self.write(b"break;"),
+            ast::Stmt::Jump(ast::Jump::Continue) => self.write(b"continue;"),
+            ast::Stmt::Jump(ast::Jump::Exit) => self.write(b"exit;"),
+
+            ast::Stmt::Return(ref expr) => {
+                self.write(b"return ");
+                self.expr(expr);
+                self.write(b";");
+            }
+
+            ast::Stmt::Case(Some(ref expr)) => {
+                self.write(b"case ");
+                self.expr(expr);
+                self.write(b":");
+            }
+            ast::Stmt::Case(None) => self.write(b"default:"),
+
+            ast::Stmt::Error(_) |
+            ast::Stmt::Assign(..) | ast::Stmt::Invoke(_) |
+            ast::Stmt::Increment(..) | ast::Stmt::Declare(..) => unreachable!(),
+        }
+    }
+
+    /// Write a statement that can appear in a `for` header, without its semicolon.
+    fn simple(&mut self, stmt: &ast::Stmt) -> bool {
+        match *stmt {
+            ast::Stmt::Assign((op, _), ref place, ref value) => {
+                self.expr(place);
+                self.write(match op {
+                    None => &b" = "[..],
+                    Some(ast::Op::Add) => b" += ",
+                    Some(ast::Op::Subtract) => b" -= ",
+                    Some(ast::Op::Multiply) => b" *= ",
+                    Some(ast::Op::Divide) => b" /= ",
+                    Some(ast::Op::BitAnd) => b" &= ",
+                    Some(ast::Op::BitOr) => b" |= ",
+                    Some(ast::Op::BitXor) => b" ^= ",
+                });
+                self.expr(value);
+            }
+
+            ast::Stmt::Invoke(ref call) => self.call(call),
+
+            ast::Stmt::Increment((op, _), ref place) => {
+                self.expr(place);
+                self.write(increment(op));
+            }
+
+            ast::Stmt::Declare(declare, ref names) => {
+                self.write(match declare {
+                    ast::Declare::Local => &b"var "[..],
+                    ast::Declare::Global => b"globalvar ",
+                });
+                for (i, &((name, _), ref value)) in names.iter().enumerate() {
+                    if i > 0 {
+                        self.write(b", ");
+                    }
+                    self.write(&name[..]);
+                    if let Some(ref value) = *value {
+                        self.write(b" = ");
+                        self.expr(value);
+                    }
+                }
+            }
+
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn header(&mut self, keyword: &[u8], expr: &(ast::Expr, Span), body: &(ast::Stmt, Span)) {
+        self.write(keyword);
+        self.write(b" (");
+        self.expr(expr);
+        self.write(b")");
+        let header = self.header_end(expr.1.high);
+        self.body(&body.0, body.1, header);
+    }
+
+    /// Write the comments inside a statement header, which ends at `header`, or after it on the
+    /// same line before `body`. The header is written on one line, so they all trail it.
+    fn header_comments(&mut self, header: usize, body: usize) {
+        while let Some(&comment) = self.comments.first() {
+            let end = cmp::max(self.last, header);
+            let inside = comment.low < header;
+            let same_line = comment.low < body && !self.has_newline(end, comment.low);
+            if !inside && !same_line {
+                break;
+            }
+            self.comment(comment, true);
+        }
+        self.last = cmp::max(self.last, header);
+    }
+
+    /// The end of a statement header whose last expression ends at `position`, past any comments
+    /// and closing parenthesis that follow it.
+    fn header_end(&self, position: usize) -> usize {
+        let end = self.skip_comments(position);
+        if self.source.get(end) == Some(&b')') { end + 1 } else { position }
+    }
+
+    /// The position of the first token at or after `position`, past whitespace and comments.
+    fn skip_comments(&self, position: usize) -> usize {
+        let mut end = position;
+        let mut comments = self.comments.iter().skip_while(|comment| comment.low < position);
+        loop {
+            while matches!(self.source.get(end), Some(c) if c.is_ascii_whitespace()) {
+                end += 1;
+            }
+            match comments.next() {
+                Some(comment) if comment.low == end => end = comment.high,
+                _ => break,
+            }
+        }
+        end
+    }
+
+    /// Write the body of a control flow statement, on its own line unless it is a block.
+    ///
+    /// `header` is the end of the statement's header.
+    fn body(&mut self, stmt: &ast::Stmt, span: Span, header: usize) {
+        self.header_comments(header, span.low);
+        match *stmt {
+            ast::Stmt::Block(ref statements) => {
+                // A comment between the header and the block goes before the opening brace.
+                self.comments_before(span.low);
+                if !self.line_start {
+                    self.write(b" ");
+                }
+                self.block(statements, span.low + 1, span.high, false);
+            }
+            _ => {
+                self.end_line();
+                self.indent += 1;
+                self.block_start = true;
+                self.statement(stmt, span, false);
+                self.indent -= 1;
+            }
+        }
+    }
+
+    fn block(&mut self, statements: &[(ast::Stmt, Span)], open: usize, high: usize, switch: bool) {
+        self.write(b"{");
+        self.last = open;
+        self.indent += 1;
+        self.block_start = true;
+        for &(ref stmt, span) in statements {
+            self.statement(stmt, span, switch);
+        }
+        self.comments_before(high);
+        self.end_line();
+        self.indent -= 1;
+        self.write(b"}");
+        self.last = high;
+        self.block_start = false;
+    }
+
+    fn expr(&mut self, &(ref expr, span): &(ast::Expr, Span)) {
+        match *expr {
+            ast::Expr::Value(ast::Value::Ident(symbol)) => self.write(&symbol[..]),
+            ast::Expr::Value(ast::Value::Real(_)) | ast::Expr::Value(ast::Value::String(_)) => {
+                let source = self.source;
+                self.write(&source[span.low..span.high]);
+            }
+
+            ast::Expr::Unary((op, _), box ref operand) => {
+                self.write(match op {
+                    ast::Unary::Positive => &b"+"[..],
+                    ast::Unary::Negate => b"-",
+                    ast::Unary::Invert => b"!",
+                    ast::Unary::BitInvert => b"~",
+                });
+                let sign = op == ast::Unary::Positive || op == ast::Unary::Negate;
+                self.operand(operand, precedence(&operand.0) < 7 || sign && is_signed(&operand.0));
+            }
+
+            ast::Expr::Binary((op, _), box ref left, box ref right) => {
+                let op_precedence = binary_precedence(op);
+                self.operand(left, precedence(&left.0) < op_precedence);
+                self.write(b" ");
+                self.write(binary(op));
+                self.write(b" ");
+                self.operand(right, precedence(&right.0) <= op_precedence);
+            }
+
+            ast::Expr::Field(box ref base, (field, _)) => {
+                let term = match base.0 {
+                    ast::Expr::Value(ast::Value::Ident(_)) | ast::Expr::Call(_) |
+                    ast::Expr::Field(..) | ast::Expr::Index(..) | ast::Expr::Accessor(..) => true,
+                    _ => false,
+                };
+                self.operand(base, !term);
+                self.write(b".");
+                self.write(&field[..]);
+            }
+
+            ast::Expr::Index(box ref base, ref args) => {
+                self.expr(base);
+                self.write(b"[");
+                self.args(args);
+                self.write(b"]");
+            }
+
+            ast::Expr::Accessor(accessor, box ref base, ref args) => {
+                self.expr(base);
+                self.write(match accessor {
+                    ast::Accessor::List => &b"[| "[..],
+                    ast::Accessor::Map => b"[? ",
+                    ast::Accessor::Grid => b"[# ",
+                    ast::Accessor::Array => b"[@ ",
+                });
+                self.args(args);
+                self.write(b"]");
+            }
+
+            ast::Expr::Call(ref call) => self.call(call),
+
+            ast::Expr::Prefix((op, _), box ref operand) => {
+                self.write(increment(op));
+                self.operand(operand, precedence(&operand.0) < 7 || is_signed(&operand.0));
+            }
+
+            ast::Expr::Postfix((op, _), box ref operand) => {
+                self.expr(operand);
+                self.write(increment(op));
+            }
+
+            ast::Expr::Ternary(box ref cond, box ref then, box ref else_) => {
+                self.operand(cond, precedence(&cond.0) == 0);
+                self.write(b" ? ");
+                self.expr(then);
+                self.write(b" : ");
+                self.expr(else_);
+            }
+
+            ast::Expr::Error => unreachable!(),
+        }
+    }
+
+    fn operand(&mut self, expr: &(ast::Expr, Span), parens: bool) {
+        if parens {
+            self.write(b"(");
+        }
+        self.expr(expr);
+        if parens {
+            self.write(b")");
+        }
+    }
+
+    fn call(&mut self, &ast::Call((name, _), ref args): &ast::Call) {
+        self.write(&name[..]);
+        self.write(b"(");
+        self.args(args);
+        self.write(b")");
+    }
+
+    fn args(&mut self, args: &[(ast::Expr, Span)]) {
+        for (i, arg) in args.iter().enumerate() {
+            if i > 0 {
+                self.write(b", ");
+            }
+            self.expr(arg);
+        }
+    }
+
+    fn comments_before(&mut self, position: usize) {
+        while let Some(&comment) = self.comments.first() {
+            if comment.low >= position {
+                break;
+            }
+            let trailing = !self.line_start &&
+                self.last <= comment.low && !self.has_newline(self.last, comment.low);
+            self.comment(comment, trailing);
+        }
+    }
+
+    /// Write a comment, either at the end of the current line or on a line of its own.
+    fn comment(&mut self, comment: Span, trailing: bool) {
+        let source = self.source;
+        let mut text = &source[comment.low..comment.high];
+        while let Some((last, rest)) = text.split_last() {
+            if !last.is_ascii_whitespace() {
+                break;
+            }
+            text = rest;
+        }
+
+        if trailing {
+            self.write(b" ");
+        } else {
+            self.end_line();
+            self.gap(comment.low);
+        }
+        self.write(text);
+        if !trailing || text.starts_with(b"//") {
+            self.end_line();
+        }
+
+        self.last = comment.high;
+        self.block_start = false;
+        self.comments = &self.comments[1..];
+    }
+
+    /// Keep a single blank line where the source had one or more.
+    fn gap(&mut self, position: usize) {
+        let source = &self.source[cmp::min(self.last, position)..position];
+        let newlines = source.iter().filter(|&&c| c == b'\n').count();
+        if !self.block_start && newlines > 1 {
+            self.out.extend_from_slice(self.newline);
+        }
+        self.block_start = false;
+    }
+
+    fn has_newline(&self, low: usize, high: usize) -> bool {
+        low < high && self.source[low..high].contains(&b'\n')
+    }
+
+    fn write(&mut self, text: &[u8]) {
+        if self.line_start {
+            self.out.extend(std::iter::repeat(b' ').take(4 * self.indent));
+            self.line_start = false;
+        }
+        self.out.extend_from_slice(text);
+    }
+
+    fn end_line(&mut self) {
+        if !self.line_start {
+            self.out.extend_from_slice(self.newline);
+            self.line_start = true;
+        }
+    }
+}
+
+/// The precedence the parser gives an expression, with terms binding tighter than any operator.
+fn precedence(expr: &ast::Expr) -> usize {
+    match *expr {
+        ast::Expr::Binary((op, _), ..) => binary_precedence(op),
+        ast::Expr::Ternary(..) => 0,
+        _ => 8,
+    }
+}
+
+fn binary_precedence(op: ast::Binary) -> usize {
+    use crate::front::ast::Binary::*;
+    use crate::front::ast::Op::*;
+
+    match op {
+        Op(Multiply) | Op(Divide) | Div | Mod => 6,
+        Op(Add) | Op(Subtract) => 5,
+        ShiftLeft | ShiftRight => 4,
+        Op(BitAnd) | Op(BitXor) | Op(BitOr) => 3,
+        Lt | Le | Eq | Ne | Ge | Gt => 2,
+        And | Or | Xor => 1,
+    }
+}
+
+/// Whether an expression starts with `+` or `-`, which must not run into a preceding sign.
+fn is_signed(expr: &ast::Expr) -> bool {
+    match *expr {
+        ast::Expr::Unary((ast::Unary::Positive, _), _) |
+        ast::Expr::Unary((ast::Unary::Negate, _), _) |
+        ast::Expr::Prefix(..) => true,
+        _ => false,
+    }
+}
+
+fn binary(op: ast::Binary) -> &'static [u8] {
+    use crate::front::ast::Binary::*;
+    use crate::front::ast::Op::*;
+
+    match op {
+        Lt => b"<",
+        Le => b"<=",
+        Eq => b"==",
+        Ne => b"!=",
+        Ge => b">=",
+        Gt => b">",
+
+        And => b"&&",
+        Or => b"||",
+        Xor => b"^^",
+
+        Op(Add) => b"+",
+        Op(Subtract) => b"-",
+        Op(Multiply) => b"*",
+        Op(Divide) => b"/",
+        Op(BitAnd) => b"&",
+        Op(BitOr) => b"|",
+        Op(BitXor) => b"^",
+
+        Div => b"div",
+        Mod => b"mod",
+
+        ShiftLeft => b"<<",
+        ShiftRight => b">>",
+    }
+}
+
+fn increment(op: ast::Op) -> &'static [u8] {
+    match op {
+        ast::Op::Add => b"++",
+        _ => b"--",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use project::Version;
+    use crate::{FunctionDisplay, ErrorPrinter};
+    use crate::symbol::Symbol;
+    use crate::front::{Lexer, Parser, Lines};
+
+    fn format(source: &str, version: Version) -> String {
+        let lines = Lines::from_code(source.as_bytes());
+        let script = Symbol::intern(b"<test>");
+        let name = FunctionDisplay::Script { script };
+        let mut errors = ErrorPrinter::new(name, &lines, Vec::new());
+        let output = super::format(source.as_bytes(), version, &mut errors)
+            .unwrap_or_else(|| panic!("{:?}", errors.sink));
+        String::from_utf8(output).unwrap()
+    }
+
+    /// Parse a program and print its AST, without the spans that formatting moves around.
+    fn parse(source: &str, version: Version) -> String {
+        let lines = Lines::from_code(source.as_bytes());
+        let script = Symbol::intern(b"<test>");
+        let name = FunctionDisplay::Script { script };
+        let mut errors = ErrorPrinter::new(name, &lines, Vec::new());
+        let program = Parser::new(Lexer::new(source.as_bytes(), 0, version), &mut errors)
+            .parse_program();
+        assert_eq!(errors.count, 0, "{:?}", errors.sink);
+
+        let mut debug = format!("{:?}", program);
+        while let Some(low) = debug.find("Span {") {
+            let high = low + debug[low..].find('}').unwrap() + 1;
+            debug.replace_range(low..high, "_");
+        }
+        debug
+    }
+
+    fn check(source: &str, version: Version) -> String {
+        let output = format(source, version);
+        assert_eq!(format(&output, version), output, "formatting is not idempotent");
+        assert_eq!(parse(&output, version), parse(source, version));
+        output
+    }
+
+    #[test]
+    fn layout() {
+        let source = "\
+            {var a,b;a:=1 b = $FF if a=b then show_message('say \"hi\"')else begin \
+            repeat(3)b+=1 end;while not a a+=1; do a-=1 until a<0 \
+            for(i=0;i<10;i+=1){}switch a{case 1:case -2:exit default:break}\
+            with(other)x=instance_nearest(x,y,obj).y return -(-a)}";
+        assert_eq!(check(source, Version::GameMaker8), "\
+{
+    var a, b;
+    a = 1;
+    b = $FF;
+    if (a == b)
+        show_message('say \"hi\"');
+    else {
+        repeat (3)
+            b += 1;
+    }
+    while (!a)
+        a += 1;
+    do
+        a -= 1;
+    until (a < 0);
+    for (i = 0; i < 10; i += 1) {
+    }
+    switch (a) {
+        case 1:
+        case -2:
+            exit;
+        default:
+            break;
+    }
+    with (other)
+        x = instance_nearest(x, y, obj).y;
+    return -(-a);
+}");
+    }
+
+    #[test]
+    fn comments() {
+        let source = "\
+// leading
+
+a = 1; // trailing
+/* own line */
+
+
+b = 2; c = 3
+if (a) { // opener
+    // inside
+    d = /* inline */ 4;
+    // before close
+}
+else e = 5 // after else
+/* last */";
+        assert_eq!(check(source, Version::GameMaker8), "\
+// leading
+
+a = 1; // trailing
+/* own line */
+
+b = 2;
+c = 3;
+if (a) { // opener
+    // inside
+    d = 4; /* inline */
+    // before close
+} else
+    e = 5; // after else
+/* last */");
+    }
+
+    #[test]
+    fn comment_before_block() {
+        let source = "if (a) // c\n{ x = 1 }\nwhile (b) /* d */ {}\nwith (c) // e\n// f\n{}";
+        assert_eq!(check(source, Version::GameMaker8), "\
+if (a) // c
+{
+    x = 1;
+}
+while (b) /* d */ {
+}
+with (c) // e
+// f
+{
+}");
+    }
+
+    /// Comments inside a statement header stay with the header.
+    #[test]
+    fn comment_in_header() {
+        let source = "\
+            while (/* x */ a) {}\n\
+            for (i = 0; /* y */ i < 3; i += 1) {}\n\
+            if (a /* z */) b = 1\n\
+            switch (/* s */ a) { case 1: break }";
+        assert_eq!(check(source, Version::GameMaker8), "\
+while (a) /* x */ {
+}
+for (i = 0; i < 3; i += 1) /* y */ {
+}
+if (a) /* z */
+    b = 1;
+switch (a) /* s */ {
+    case 1:
+        break;
+}");
+    }
+
+    #[test]
+    fn parentheses() {
+        let source = "\
+            a = (b - c) - (d - e) * -(f + g) / (h mod i);\
+            a = -(-b) + - -c - +(+d) + ++e - --f;\
+            a = (b + c).d + (-e).f + g().h[0].i;\
+            a = !(b && c) || d ^^ (e or f) and not g;\
+            a = b << (c + d) & (e | f) == (g < h);";
+        check(source, Version::GameMaker8);
+        check(source, Version::GameMakerStudio);
+
+        let source = "a = (b ? c : d) ? (e || f) : g ? h : i; a = (b ? c : d) + 1;";
+        check(source, Version::GameMakerStudio);
+    }
+
+    #[test]
+    fn studio() {
+        let source = "#macro SIZE 10\r\n\
+            enum color { red, green = 5, blue, }\r\n\
+            var s = \"a\\\"b\\n\", t = list[| 0] + map[? \"k\"] + grid[# 1, 2];\r\n\
+            arr[@ 0] = s; i++";
+        assert_eq!(check(source, Version::GameMakerStudio), "\
+#macro SIZE 10\r\n\
+enum color { red, green = 5, blue }\r\n\
+var s = \"a\\\"b\\n\", t = list[| 0] + map[? \"k\"] + grid[# 1, 2];\r\n\
+arr[@ 0] = s;\r\n\
+i++;");
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let source = b"a = ;";
+        let lines = Lines::from_code(source);
+        let script = Symbol::intern(b"<test>");
+        let name = FunctionDisplay::Script { script };
+        let mut errors = ErrorPrinter::new(name, &lines, Vec::new());
+        assert_eq!(super::format(source, Version::GameMaker8, &mut errors), None);
+        assert_eq!(errors.sink.len(), 1);
+    }
+}
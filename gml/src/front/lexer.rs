@@ -1,15 +1,29 @@
+use project::Version;
+
 use crate::symbol::Symbol;
 use crate::front::Span;
-use crate::front::token::{Token, BinOp, Delim};
+use crate::front::token::{Token, BinOp, Delim, Accessor};
 
 pub struct Lexer<'s> {
     source: &'s [u8],
     position: usize,
+    version: Version,
+
+    /// The comments skipped so far, which the parser otherwise never sees.
+    comments: Vec<Span>,
 }
 
 impl<'s> Lexer<'s> {
-    pub fn new(source: &'s [u8], position: usize) -> Lexer<'s> {
-        Lexer { source, position }
+    pub fn new(source: &'s [u8], position: usize, version: Version) -> Lexer<'s> {
+        Lexer { source, position, version, comments: Vec::new() }
+    }
+
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    pub fn comments(&self) -> &[Span] {
+        &self.comments
     }
 
     pub fn read_token(&mut self) -> (Token, Span) {
@@ -45,6 +59,7 @@ impl<'s> Lexer<'s> {
                 Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') => (),
 
                 Some(b'/') if self.next_char() == Some(b'/') => {
+                    let low = self.position;
                     self.advance_byte();
                     self.advance_byte();
 
@@ -54,9 +69,12 @@ impl<'s> Lexer<'s> {
                         }
                         self.advance_byte();
                     }
+
+                    self.comments.push(Span { low, high: self.position });
                 }
 
                 Some(b'/') if self.next_char() == Some(b'*') => {
+                    let low = self.position;
                     self.advance_byte();
                     self.advance_byte();
 
@@ -71,6 +89,10 @@ impl<'s> Lexer<'s> {
                         }
                         self.advance_byte();
                     }
+
+                    // The outer loop advances past the final `/`, if there is one.
+                    let high = self.position + self.current().map_or(0, |_| 1);
+                    self.comments.push(Span { low, high });
                 }
 
                 _ => break,
@@ -139,6 +161,10 @@ impl<'s> Lexer<'s> {
         self.advance_byte();
 
         while self.current() != delim && self.current() != None {
+            // GMS strings may escape their delimiter.
+            if self.version >= Version::GameMakerStudio && self.current() == Some(b'\\') {
+                self.advance_byte();
+            }
             self.advance_byte();
         }
 
@@ -153,6 +179,13 @@ impl<'s> Lexer<'s> {
         match self.advance_byte() {
             Some(b'(') => Token::OpenDelim(Delim::Paren),
             Some(b')') => Token::CloseDelim(Delim::Paren),
+            Some(b'[') if self.version >= Version::GameMakerStudio => match self.current() {
+                Some(b'|') => { self.advance_byte(); Token::OpenAccessor(Accessor::List) }
+                Some(b'?') => { self.advance_byte(); Token::OpenAccessor(Accessor::Map) }
+                Some(b'#') => { self.advance_byte(); Token::OpenAccessor(Accessor::Grid) }
+                Some(b'@') => { self.advance_byte(); Token::OpenAccessor(Accessor::Array) }
+                _ => Token::OpenDelim(Delim::Bracket),
+            },
             Some(b'[') => Token::OpenDelim(Delim::Bracket),
             Some(b']') => Token::CloseDelim(Delim::Bracket),
             Some(b'{') => Token::OpenDelim(Delim::Brace),
@@ -178,6 +211,14 @@ impl<'s> Lexer<'s> {
                 _ => Token::Gt
             },
 
+            Some(b'+') if self.version >= Version::GameMakerStudio => match self.current() {
+                Some(b'+') => { self.advance_byte(); Token::PlusPlus }
+                _ => self.scan_binop(BinOp::Plus)
+            },
+            Some(b'-') if self.version >= Version::GameMakerStudio => match self.current() {
+                Some(b'-') => { self.advance_byte(); Token::MinusMinus }
+                _ => self.scan_binop(BinOp::Minus)
+            },
             Some(b'+') => self.scan_binop(BinOp::Plus),
             Some(b'-') => self.scan_binop(BinOp::Minus),
             Some(b'*') => self.scan_binop(BinOp::Star),
@@ -205,12 +246,30 @@ impl<'s> Lexer<'s> {
                 Some(b'=') => { self.advance_byte(); Token::ColonEq }
                 _ => Token::Colon
             },
+            Some(b'?') if self.version >= Version::GameMakerStudio => Token::Question,
+            Some(b'#') if self.version >= Version::GameMakerStudio => self.scan_directive(),
 
             Some(c) => Token::Unexpected(c),
             None => Token::Eof,
         }
     }
 
+    fn scan_directive(&mut self) -> Token {
+        if !is_ident_start(self.current()) {
+            return Token::Unexpected(b'#');
+        }
+
+        let source = &self.source[..];
+        let low = self.position;
+        while is_ident_continue(self.current()) {
+            self.advance_byte();
+        }
+        let high = self.position;
+
+        let symbol = Symbol::intern(&source[..high - low]);
+        Token::Directive(symbol)
+    }
+
     fn scan_binop(&mut self, op: BinOp) -> Token {
         if self.current() == Some(b'=') {
             self.advance_byte();
@@ -257,7 +316,7 @@ fn is_digit(c: Option<u8>) -> bool {
 fn is_operator(c: Option<u8>) -> bool {
     [
         Some(b'{'), Some(b'}'), Some(b'('), Some(b')'), Some(b'['), Some(b']'),
-        Some(b'.'), Some(b','), Some(b':'), Some(b';'),
+        Some(b'.'), Some(b','), Some(b':'), Some(b';'), Some(b'?'), Some(b'#'),
         Some(b'+'), Some(b'-'), Some(b'*'), Some(b'/'),
         Some(b'|'), Some(b'&'), Some(b'^'), Some(b'~'),
         Some(b'='), Some(b'<'), Some(b'>'),
@@ -289,7 +348,7 @@ mod tests {
 
     #[test]
     fn spans() {
-        let mut lexer = Lexer::new(b"/* comment */ var foo; foo = 3", 0);
+        let mut lexer = Lexer::new(b"/* comment */ var foo; foo = 3", 0, Version::GameMaker8);
 
         assert_eq!(lexer.read_token(), (keyword(b"var"), span(14, 17)));
         assert_eq!(lexer.read_token(), (ident(b"foo"), span(18, 21)));
@@ -299,4 +358,29 @@ mod tests {
         assert_eq!(lexer.read_token(), (real(b"3"), span(29, 30)));
         assert_eq!(lexer.read_token(), (Token::Eof, span(30, 30)));
     }
+
+    #[test]
+    fn studio() {
+        let source = br#"a[| i++] = b ? "\"" : --c"#;
+        let quote = Token::String(Symbol::intern(br#""\"""#));
+
+        let mut lexer = Lexer::new(source, 0, Version::GameMakerStudio);
+        assert_eq!(lexer.read_token(), (ident(b"a"), span(0, 1)));
+        assert_eq!(lexer.read_token(), (Token::OpenAccessor(Accessor::List), span(1, 3)));
+        assert_eq!(lexer.read_token(), (ident(b"i"), span(4, 5)));
+        assert_eq!(lexer.read_token(), (Token::PlusPlus, span(5, 7)));
+        assert_eq!(lexer.read_token(), (Token::CloseDelim(Delim::Bracket), span(7, 8)));
+        assert_eq!(lexer.read_token(), (Token::Eq, span(9, 10)));
+        assert_eq!(lexer.read_token(), (ident(b"b"), span(11, 12)));
+        assert_eq!(lexer.read_token(), (Token::Question, span(13, 14)));
+        assert_eq!(lexer.read_token(), (quote, span(15, 19)));
+        assert_eq!(lexer.read_token(), (Token::Colon, span(20, 21)));
+        assert_eq!(lexer.read_token(), (Token::MinusMinus, span(22, 24)));
+        assert_eq!(lexer.read_token(), (ident(b"c"), span(24, 25)));
+        assert_eq!(lexer.read_token(), (Token::Eof, span(25, 25)));
+
+        let mut lexer = Lexer::new(b"[|", 0, Version::GameMaker8);
+        assert_eq!(lexer.read_token(), (Token::OpenDelim(Delim::Bracket), span(0, 1)));
+        assert_eq!(lexer.read_token(), (Token::BinOp(BinOp::Pipe), span(1, 2)));
+    }
 }
@@ -1,9 +1,12 @@
+use std::cmp;
 use std::iter::{self, FromIterator};
+use std::ops::Range;
 
 use project::{action_kind, action_type};
 
 pub mod token;
 pub mod ast;
+pub mod cst;
 mod action_ast;
 
 mod lexer;
@@ -11,11 +14,15 @@ mod parser;
 mod action_parser;
 mod ssa;
 mod codegen;
+mod constant;
+mod format;
 
 pub use lexer::Lexer;
 pub use parser::Parser;
 pub use action_parser::ActionParser;
 pub use codegen::Codegen;
+pub use constant::{evaluate, Declarations};
+pub use format::format;
 
 /// A range of positions in an event or script.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
@@ -35,7 +42,7 @@ pub struct Span {
 ///
 /// Converting an absolute index (relative to a whole event) into a local index (relative to the
 /// parent item) works by subtracting the absolute index of the parent item's first child.
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Lines {
     /// The byte offset of each action, and the absolute index of its first argument.
     pub actions: Vec<(usize, usize)>,
@@ -43,9 +50,13 @@ pub struct Lines {
     pub arguments: Vec<(usize, usize)>,
     /// The byte offset of each line. The absolute index of its first column has the same value.
     pub lines: Vec<usize>,
+    /// The text these byte offsets index into. For events, this is each action's name or code,
+    /// followed by its arguments, laid end to end.
+    pub source: Vec<u8>,
 }
 
 /// A user-facing position in an event or script.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub struct Position {
     pub action: Option<usize>,
     pub argument: Option<usize>,
@@ -58,36 +69,38 @@ impl Lines {
         let actions = Vec::default();
         let arguments = Vec::default();
         let lines = Vec::from_iter(Self::compute_lines(source, 0));
-        Lines { actions, arguments, lines }
+        let source = source.to_vec();
+        Lines { actions, arguments, lines, source }
     }
 
     pub fn from_actions(source: &[project::Action<'_>]) -> Lines {
         let mut actions = Vec::default();
         let mut arguments = Vec::default();
         let mut lines = Vec::default();
+        let mut text = Vec::default();
 
-        let mut offset = 0;
         for action in source {
-            actions.push((offset, arguments.len()));
+            actions.push((text.len(), arguments.len()));
 
-            offset += match (action.action_kind, action.action_type) {
-                (action_kind::NORMAL, action_type::FUNCTION) => action.name.len(),
-                (action_kind::NORMAL, action_type::CODE) => action.code.len(),
-                (_, _) => 0,
-            };
+            text.extend_from_slice(match (action.action_kind, action.action_type) {
+                (action_kind::NORMAL, action_type::FUNCTION) => action.name,
+                (action_kind::NORMAL, action_type::CODE) => action.code,
+                (_, _) => &[],
+            });
 
             for argument in &action.arguments[..action.parameters_used as usize] {
+                let offset = text.len();
                 arguments.push((offset, lines.len()));
 
                 if action.action_kind == action_kind::CODE {
                     lines.extend(Self::compute_lines(argument, offset));
                 }
 
-                offset += argument.len();
+                text.extend_from_slice(argument);
             }
         }
 
-        Lines { actions, arguments, lines }
+        Lines { actions, arguments, lines, source: text }
     }
 
     fn compute_lines(source: &[u8], offset: usize) -> impl Iterator<Item = usize> + '_ {
@@ -134,4 +147,24 @@ impl Lines {
 
         Position { action, argument, line, column }
     }
+
+    /// The byte range of the line containing `pos`, without its line terminator.
+    ///
+    /// Lines end at a newline or at the start of the next action or argument, whichever is first.
+    pub fn get_line(&self, pos: usize) -> Option<Range<usize>> {
+        let line = self.lines.binary_search(&pos)
+            .unwrap_or_else(|line| line.wrapping_sub(1));
+        let low = *self.lines.get(line)?;
+
+        let next_line = self.lines.get(line + 1).copied();
+        let next_action = self.actions.iter().map(|&(pos, _)| pos).find(|&pos| pos > low);
+        let next_argument = self.arguments.iter().map(|&(pos, _)| pos).find(|&pos| pos > low);
+        let high = iter::empty()
+            .chain(next_line).chain(next_action).chain(next_argument)
+            .fold(self.source.len(), cmp::min);
+
+        let text = &self.source[low..high];
+        let len = text.iter().rposition(|&b| b != b'\n' && b != b'\r').map_or(0, |i| i + 1);
+        Some(low..low + len)
+    }
 }
@@ -1,10 +1,12 @@
-use std::mem;
+use std::{fmt, mem};
 use std::str::{self, FromStr};
 
+use project::Version;
+
 use crate::ErrorPrinter;
 use crate::symbol::{Symbol, keyword};
 use crate::front::{ast, Lexer, Span};
-use crate::front::token::{Token, Delim, BinOp};
+use crate::front::token::{Token, Delim, BinOp, Accessor};
 
 pub struct Parser<'s, 'e, 'f> {
     reader: Lexer<'s>,
@@ -12,6 +14,9 @@ pub struct Parser<'s, 'e, 'f> {
 
     current: Token,
     span: Span,
+
+    /// Where the last syntax error was reported, so a single mistake is not reported twice.
+    last_error: Option<usize>,
 }
 
 impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
@@ -22,6 +27,8 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
 
             current: Token::Eof,
             span: Span { low: 0, high: 0 },
+
+            last_error: None,
         };
 
         parser.advance_token();
@@ -36,6 +43,16 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
             let mut stmts = vec![];
             let mut high = low;
             while self.current != Token::Eof {
+                // A stray end of block would otherwise stop every statement from making progress.
+                if
+                    self.current == Token::CloseDelim(Delim::Brace) ||
+                    self.current == Token::Keyword(keyword::End)
+                {
+                    self.unexpected(format_args!("{}", Token::Eof));
+                    self.advance_token();
+                    continue;
+                }
+
                 let (stmt, span) = self.parse_statement();
                 if let ast::Stmt::Error(_) = stmt {
                     self.skip_to_statement();
@@ -51,13 +68,23 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
         let high = span.high;
 
         if self.current != Token::Eof {
-            self.errors.error(self.span,
-                format_args!("unexpected {}; expected {}", self.current, Token::Eof));
+            self.unexpected(format_args!("{}", Token::Eof));
         }
 
         (stmt, Span { low: low, high: high })
     }
 
+    /// Parse a lone expression, such as the value of a project constant.
+    pub fn parse_constant(&mut self) -> (ast::Expr, Span) {
+        let expr = self.parse_expression(0);
+
+        if self.current != Token::Eof {
+            self.unexpected(format_args!("{}", Token::Eof));
+        }
+
+        expr
+    }
+
     fn parse_statement(&mut self) -> (ast::Stmt, Span) {
         let low = self.span.low;
 
@@ -77,6 +104,17 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
             Keyword(Break) | Keyword(Continue) | Keyword(Exit) => self.parse_jump(),
             Keyword(Return) => self.parse_return(),
             Keyword(Case) | Keyword(Default) => self.parse_case(),
+            Directive(_) => self.parse_directive(),
+            // `enum` is only reserved in GMS, where it cannot name a variable.
+            Ident(symbol) if
+                self.reader.version() >= Version::GameMakerStudio &&
+                symbol == Symbol::intern(b"enum")
+            => self.parse_enum(),
+            // A stray closing delimiter cannot start an expression, let alone a statement.
+            CloseDelim(Delim::Paren) | CloseDelim(Delim::Bracket) => {
+                self.unexpected(format_args!("statement"));
+                (ast::Stmt::Error(ast::Expr::Error), self.span)
+            }
             _ => self.parse_assign_or_invoke(),
         };
 
@@ -95,6 +133,8 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
 
         match place {
             ast::Expr::Call(call) => return (ast::Stmt::Invoke(call), left_span),
+            ast::Expr::Prefix(op, place) | ast::Expr::Postfix(op, place) =>
+                return (ast::Stmt::Increment(op, place), left_span),
             ast::Expr::Error => return (ast::Stmt::Error(place), left_span),
             _ => (),
         }
@@ -113,8 +153,7 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
             BinOpEq(Pipe) => Some(BitOr),
             BinOpEq(Caret) => Some(BitXor),
             _ => {
-                self.errors.error(self.span,
-                    format_args!("unexpected {}; expected assignment operator", self.current));
+                self.unexpected(format_args!("assignment operator"));
                 return (ast::Stmt::Error(place), left_span);
             }
         };
@@ -145,29 +184,108 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
                 _ => break,
             };
 
-            idents.push((symbol, span));
-
             self.advance_token();
+
+            let value = if
+                (self.current == Token::Eq || self.current == Token::ColonEq) &&
+                declare == ast::Declare::Local &&
+                self.reader.version() >= Version::GameMakerStudio
+            {
+                self.advance_token();
+                Some(self.parse_expression(0))
+            } else {
+                None
+            };
+
+            idents.push(((symbol, span), value));
+
             if let Token::Comma = self.current {
                 let _ = self.advance_token();
             }
         }
 
         if self.current == Token::Eq || self.current == Token::ColonEq {
-            // TODO: gms allows this
-            self.errors.error(self.span, format_args!("unexpected {}; expected ;", self.current));
+            self.unexpected(format_args!("{}", Token::Semicolon));
 
             self.advance_token();
             self.parse_expression(0);
         }
 
         let high = self.span.high;
-        self.expect(Token::Semicolon);
+        if !self.expect(Token::Semicolon) {
+            self.skip_to_statement();
+        }
 
         let span = Span { low: low, high: high };
         (ast::Stmt::Declare(declare, idents.into_boxed_slice()), span)
     }
 
+    fn parse_directive(&mut self) -> (ast::Stmt, Span) {
+        let low = self.span.low;
+        let (directive, directive_span) = self.advance_token();
+        match directive {
+            Token::Directive(symbol) if symbol == Symbol::intern(b"macro") => (),
+            _ => {
+                self.errors.error(directive_span, format_args!("unknown {}", directive));
+                return (ast::Stmt::Error(ast::Expr::Error), directive_span);
+            }
+        }
+
+        let (name, name_span) = match self.parse_ident() {
+            Some(name) => name,
+            None => return (ast::Stmt::Error(ast::Expr::Error), directive_span),
+        };
+
+        let (value, value_span) = self.parse_expression(0);
+        let high = value_span.high;
+
+        let span = Span { low: low, high: high };
+        (ast::Stmt::Macro((name, name_span), Box::new((value, value_span))), span)
+    }
+
+    fn parse_enum(&mut self) -> (ast::Stmt, Span) {
+        let low = self.span.low;
+        let (_, enum_span) = self.advance_token();
+
+        let (name, name_span) = match self.parse_ident() {
+            Some(name) => name,
+            None => return (ast::Stmt::Error(ast::Expr::Error), enum_span),
+        };
+
+        self.expect(Token::OpenDelim(Delim::Brace));
+
+        let mut members = vec![];
+        while let Token::Ident(member) = self.current {
+            let (_, member_span) = self.advance_token();
+
+            let value = if self.current == Token::Eq {
+                self.advance_token();
+                Some(self.parse_expression(0))
+            } else {
+                None
+            };
+
+            members.push(((member, member_span), value));
+
+            if self.current == Token::Comma {
+                self.advance_token();
+            } else {
+                break;
+            }
+        }
+
+        let high = self.span.high;
+        if self.current != Token::CloseDelim(Delim::Brace) {
+            let delim = Token::CloseDelim(Delim::Brace);
+            self.unexpected(format_args!("{} or {}", delim, Token::Comma));
+        } else {
+            self.advance_token();
+        }
+
+        let span = Span { low: low, high: high };
+        (ast::Stmt::Enum((name, name_span), members.into_boxed_slice()), span)
+    }
+
     fn parse_block(&mut self) -> (ast::Stmt, Span) {
         let low = self.span.low;
         self.advance_token();
@@ -188,9 +306,7 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
 
         let high;
         if self.current == Token::Eof {
-            let expected = Token::CloseDelim(Delim::Brace);
-            self.errors.error(self.span,
-                format_args!("unexpected {}; expected {}", self.current, expected));
+            self.unexpected(format_args!("{}", Token::CloseDelim(Delim::Brace)));
             high = self.span.low;
         } else {
             let (_, span) = self.advance_token();
@@ -314,9 +430,7 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
             self.current != Token::OpenDelim(Delim::Brace) &&
             self.current != Token::Keyword(keyword::Begin)
         {
-            let expected = Token::OpenDelim(Delim::Brace);
-            self.errors.error(self.span,
-                format_args!("unexpected {}; expected {}", self.current, expected));
+            self.unexpected(format_args!("{}", Token::OpenDelim(Delim::Brace)));
         }
 
         let (body, Span { high, .. }) = self.parse_block();
@@ -405,8 +519,7 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
                         let (_, field_span) = self.advance_token();
                         (field, field_span)
                     } else {
-                        self.errors.error(self.span,
-                            format_args!("unexpected {}; expected identifier", self.current));
+                        self.unexpected(format_args!("identifier"));
                         break;
                     };
                     let high = field_span.high;
@@ -416,6 +529,46 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
                     parens = false;
                 }
 
+                (&ast::Expr::Value(ast::Value::Ident(_)), Infix::Accessor(accessor)) |
+                (&ast::Expr::Field(..), Infix::Accessor(accessor))
+                if !parens => {
+                    let (args, high) = self.parse_args(Delim::Bracket);
+
+                    left = ast::Expr::Accessor(accessor, Box::new((left, left_span)), args);
+                    left_span = Span { low: low, high: high };
+                    parens = false;
+                }
+
+                (&ast::Expr::Value(ast::Value::Ident(_)), Infix::Postfix(op)) |
+                (&ast::Expr::Field(..), Infix::Postfix(op)) |
+                (&ast::Expr::Index(..), Infix::Postfix(op)) |
+                (&ast::Expr::Accessor(..), Infix::Postfix(op))
+                if !parens => {
+                    let (_, op_span) = self.advance_token();
+                    let high = op_span.high;
+
+                    left = ast::Expr::Postfix((op, op_span), Box::new((left, left_span)));
+                    left_span = Span { low: low, high: high };
+                    parens = true;
+                }
+
+                (_, Infix::Ternary) => {
+                    self.advance_token();
+
+                    let (then, then_span) = self.parse_expression(0);
+                    self.expect(Token::Colon);
+                    let (else_, else_span) = self.parse_expression(0);
+                    let high = else_span.high;
+
+                    left = ast::Expr::Ternary(
+                        Box::new((left, left_span)),
+                        Box::new((then, then_span)),
+                        Box::new((else_, else_span)),
+                    );
+                    left_span = Span { low: low, high: high };
+                    parens = true;
+                }
+
                 (_, Infix::Binary(op)) => {
                     let op_span = self.span;
                     self.advance_token();
@@ -474,7 +627,12 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
 
             String(symbol) => {
                 let (_, span) = self.advance_token();
-                let symbol = Symbol::intern(&symbol[1..symbol.len() - 1]);
+                let contents = &symbol[1..symbol.len() - 1];
+                let symbol = if self.reader.version() >= Version::GameMakerStudio {
+                    Symbol::intern(&unescape(contents))
+                } else {
+                    Symbol::intern(contents)
+                };
                 (ast::Expr::Value(ast::Value::String(symbol)), span, false)
             }
 
@@ -495,17 +653,36 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
                 (ast::Expr::Unary((op, op_span), Box::new((expr, expr_span))), span, true)
             }
 
+            PlusPlus | MinusMinus => {
+                let (current, op_span) = self.advance_token();
+                let op = match current {
+                    PlusPlus => ast::Op::Add,
+                    MinusMinus => ast::Op::Subtract,
+                    _ => unreachable!(),
+                };
+
+                let (expr, expr_span) = self.parse_term();
+                let high = expr_span.high;
+
+                let span = Span { low: low, high: high };
+                (ast::Expr::Prefix((op, op_span), Box::new((expr, expr_span))), span, true)
+            }
+
             OpenDelim(Delim::Paren) => {
                 let (_, _) = self.advance_token();
                 let (expr, expr_span) = self.parse_expression(0);
-                self.expect(CloseDelim(Delim::Paren));
+                if !self.expect(CloseDelim(Delim::Paren)) {
+                    self.skip_to_close(Delim::Paren, false);
+                    if self.current == CloseDelim(Delim::Paren) {
+                        self.advance_token();
+                    }
+                }
 
                 (expr, expr_span, true)
             }
 
             _ => {
-                self.errors.error(self.span,
-                    format_args!("unexpected {}; expected expression", self.current));
+                self.unexpected(format_args!("expression"));
 
                 let span = Span { low: low, high: low };
                 (ast::Expr::Error, span, false)
@@ -516,10 +693,17 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
     fn parse_args(&mut self, delim: Delim) -> (Box<[(ast::Expr, Span)]>, usize) {
         self.advance_token();
 
+        let close = Token::CloseDelim(delim);
         let mut args = vec![];
-        while self.current != Token::CloseDelim(delim) && self.current != Token::Eof {
+        while self.current != close && self.current != Token::Eof {
             args.push(self.parse_expression(0));
 
+            // Skip the rest of a malformed argument, but keep going with the next one.
+            if self.current != Token::Comma && self.current != close {
+                self.unexpected(format_args!("{} or {}", close, Token::Comma));
+                self.skip_to_close(delim, true);
+            }
+
             if self.current == Token::Comma {
                 self.advance_token();
             } else {
@@ -528,11 +712,8 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
         }
 
         let high = self.span.high;
-        if self.current != Token::CloseDelim(delim) {
-            let delim = Token::CloseDelim(delim);
-            let comma = Token::Comma;
-            self.errors.error(self.span,
-                format_args!("unexpected {}; expected {} or {}", self.current, delim, comma));
+        if self.current != close {
+            self.unexpected(format_args!("{} or {}", close, Token::Comma));
         } else {
             self.advance_token();
         }
@@ -540,6 +721,16 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
         (args.into_boxed_slice(), high)
     }
 
+    fn parse_ident(&mut self) -> Option<(Symbol, Span)> {
+        if let Token::Ident(symbol) = self.current {
+            let (_, span) = self.advance_token();
+            Some((symbol, span))
+        } else {
+            self.unexpected(format_args!("identifier"));
+            None
+        }
+    }
+
     fn parse_term(&mut self) -> (ast::Expr, Span) {
         self.parse_expression(7)
     }
@@ -549,12 +740,22 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
             self.advance_token();
             true
         } else {
-            self.errors.error(self.span,
-                format_args!("unexpected {}; expected {}", self.current, token));
+            self.unexpected(format_args!("{}", token));
             false
         }
     }
 
+    /// Report an error at the current token, unless one has already been reported there.
+    fn unexpected(&mut self, expected: fmt::Arguments<'_>) {
+        if self.last_error == Some(self.span.low) {
+            return;
+        }
+
+        self.last_error = Some(self.span.low);
+        self.errors.error(self.span,
+            format_args!("unexpected {}; expected {}", self.current, expected));
+    }
+
     fn advance_token(&mut self) -> (Token, Span) {
         let (token, span) = self.reader.read_token();
 
@@ -585,9 +786,10 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
                 Keyword(Global) | Keyword(Local) |
                 OpenDelim(Delim::Paren) |
                 Keyword(Var) | Keyword(GlobalVar) |
+                PlusPlus | MinusMinus |
 
                 // Possible statement:
-                OpenDelim(Delim::Brace) | Keyword(Begin) |
+                OpenDelim(Delim::Brace) | Keyword(Begin) | Directive(_) |
                 Keyword(If) |
                 Keyword(Repeat) | Keyword(While) | Keyword(With) | Keyword(Do) | Keyword(For) |
                 Keyword(Break) | Keyword(Continue) | Keyword(Exit) |
@@ -598,13 +800,79 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
             }
         }
     }
+
+    /// Skip the rest of a malformed parenthesized expression or argument.
+    ///
+    /// This stops before the closing `delim`, before a `,` if `list` is set, or before anything
+    /// more likely to belong to the surrounding code: a `;`, a mismatched closing delimiter, a
+    /// brace, or a keyword that does not appear in expressions.
+    fn skip_to_close(&mut self, delim: Delim, list: bool) {
+        use crate::front::token::Token::*;
+        use crate::symbol::keyword::*;
+
+        let mut depth = 0;
+        loop {
+            #[allow(non_upper_case_globals)]
+            match self.current {
+                OpenDelim(Delim::Paren) | OpenDelim(Delim::Bracket) | OpenAccessor(_) => {
+                    depth += 1;
+                }
+                CloseDelim(Delim::Paren) | CloseDelim(Delim::Bracket) if depth > 0 => {
+                    depth -= 1;
+                }
+
+                CloseDelim(close) if close == delim => break,
+                Comma if list && depth == 0 => break,
+
+                Eof | Semicolon | CloseDelim(_) |
+                OpenDelim(Delim::Brace) | Keyword(Begin) | Keyword(End) | Directive(_) |
+                Keyword(Var) | Keyword(GlobalVar) |
+                Keyword(If) | Keyword(Then) | Keyword(Else) |
+                Keyword(Repeat) | Keyword(While) | Keyword(With) |
+                Keyword(Do) | Keyword(Until) | Keyword(For) |
+                Keyword(Break) | Keyword(Continue) | Keyword(Exit) |
+                Keyword(Switch) | Keyword(Case) | Keyword(Default) |
+                Keyword(Return) => break,
+
+                _ => (),
+            }
+            self.advance_token();
+        }
+    }
+}
+
+/// Replace the escape sequences in a GMS string literal.
+fn unescape(contents: &[u8]) -> Vec<u8> {
+    let mut string = Vec::with_capacity(contents.len());
+    let mut bytes = contents.iter().copied();
+    while let Some(byte) = bytes.next() {
+        if byte != b'\\' {
+            string.push(byte);
+            continue;
+        }
+
+        match bytes.next() {
+            Some(b'n') => string.push(b'\n'),
+            Some(b'r') => string.push(b'\r'),
+            Some(b't') => string.push(b'\t'),
+            Some(b'b') => string.push(0x08),
+            Some(b'f') => string.push(0x0c),
+            Some(b'v') => string.push(0x0b),
+            Some(byte) => string.push(byte),
+            None => string.push(b'\\'),
+        }
+    }
+    string
 }
 
 enum Infix {
     Binary(ast::Binary),
     Field,
     Index,
+    Accessor(Accessor),
     Call,
+    Postfix(ast::Op),
+    Ternary,
 }
 
 impl Infix {
@@ -616,6 +884,10 @@ impl Infix {
             Token::Dot => Infix::Field,
             Token::OpenDelim(Delim::Bracket) => Infix::Index,
             Token::OpenDelim(Delim::Paren) => Infix::Call,
+            Token::OpenAccessor(accessor) => Infix::Accessor(accessor),
+            Token::PlusPlus => Infix::Postfix(Add),
+            Token::MinusMinus => Infix::Postfix(Subtract),
+            Token::Question => Infix::Ternary,
 
             _ => Infix::Binary(match token {
                 Token::Lt => Lt,
@@ -653,7 +925,9 @@ impl Infix {
         }
 
         let precedence = match op {
-            Infix::Field | Infix::Index | Infix::Call => 7,
+            Infix::Field | Infix::Index | Infix::Accessor(_) | Infix::Call |
+            Infix::Postfix(_) => 7,
+            Infix::Ternary => 0,
             Infix::Binary(op) => match op {
                 Op(Multiply) | Op(Divide) | Div | Mod => 6,
                 Op(Add) | Op(Subtract) => 5,
@@ -671,18 +945,21 @@ impl Infix {
 #[cfg(test)]
 mod tests {
     use std::io;
+    use project::Version;
     use crate::{FunctionDisplay, ErrorPrinter};
+    use crate::diagnostic::Printer;
     use crate::symbol::Symbol;
     use crate::front::{Span, Lexer, Parser, Lines};
     use crate::front::ast::*;
 
     fn setup<'e, 's>(lines: &'e mut Lines, source: &'s [u8]) ->
-        (Lexer<'s>, ErrorPrinter<'e, io::Stderr>)
+        (Lexer<'s>, ErrorPrinter<'e, Printer<io::Stderr>>)
     {
         *lines = Lines::from_code(source);
         let script = Symbol::intern(b"<test>");
-        let errors = ErrorPrinter::new(FunctionDisplay::Script { script }, lines, io::stderr());
-        (Lexer::new(source, 0), errors)
+        let name = FunctionDisplay::Script { script };
+        let errors = ErrorPrinter::new(name, lines, Printer::new(io::stderr()));
+        (Lexer::new(source, 0, Version::GameMaker8), errors)
     }
 
     fn span(low: usize, high: usize) -> Span {
@@ -706,7 +983,7 @@ mod tests {
             Stmt::Block(vec![
                 (Stmt::Declare(
                     Declare::Local,
-                    vec![(x, span(6, 7))].into_boxed_slice(),
+                    vec![((x, span(6, 7)), None)].into_boxed_slice(),
                 ), span(2, 8)),
                 (Stmt::Assign(
                     (None, span(11, 12)),
@@ -752,4 +1029,68 @@ mod tests {
             span(0, 14)
         ));
     }
+
+    #[test]
+    fn recovery() {
+        type Errors = &'static [(usize, usize, &'static str)];
+        let corpus: &[(&[u8], Errors)] = &[
+            (b"f(a, b; x = 1", &[(6, 7, "unexpected ;; expected ) or ,")]),
+            (b"f(1 2, 3 4); y = 5", &[
+                (4, 5, "unexpected real 2; expected ) or ,"),
+                (9, 10, "unexpected real 4; expected ) or ,"),
+            ]),
+            (b"x = f(g(1 2), (3, 4)); y = 5", &[
+                (10, 11, "unexpected real 2; expected ) or ,"),
+                (16, 17, "unexpected ,; expected )"),
+            ]),
+            (b"f(1,, 2); g(", &[
+                (4, 5, "unexpected ,; expected expression"),
+                (12, 12, "unexpected end of file; expected ) or ,"),
+            ]),
+            (b"a[1, 2 = 3; b = 4", &[(10, 11, "unexpected ;; expected ] or ,")]),
+            (b"x = (1 + 2; y = 3", &[(10, 11, "unexpected ;; expected )")]),
+            (b"if (a { b = 1 } c = 2", &[(6, 7, "unexpected {; expected )")]),
+            (b"repeat (3 { x = 1 } y = 2", &[(10, 11, "unexpected {; expected )")]),
+            (b"if (a) { f(1; } else { g(2; }", &[
+                (12, 13, "unexpected ;; expected ) or ,"),
+                (26, 27, "unexpected ;; expected ) or ,"),
+            ]),
+            (b"{ x = ; y = 1 }", &[(6, 7, "unexpected ;; expected expression")]),
+            (b"x = 1 + * 2; y = 3", &[(8, 9, "unexpected *; expected expression")]),
+            (b"while x < { y = 1 }", &[(10, 11, "unexpected {; expected expression")]),
+            (b"x = 1 +\nif (y) z = 2", &[(8, 10, "unexpected keyword if; expected expression")]),
+            (b"if x then else y = 1", &[
+                (10, 14, "unexpected keyword else; expected expression"),
+            ]),
+            (b"x = [1]; y = 2", &[(4, 5, "unexpected [; expected expression")]),
+            (b"x = a.; y = 1", &[(6, 7, "unexpected ;; expected identifier")]),
+            (b"with x y z = 1; w = 2", &[
+                (9, 10, "unexpected identifier 'z'; expected assignment operator"),
+            ]),
+            (b"var 1; a = 2", &[(4, 5, "unexpected real 1; expected ;")]),
+            (b"switch (x) { case : break; default: y = 1 }", &[
+                (18, 19, "unexpected :; expected expression"),
+            ]),
+            (b"x = 1 ) y = 2", &[(6, 7, "unexpected ); expected statement")]),
+            (b"{ x = 1 ] y = 2 }", &[(8, 9, "unexpected ]; expected statement")]),
+            (b"} x = 1 end", &[
+                (0, 1, "unexpected }; expected end of file"),
+                (8, 11, "unexpected keyword end; expected end of file"),
+            ]),
+            (b"{ x = 1 ", &[(8, 8, "unexpected end of file; expected }")]),
+        ];
+
+        for &(source, expected) in corpus {
+            let lines = Lines::from_code(source);
+            let name = FunctionDisplay::Script { script: Symbol::intern(b"<test>") };
+            let mut errors = ErrorPrinter::new(name, &lines, Vec::new());
+            let reader = Lexer::new(source, 0, Version::GameMakerStudio);
+            Parser::new(reader, &mut errors).parse_program();
+
+            let errors: Vec<_> = errors.sink.iter()
+                .map(|error| (error.span.low, error.span.high, &error.message[..]))
+                .collect();
+            assert_eq!(&errors[..], expected, "{}", String::from_utf8_lossy(source));
+        }
+    }
 }
@@ -10,9 +10,13 @@ pub enum Token {
     Keyword(Symbol),
     Real(Symbol),
     String(Symbol),
+    /// A preprocessor-style directive, like `#macro`.
+    Directive(Symbol),
 
     OpenDelim(Delim),
     CloseDelim(Delim),
+    /// The opening bracket of a data structure accessor, like `[|`.
+    OpenAccessor(Accessor),
 
     Eq,
     ColonEq,
@@ -34,11 +38,14 @@ pub enum Token {
     Shr,
     Bang,
     Tilde,
+    PlusPlus,
+    MinusMinus,
 
     Dot,
     Comma,
     Semicolon,
     Colon,
+    Question,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
@@ -48,6 +55,18 @@ pub enum Delim {
     Brace,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Accessor {
+    /// `[| i]`, for `ds_list`s.
+    List,
+    /// `[? key]`, for `ds_map`s.
+    Map,
+    /// `[# i, j]`, for `ds_grid`s.
+    Grid,
+    /// `[@ i]`, for writing to an array without copying it.
+    Array,
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum BinOp {
     Plus,
@@ -70,6 +89,7 @@ impl fmt::Display for Token {
             Token::Keyword(symbol) => write!(fmt, "keyword {}", symbol)?,
             Token::Real(symbol) => write!(fmt, "real {}", symbol)?,
             Token::String(symbol) => write!(fmt, "string \"{}\"", symbol)?,
+            Token::Directive(symbol) => write!(fmt, "directive #{}", symbol)?,
 
             Token::OpenDelim(delim) => match delim {
                 Delim::Paren => write!(fmt, "(")?,
@@ -81,6 +101,12 @@ impl fmt::Display for Token {
                 Delim::Bracket => write!(fmt, "]")?,
                 Delim::Brace => write!(fmt, "}}")?,
             },
+            Token::OpenAccessor(accessor) => match accessor {
+                Accessor::List => write!(fmt, "[|")?,
+                Accessor::Map => write!(fmt, "[?")?,
+                Accessor::Grid => write!(fmt, "[#")?,
+                Accessor::Array => write!(fmt, "[@")?,
+            },
 
             Token::Eq => write!(fmt, "=")?,
             Token::ColonEq => write!(fmt, ":=")?,
@@ -120,11 +146,14 @@ impl fmt::Display for Token {
             Token::Shr => write!(fmt, ">>")?,
             Token::Bang => write!(fmt, "!")?,
             Token::Tilde => write!(fmt, "~")?,
+            Token::PlusPlus => write!(fmt, "++")?,
+            Token::MinusMinus => write!(fmt, "--")?,
 
             Token::Dot => write!(fmt, ".")?,
             Token::Comma => write!(fmt, ",")?,
             Token::Semicolon => write!(fmt, ";")?,
             Token::Colon => write!(fmt, ":")?,
+            Token::Question => write!(fmt, "?")?,
         }
 
         Ok(())
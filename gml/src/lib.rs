@@ -5,12 +5,14 @@
 #![feature(untagged_unions)]
 
 use std::collections::HashMap;
-use std::{fmt, io};
+use std::convert::TryFrom;
+use std::fmt;
 
 use crate::symbol::Symbol;
-use crate::front::{Lexer, Parser, ActionParser, Lines, Position, Span};
+use crate::diagnostic::{Diagnostic, Severity, Sink, Snippet};
+use crate::front::{Lexer, Parser, ActionParser, Lines, Span};
 use crate::back::ssa;
-use crate::vm::code;
+use project::event_type;
 
 pub use gml_meta::bind;
 
@@ -19,6 +21,7 @@ mod handle_map;
 mod rc_vec;
 mod bit_vec;
 pub mod symbol;
+pub mod diagnostic;
 
 pub mod front;
 pub mod back;
@@ -35,55 +38,339 @@ pub enum Function {
 pub enum Item<W, A> {
     Native(vm::ApiFunction<W, A>, usize, bool),
     Member(Option<vm::GetFunction<W, A>>, Option<vm::SetFunction<W, A>>),
+    /// A real or string constant. Arrays cannot be constants, and are ignored.
+    Constant(vm::Value),
+}
+
+/// Settings that control how code is compiled, but not what it means.
+#[derive(Clone)]
+pub struct Options {
+    /// Run the SSA optimization pipeline before lowering to bytecode.
+    pub optimize: bool,
+    /// Check SSA after code generation and after each optimization pass, panicking with the
+    /// malformed IR if it is invalid. On by default in debug builds.
+    pub verify: bool,
+    /// Called with each function's SSA just before it is lowered to bytecode.
+    pub dump_ssa: Option<fn(FunctionDisplay, &ssa::Function)>,
+    /// The number of registers instructions may address, at most `vm::code::REGISTER_LIMIT`.
+    /// Functions that need more spill the rest, so lowering this exercises spilling.
+    pub register_limit: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            optimize: true,
+            verify: cfg!(debug_assertions),
+            dump_ssa: None,
+            register_limit: vm::code::REGISTER_LIMIT,
+        }
+    }
 }
 
 /// Build the GML and D&D in a Game Maker project.
-pub fn build<W, A, F: FnMut() -> E, E: io::Write + 'static>(
-    game: &project::Game, engine: &HashMap<Symbol, Item<W, A>>, mut errors: F
-) -> Result<(vm::Assets<W, A>, vm::Debug), u32> {
-    let mut assets = vm::Assets::default();
-    let mut prototypes = HashMap::with_capacity(game.scripts.len() + engine.len());
-    let mut debug = vm::Debug::default();
+///
+/// Diagnostics are returned either way. The build fails if any of them is an error.
+pub fn build<W, A>(
+    game: &project::Game, engine: &HashMap<Symbol, Item<W, A>>
+) -> Result<(vm::Assets<W, A>, vm::Debug, Vec<Diagnostic>), Vec<Diagnostic>> {
+    build_with(game, engine, &Options::default())
+}
 
-    // Collect the prototypes of entities that may be referred to in code.
-    for (&name, item) in engine.iter() {
+/// Build the GML and D&D in a Game Maker project, with non-default `Options`.
+pub fn build_with<W, A>(
+    game: &project::Game, engine: &HashMap<Symbol, Item<W, A>>, options: &Options
+) -> Result<(vm::Assets<W, A>, vm::Debug, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let mut assets = vm::Assets {
+        version: game.version,
+        uninitialized_as_zero: game.settings.uninitialized_as_zero,
+        ..vm::Assets::default()
+    };
+    bind_engine(&mut assets, engine);
+
+    let FrontEnd { prototypes, mut debug, functions, mut diagnostics, mut total_errors } =
+        front_end(game, engine);
+
+    // Optimize and lower SSA to bytecode.
+    let passes = if options.optimize {
+        back::PassManager::standard()
+    } else {
+        back::PassManager::new()
+    };
+    for (function, name, mut program, lines) in functions {
+        if options.verify {
+            verify_ssa(name, "code generation", &program, false);
+        }
+        passes.run(&mut program, |pass, program| if options.verify {
+            verify_ssa(name, pass, program, true);
+        });
+        if options.verify {
+            verify_ssa(name, "optimization", &program, false);
+        }
+        if let Some(dump_ssa) = options.dump_ssa {
+            dump_ssa(name, &program);
+        }
+        let codegen = back::Codegen::new(&prototypes, options.register_limit);
+        let (code, locations) = match codegen.compile(&program) {
+            Ok(code) => code,
+            Err(back::TooLarge) => {
+                let errors: &mut ErrorPrinter<'_> =
+                    &mut ErrorPrinter::new(name, &lines, &mut diagnostics);
+                errors.error(Span { low: 0, high: 0 }, format_args!("function is too large"));
+                total_errors += 1;
+                (vm::code::Function::new(), vm::code::Locations::default())
+            }
+        };
+        assets.code.insert(function, code);
+        debug.locations.insert(function, vm::Locations { locations, lines });
+    }
+
+    // Objects inherit the events they do not define from their nearest ancestor that does.
+    for (object_index, object) in game.objects.iter().enumerate() {
+        let object_index = object_index as i32;
+        let mut parent = object.parent;
+        for _ in 0..game.objects.len() {
+            let ancestor = match usize::try_from(parent).ok().and_then(|i| game.objects.get(i)) {
+                Some(ancestor) => ancestor,
+                None => break,
+            };
+            for &project::Event { event_type, event_kind, .. } in &ancestor.events {
+                let function = Function::Event { object_index, event_type, event_kind };
+                if assets.code.contains_key(&function) {
+                    continue;
+                }
+                let inherited = Function::Event { object_index: parent, event_type, event_kind };
+                let code = assets.code[&inherited].clone();
+                let locations = debug.locations[&inherited].clone();
+                assets.code.insert(function, code);
+                debug.locations.insert(function, locations);
+            }
+            parent = ancestor.parent;
+        }
+    }
+
+    if total_errors > 0 {
+        return Err(diagnostics);
+    }
+
+    Ok((assets, debug, diagnostics))
+}
+
+/// Encode the code built from a Game Maker project, to be restored by `load` on a later run.
+///
+/// `options` must be the ones the code was built with.
+pub fn save<W, A>(
+    assets: &vm::Assets<W, A>, debug: &vm::Debug,
+    game: &project::Game, engine: &HashMap<Symbol, Item<W, A>>, options: &Options
+) -> Vec<u8> {
+    vm::serialize::write(&assets.code, debug, source_hash(game, engine, options))
+}
+
+/// Restore the code saved from a Game Maker project, binding it to the engine by name.
+///
+/// If the project, the engine, or the options that change the generated code are different from
+/// when the code was saved, this fails with `vm::serialize::Error::Stale` and the project must be
+/// built again.
+pub fn load<W, A>(
+    data: &[u8], game: &project::Game, engine: &HashMap<Symbol, Item<W, A>>, options: &Options
+) -> Result<(vm::Assets<W, A>, vm::Debug), vm::serialize::Error> {
+    let (code, debug) = vm::serialize::read(data, source_hash(game, engine, options))?;
+    let version = game.version;
+    let uninitialized_as_zero = game.settings.uninitialized_as_zero;
+    let mut assets = vm::Assets { code, version, uninitialized_as_zero, ..vm::Assets::default() };
+    bind_engine(&mut assets, engine);
+    Ok((assets, debug))
+}
+
+/// Hash everything in a project, engine, and options that `build` reads.
+fn source_hash<W, A>(
+    game: &project::Game, engine: &HashMap<Symbol, Item<W, A>>, options: &Options
+) -> u64 {
+    let mut hash = vm::serialize::SourceHash::default();
+    // Verifying and dumping SSA leave the generated code alone.
+    hash.write_u32(options.optimize as u32);
+    hash.write_u64(options.register_limit as u64);
+    hash.write_u32(game.version as u32);
+    hash.write_u32(game.settings.uninitialized_as_zero as u32);
+
+    let mut items: Vec<_> = engine.iter().collect();
+    items.sort_by_key(|&(&name, _)| name);
+    for (name, item) in items {
+        hash.write_bytes(name);
         match *item {
-            Item::Native(api, arity, variadic) => {
-                assets.api.insert(name, api);
-                prototypes.insert(name, ssa::Prototype::Native { arity, variadic });
+            Item::Native(_, arity, variadic) => {
+                hash.write_u32(0);
+                hash.write_u32(arity as u32);
+                hash.write_u32(variadic as u32);
             }
             Item::Member(get, set) => {
-                if let Some(get) = get { assets.get.insert(name, get); }
-                if let Some(set) = set { assets.set.insert(name, set); }
-                prototypes.insert(name, ssa::Prototype::Member);
+                hash.write_u32(1);
+                hash.write_u32(get.is_some() as u32);
+                hash.write_u32(set.is_some() as u32);
+            }
+            Item::Constant(ref value) => {
+                hash.write_u32(2);
+                match value.borrow().decode() {
+                    vm::Data::Real(real) => hash.write_u64(real.to_bits()),
+                    vm::Data::String(string) => hash.write_bytes(&string),
+                    vm::Data::Array(_) => {}
+                }
             }
         }
     }
-    for (id, &project::Script { name, .. }) in game.scripts.iter().enumerate() {
-        let id = id as i32;
-        let name = Symbol::intern(name);
-        prototypes.insert(name, ssa::Prototype::Script { id });
-        debug.scripts.push(name);
+
+    for &project::Constant { name, value } in &game.constants {
+        hash.write_bytes(name);
+        hash.write_bytes(value);
+    }
+
+    let resources: [Vec<&[u8]>; 7] = [
+        game.sprites.iter().map(|sprite| sprite.name).collect(),
+        game.sounds.iter().map(|sound| sound.name).collect(),
+        game.backgrounds.iter().map(|background| background.name).collect(),
+        game.paths.iter().map(|path| path.name).collect(),
+        game.fonts.iter().map(|font| font.name).collect(),
+        game.timelines.iter().map(|timeline| timeline.name).collect(),
+        game.rooms.iter().map(|room| room.name).collect(),
+    ];
+    for names in &resources {
+        hash.write_u32(names.len() as u32);
+        for name in names {
+            hash.write_bytes(name);
+        }
+    }
+
+    hash.write_u32(game.scripts.len() as u32);
+    for &project::Script { name, body } in &game.scripts {
+        hash.write_bytes(name);
+        hash.write_bytes(body);
+    }
+
+    hash.write_u32(game.objects.len() as u32);
+    for object in &game.objects {
+        hash.write_bytes(object.name);
+        hash.write_u32(object.parent as u32);
+        hash.write_u32(object.events.len() as u32);
+        for event in &object.events {
+            hash.write_u32(event.event_type);
+            hash.write_u32(event.event_kind as u32);
+            hash.write_u32(event.actions.len() as u32);
+            for action in &event.actions {
+                hash_action(&mut hash, action);
+            }
+        }
+    }
+
+    hash.finish()
+}
+
+fn hash_action(hash: &mut vm::serialize::SourceHash, action: &project::Action<'_>) {
+    hash.write_u32(action.library);
+    hash.write_u32(action.action);
+    hash.write_u32(action.action_kind);
+    hash.write_u32(action.has_relative as u32);
+    hash.write_u32(action.is_question as u32);
+    hash.write_u32(action.has_target as u32);
+    hash.write_u32(action.action_type);
+    hash.write_bytes(action.name);
+    hash.write_bytes(action.code);
+    hash.write_u32(action.parameters_used);
+    hash.write_u32(action.parameters.len() as u32);
+    for &parameter in &action.parameters {
+        hash.write_u32(parameter);
+    }
+    hash.write_u32(action.target as u32);
+    hash.write_u32(action.relative as u32);
+    hash.write_u32(action.arguments.len() as u32);
+    for argument in &action.arguments {
+        hash.write_bytes(argument);
+    }
+    hash.write_u32(action.negate as u32);
+}
+
+/// Collect the names visible to code in a Game Maker project, the same way `build` does.
+///
+/// This includes engine items, resources, scripts, constants, and the macros and enums declared
+/// in code. Errors are ignored, so that tools can resolve names in projects that do not build.
+pub fn prototypes<W, A>(
+    game: &project::Game, engine: &HashMap<Symbol, Item<W, A>>
+) -> HashMap<Symbol, ssa::Prototype> {
+    declare(game, engine).prototypes
+}
+
+/// Check the GML and D&D in a Game Maker project without lowering it to bytecode.
+///
+/// This returns the names visible to code, as `prototypes` does, along with the diagnostics that
+/// `build` reports before lowering. It is meant for tools that check a project as it is edited.
+pub fn check<W, A>(
+    game: &project::Game, engine: &HashMap<Symbol, Item<W, A>>
+) -> (HashMap<Symbol, ssa::Prototype>, Vec<Diagnostic>) {
+    let FrontEnd { prototypes, diagnostics, .. } = front_end(game, engine);
+    (prototypes, diagnostics)
+}
+
+/// A project's code after parsing, and the prototypes of everything it may refer to.
+struct Parsed {
+    prototypes: HashMap<Symbol, ssa::Prototype>,
+    debug: vm::Debug,
+    scripts: Vec<(Function, FunctionDisplay, (front::ast::Stmt, Span), Lines)>,
+    object_events: Vec<(Function, FunctionDisplay, (front::ast::Action, Span), Lines)>,
+    diagnostics: Vec<Diagnostic>,
+    total_errors: u32,
+}
+
+/// A project's code in SSA form, ready to be optimized and lowered to bytecode.
+struct FrontEnd {
+    prototypes: HashMap<Symbol, ssa::Prototype>,
+    debug: vm::Debug,
+    functions: Vec<(Function, FunctionDisplay, ssa::Function, Lines)>,
+    diagnostics: Vec<Diagnostic>,
+    total_errors: u32,
+}
+
+/// Parse a project's code, and declare the entities it may refer to, including its constants and
+/// the macros and enums declared anywhere in it.
+fn declare<W, A>(game: &project::Game, engine: &HashMap<Symbol, Item<W, A>>) -> Parsed {
+    let mut prototypes = HashMap::with_capacity(game.scripts.len() + engine.len());
+    let mut debug = vm::Debug::default();
+
+    // Collect the prototypes of entities that may be referred to in code.
+    declare_engine(&mut prototypes, engine);
+    declare_resources(&mut prototypes, game);
+    for &project::Script { name, .. } in game.scripts.iter() {
+        debug.scripts.push(Symbol::intern(name));
     }
     for &project::Object { name, .. } in game.objects.iter() {
         let name = Symbol::intern(name);
         debug.objects.push(name);
     }
 
+    let mut diagnostics = Vec::new();
     let mut total_errors = 0;
 
-    // Compile scripts.
+    // Evaluate project constants, each of which may refer to those before it.
+    for &project::Constant { name, value } in game.constants.iter() {
+        let constant = Symbol::intern(name);
+        let name = FunctionDisplay::Constant { constant };
+        let (value, errors) =
+            evaluate_constant(&prototypes, game.version, name, value, &mut diagnostics);
+        prototypes.insert(constant, ssa::Prototype::Constant(value));
+        total_errors += errors;
+    }
+
+    // Parse scripts and object events.
+    let mut scripts = Vec::with_capacity(game.scripts.len());
     let resources = Iterator::zip(debug.scripts.iter(), game.scripts.iter());
     for (id, (&script, &project::Script { body, .. })) in resources.enumerate() {
         let function = Function::Script { id: id as i32 };
         let name = FunctionDisplay::Script { script };
-        let (code, locations, errors) = compile_program(&prototypes, name, body, errors());
-        assets.code.insert(function, code);
-        debug.locations.insert(function, locations);
+        let (program, lines, errors) =
+            parse_program(game.version, name, body, &mut diagnostics);
+        scripts.push((function, name, program, lines));
         total_errors += errors;
     }
 
-    // Compile object events.
+    let mut object_events = Vec::new();
     let resources = Iterator::zip(debug.objects.iter(), game.objects.iter());
     for (object_index, (&object, &project::Object { ref events, .. })) in resources.enumerate() {
         let object_index = object_index as i32;
@@ -91,79 +378,331 @@ pub fn build<W, A, F: FnMut() -> E, E: io::Write + 'static>(
             let function = Function::Event { object_index, event_type, event_kind };
             let event_kind = EventDisplay::from_debug(&debug, event_type, event_kind);
             let name = FunctionDisplay::Event { object, event_type, event_kind };
-            let (code, locations, errors) = compile_event(&prototypes, name, actions, errors());
-            assets.code.insert(function, code);
-            debug.locations.insert(function, locations);
+            let (program, lines, errors) =
+                parse_event(game.version, name, actions, &mut diagnostics);
+            object_events.push((function, name, program, lines));
             total_errors += errors;
         }
     }
 
-    if total_errors > 0 {
-        return Err(total_errors);
+    // Evaluate the macros and enums declared anywhere in the project.
+    let mut declarations = front::Declarations::default();
+    for &(_, name, ref program, ref lines) in &scripts {
+        declarations.declare(name, lines, program);
+    }
+    for &(_, name, ref program, ref lines) in &object_events {
+        declarations.declare_event(name, lines, program);
     }
+    total_errors += declarations.evaluate(&mut prototypes, &mut diagnostics);
 
-    Ok((assets, debug))
+    Parsed { prototypes, debug, scripts, object_events, diagnostics, total_errors }
+}
+
+/// Parse a project's code and generate SSA for it, checking calls between scripts.
+fn front_end<W, A>(game: &project::Game, engine: &HashMap<Symbol, Item<W, A>>) -> FrontEnd {
+    let Parsed {
+        prototypes, debug, scripts, object_events, mut diagnostics, mut total_errors
+    } = declare(game, engine);
+
+    // Generate SSA for scripts and object events.
+    let mut functions = Vec::with_capacity(scripts.len() + object_events.len());
+    let mut syntax = Vec::with_capacity(scripts.len() + object_events.len());
+    for (function, name, program, lines) in scripts {
+        let (program, program_syntax, errors) =
+            compile_program(&prototypes, game.version, name, &program, &lines, &mut diagnostics);
+        functions.push((function, name, program, lines));
+        syntax.push(program_syntax);
+        total_errors += errors;
+    }
+    for (function, name, program, lines) in object_events {
+        let (program, event_syntax, errors) =
+            compile_event(&prototypes, game.version, name, &program, &lines, &mut diagnostics);
+        functions.push((function, name, program, lines));
+        syntax.push(event_syntax);
+        total_errors += errors;
+    }
+
+    // Scripts take as many arguments as they read, or any number if they use the `argument`
+    // array. Warn about calls that pass more, along with other likely mistakes, in source order.
+    let arguments: HashMap<_, _> = functions.iter()
+        .filter_map(|&(function, _, ref program, _)| match function {
+            Function::Script { id } if reads_argument_array(program) => Some((id, None)),
+            Function::Script { id } =>
+                Some((id, Some(program.blocks[ssa::ENTRY].parameters.len()))),
+            Function::Event { .. } => None,
+        })
+        .collect();
+    for (&(_, name, ref program, ref lines), syntax) in Iterator::zip(functions.iter(), &syntax) {
+        let mut warnings = Vec::new();
+        let mut errors = ErrorPrinter::new(name, lines, &mut warnings);
+        back::lint::lint(program, syntax, &mut errors);
+        check_calls(&prototypes, &arguments, program, &mut errors);
+        warnings.sort_by_key(|diagnostic| diagnostic.span);
+        diagnostics.extend(warnings);
+    }
+
+    FrontEnd { prototypes, debug, functions, diagnostics, total_errors }
 }
 
-fn compile_program<E: io::Write + 'static>(
+/// Bind the engine's functions and members by name, for code to call.
+fn bind_engine<W, A>(assets: &mut vm::Assets<W, A>, engine: &HashMap<Symbol, Item<W, A>>) {
+    for (&name, item) in engine.iter() {
+        match *item {
+            Item::Native(api, ..) => { assets.api.insert(name, api); }
+            Item::Member(get, set) => {
+                if let Some(get) = get { assets.get.insert(name, get); }
+                if let Some(set) = set { assets.set.insert(name, set); }
+            }
+            Item::Constant(_) => (),
+        }
+    }
+}
+
+fn declare_engine<W, A>(
+    prototypes: &mut HashMap<Symbol, ssa::Prototype>, engine: &HashMap<Symbol, Item<W, A>>
+) {
+    for (&name, item) in engine.iter() {
+        let prototype = match *item {
+            Item::Native(_, arity, variadic) => ssa::Prototype::Native { arity, variadic },
+            Item::Member(..) => ssa::Prototype::Member,
+            Item::Constant(ref value) => ssa::Prototype::Constant(match value.borrow().decode() {
+                vm::Data::Real(real) => ssa::Constant::Real(real),
+                vm::Data::String(string) => ssa::Constant::String(string),
+                vm::Data::Array(_) => continue,
+            }),
+        };
+        prototypes.insert(name, prototype);
+    }
+}
+
+fn declare_resources(prototypes: &mut HashMap<Symbol, ssa::Prototype>, game: &project::Game) {
+    bind_resources(prototypes, game.sprites.iter().map(|sprite| sprite.name));
+    bind_resources(prototypes, game.sounds.iter().map(|sound| sound.name));
+    bind_resources(prototypes, game.backgrounds.iter().map(|background| background.name));
+    bind_resources(prototypes, game.paths.iter().map(|path| path.name));
+    bind_resources(prototypes, game.fonts.iter().map(|font| font.name));
+    bind_resources(prototypes, game.timelines.iter().map(|timeline| timeline.name));
+    bind_resources(prototypes, game.objects.iter().map(|object| object.name));
+    bind_resources(prototypes, game.rooms.iter().map(|room| room.name));
+    for (id, &project::Script { name, .. }) in game.scripts.iter().enumerate() {
+        let id = id as i32;
+        prototypes.insert(Symbol::intern(name), ssa::Prototype::Script { id });
+    }
+}
+
+/// Bind the index of each named resource as a constant.
+fn bind_resources<'a, I: Iterator<Item = &'a [u8]>>(
+    prototypes: &mut HashMap<Symbol, ssa::Prototype>, names: I
+) {
+    for (index, name) in names.enumerate() {
+        // Deleted resources leave an empty slot behind.
+        if name.is_empty() {
+            continue;
+        }
+        let constant = ssa::Constant::Real(index as f64);
+        prototypes.insert(Symbol::intern(name), ssa::Prototype::Constant(constant));
+    }
+}
+
+fn evaluate_constant(
     prototypes: &HashMap<Symbol, ssa::Prototype>,
+    version: project::Version,
+    name: FunctionDisplay,
+    value: &[u8],
+    sink: &mut dyn Sink,
+) -> (ssa::Constant, u32) {
+    let lines = Lines::from_code(value);
+    let mut errors = ErrorPrinter::new(name, &lines, sink);
+    let expression = Parser::new(Lexer::new(value, 0, version), &mut errors).parse_constant();
+    let value = front::evaluate(prototypes, &mut errors, &expression);
+    (value, errors.count)
+}
+
+fn parse_program(
+    version: project::Version,
     name: FunctionDisplay,
     code: &[u8],
-    errors: E,
-) -> (code::Function, vm::Locations, u32) {
+    sink: &mut dyn Sink,
+) -> ((front::ast::Stmt, Span), Lines, u32) {
     let lines = Lines::from_code(code);
-    let mut errors = ErrorPrinter::new(name, &lines, errors);
-    let program = Parser::new(Lexer::new(code, 0), &mut errors).parse_program();
-    let program = { front::Codegen::new(&prototypes, &mut errors).compile_program(&program) };
-    let (code, locations) = back::Codegen::new(prototypes).compile(&program);
+    let mut errors = ErrorPrinter::new(name, &lines, sink);
+    let program = Parser::new(Lexer::new(code, 0, version), &mut errors).parse_program();
     let count = errors.count;
-    (code, vm::Locations { locations, lines }, count)
+    (program, lines, count)
 }
 
-fn compile_event<E: io::Write + 'static>(
-    prototypes: &HashMap<Symbol, ssa::Prototype>,
+fn parse_event(
+    version: project::Version,
     name: FunctionDisplay,
     actions: &[project::Action<'_>],
-    errors: E,
-) -> (code::Function, vm::Locations, u32) {
+    sink: &mut dyn Sink,
+) -> ((front::ast::Action, Span), Lines, u32) {
     let lines = Lines::from_actions(actions);
-    let mut errors = ErrorPrinter::new(name, &lines, errors);
-    let program = ActionParser::new(actions.iter(), &mut errors).parse_event();
-    let program = front::Codegen::new(&prototypes, &mut errors).compile_event(&program);
-    let (code, locations) = back::Codegen::new(prototypes).compile(&program);
+    let mut errors = ErrorPrinter::new(name, &lines, sink);
+    let program = ActionParser::new(actions.iter(), &mut errors, version).parse_event();
     let count = errors.count;
-    (code, vm::Locations { locations, lines }, count)
+    (program, lines, count)
+}
+
+fn compile_program(
+    prototypes: &HashMap<Symbol, ssa::Prototype>,
+    version: project::Version,
+    name: FunctionDisplay,
+    program: &(front::ast::Stmt, Span),
+    lines: &Lines,
+    sink: &mut dyn Sink,
+) -> (ssa::Function, back::lint::Syntax, u32) {
+    let mut errors = ErrorPrinter::new(name, lines, sink);
+    let (program, syntax) =
+        front::Codegen::new(&prototypes, version, &mut errors).compile_program(program);
+    (program, syntax, errors.count)
+}
+
+fn compile_event(
+    prototypes: &HashMap<Symbol, ssa::Prototype>,
+    version: project::Version,
+    name: FunctionDisplay,
+    event: &(front::ast::Action, Span),
+    lines: &Lines,
+    sink: &mut dyn Sink,
+) -> (ssa::Function, back::lint::Syntax, u32) {
+    let mut errors = ErrorPrinter::new(name, lines, sink);
+    let (program, syntax) =
+        front::Codegen::new(&prototypes, version, &mut errors).compile_event(event);
+    (program, syntax, errors.count)
+}
+
+/// Panic if a stage of compilation produced malformed SSA.
+fn verify_ssa(name: FunctionDisplay, stage: &str, program: &ssa::Function, allow_aliases: bool) {
+    if let Err(errors) = back::verify::verify(program, allow_aliases) {
+        let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        panic!("invalid SSA for {} after {}:\n{}\n\n{:?}", name, stage, errors.join("\n"), program);
+    }
+}
+
+/// Whether a script refers to `argument`, and so may read any number of arguments.
+fn reads_argument_array(program: &ssa::Function) -> bool {
+    let argument = Symbol::intern(b"argument");
+    let mut values = program.blocks.keys()
+        .flat_map(|block| program.blocks[block].instructions.iter().copied());
+    values.any(|value| match program.values[value] {
+        ssa::Instruction::UnarySymbol { op: ssa::Opcode::Constant, .. } => false,
+        ssa::Instruction::UnarySymbol { symbol, .. } |
+        ssa::Instruction::BinarySymbol { symbol, .. } |
+        ssa::Instruction::TernarySymbol { symbol, .. } => symbol == argument,
+        _ => false,
+    })
+}
+
+/// Warn about calls that pass a script more arguments than it reads.
+///
+/// Scripts that may read any number of arguments are mapped to `None`, and never warned about.
+fn check_calls(
+    prototypes: &HashMap<Symbol, ssa::Prototype>,
+    arguments: &HashMap<i32, Option<usize>>,
+    program: &ssa::Function,
+    errors: &mut ErrorPrinter<'_>,
+) {
+    let mut calls: Vec<_> = program.blocks.keys()
+        .flat_map(|block| program.blocks[block].instructions.iter().copied())
+        .filter_map(|value| match program.values[value] {
+            ssa::Instruction::Call { op: ssa::Opcode::Call, symbol, ref args, .. } =>
+                Some((program.locations[value], symbol, args.len())),
+            _ => None,
+        })
+        .collect();
+    calls.sort_by_key(|&(location, _, _)| location);
+
+    for (location, symbol, len) in calls {
+        let id = match prototypes.get(&symbol) {
+            Some(&ssa::Prototype::Script { id }) => id,
+            _ => continue,
+        };
+        let read = match arguments[&id] {
+            Some(read) => read,
+            None => continue,
+        };
+        if len > read {
+            let span = Span { low: location, high: location + symbol.len() };
+            let note = format!("{} reads {} arguments", symbol, read);
+            errors.report(Severity::Warning, span,
+                format_args!("too many arguments to script {}", symbol), vec![note]);
+        }
+    }
 }
 
-pub struct ErrorPrinter<'a, W: ?Sized = dyn io::Write> {
+/// Reports diagnostics for a single script, event, or constant to a `Sink`.
+pub struct ErrorPrinter<'a, S: ?Sized = dyn Sink + 'a> {
     pub name: FunctionDisplay,
     pub lines: &'a Lines,
     pub count: u32,
-    pub write: W,
+    pub sink: S,
 }
 
+#[derive(Copy, Clone, Debug)]
 pub enum FunctionDisplay {
     Event { object: Symbol, event_type: u32, event_kind: EventDisplay },
     Script { script: Symbol },
+    Moment { timeline: Symbol, moment: u32 },
+    Constant { constant: Symbol },
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub enum EventDisplay {
     Id(i32),
     Name(Symbol),
 }
 
 impl<'a> ErrorPrinter<'a> {
-    pub fn new<W: io::Write>(name: FunctionDisplay, lines: &'a Lines, write: W) ->
-        ErrorPrinter<'a, W>
+    pub fn new<S: Sink>(name: FunctionDisplay, lines: &'a Lines, sink: S) ->
+        ErrorPrinter<'a, S>
     {
-        ErrorPrinter { name, lines, count: 0, write }
+        ErrorPrinter { name, lines, count: 0, sink }
     }
 
-    pub fn from_debug<W: io::Write>(debug: &vm::Debug, function: Function, write: W) ->
-        ErrorPrinter<W>
+    pub fn from_debug<S: Sink>(debug: &vm::Debug, function: Function, sink: S) ->
+        ErrorPrinter<S>
     {
-        let name = match function {
+        let name = FunctionDisplay::from_debug(debug, function);
+        let lines = &debug.locations[&function].lines;
+
+        ErrorPrinter::new(name, lines, sink)
+    }
+
+    pub fn error(&mut self, span: Span, message: fmt::Arguments<'_>) {
+        self.report(Severity::Error, span, message, Vec::new());
+    }
+
+    /// Report a likely mistake. Unlike errors, warnings do not fail the build.
+    pub fn warning(&mut self, span: Span, message: fmt::Arguments<'_>) {
+        self.report(Severity::Warning, span, message, Vec::new());
+    }
+
+    pub fn report(
+        &mut self, severity: Severity, span: Span, message: fmt::Arguments<'_>, notes: Vec<String>
+    ) {
+        let position = self.lines.get_position(span.low);
+        let snippet = self.lines.get_line(span.low).map(|line| {
+            let source = &self.lines.source;
+            let columns = |pos: usize| {
+                let pos = usize::max(line.start, usize::min(pos, line.end));
+                String::from_utf8_lossy(&source[line.start..pos]).chars().count()
+            };
+            let text = String::from_utf8_lossy(&source[line.clone()]).into_owned();
+            Snippet { text, columns: (columns(span.low), columns(span.high)) }
+        });
+        let function = self.name;
+        let message = message.to_string();
+        self.sink.report(Diagnostic { severity, function, position, span, message, notes, snippet });
+        if severity == Severity::Error {
+            self.count += 1;
+        }
+    }
+}
+
+impl FunctionDisplay {
+    pub fn from_debug(debug: &vm::Debug, function: Function) -> FunctionDisplay {
+        match function {
             Function::Event { object_index, event_type, event_kind } => {
                 let object = debug.objects[object_index as usize];
                 let event_kind = EventDisplay::from_debug(debug, event_type, event_kind);
@@ -173,36 +712,30 @@ impl<'a> ErrorPrinter<'a> {
                 let script = debug.scripts[id as usize];
                 FunctionDisplay::Script { script }
             }
-        };
-
-        let lines = &debug.locations[&function].lines;
-
-        ErrorPrinter::new(name, lines, write)
-    }
-
-    pub fn error(&mut self, span: Span, message: fmt::Arguments<'_>) {
-        let Position { action, argument, line, column } = self.lines.get_position(span.low);
-        let _ = write!(self.write, "error in {}", self.name);
-        if let Some(action) = action {
-            let _ = write!(self.write, ", action {}", action);
-        }
-        if let (Some(argument), None) = (argument, line) {
-            let _ = write!(self.write, ", argument {}", argument);
         }
-        if let Some(line) = line {
-            let _ = write!(self.write, ":{}", line);
-        }
-        if let Some(column) = column {
-            let _ = write!(self.write, ":{}", column);
-        }
-        let _ = writeln!(self.write, ": {}", message);
-        self.count += 1;
     }
 }
 
 impl EventDisplay {
-    fn from_debug(_: &vm::Debug, event_type: u32, event_kind: i32) -> EventDisplay {
+    fn from_debug(debug: &vm::Debug, event_type: u32, event_kind: i32) -> EventDisplay {
+        Self::new(event_type, event_kind, |object| debug.objects.get(object).copied())
+    }
+
+    /// Name an event from the project it is in, for code that does not build the project.
+    pub fn from_game(game: &project::Game<'_>, event_type: u32, event_kind: i32) -> EventDisplay {
+        Self::new(event_type, event_kind, |object| {
+            game.objects.get(object).map(|object| Symbol::intern(object.name))
+        })
+    }
+
+    fn new<F>(event_type: u32, event_kind: i32, object: F) -> EventDisplay
+        where F: FnOnce(usize) -> Option<Symbol>
+    {
         match event_type {
+            event_type::COLLISION => match usize::try_from(event_kind).ok().and_then(object) {
+                Some(object) => EventDisplay::Name(object),
+                None => EventDisplay::Id(event_kind),
+            }
             _ => EventDisplay::Id(event_kind),
         }
     }
@@ -214,6 +747,9 @@ impl fmt::Display for FunctionDisplay {
             FunctionDisplay::Event { object, event_type, event_kind } =>
                 display_event(object, event_type, event_kind, f),
             FunctionDisplay::Script { script } => write!(f, "script {}", script),
+            FunctionDisplay::Moment { timeline, moment } =>
+                write!(f, "moment {} of timeline {}", moment, timeline),
+            FunctionDisplay::Constant { constant } => write!(f, "constant {}", constant),
         }
     }
 }
@@ -221,11 +757,135 @@ impl fmt::Display for FunctionDisplay {
 fn display_event(
     object: Symbol, event_type: u32, event_kind: EventDisplay, f: &mut fmt::Formatter<'_>
 ) -> fmt::Result {
-    write!(f, "event {}", event_type)?;
-    match event_kind {
-        EventDisplay::Id(id) => write!(f, "({})", id)?,
-        EventDisplay::Name(name) => write!(f, "({})", name)?,
-    }
+    write!(f, "event ")?;
+    display_event_name(event_type, event_kind, f)?;
     write!(f, " for object {}", object)?;
     Ok(())
 }
+
+fn display_event_name(
+    event_type: u32, event_kind: EventDisplay, f: &mut fmt::Formatter<'_>
+) -> fmt::Result {
+    let kind = match event_kind {
+        EventDisplay::Id(id) => id,
+        EventDisplay::Name(name) if event_type == event_type::COLLISION =>
+            return write!(f, "Collision with {}", name),
+        EventDisplay::Name(name) => return write!(f, "{}({})", event_type, name),
+    };
+
+    match (event_type, kind) {
+        (event_type::CREATE, 0) => write!(f, "Create"),
+        (event_type::DESTROY, 0) => write!(f, "Destroy"),
+        (event_type::ALARM, _) => write!(f, "Alarm {}", kind),
+        (event_type::STEP, 0) => write!(f, "Step"),
+        (event_type::STEP, 1) => write!(f, "Step Begin"),
+        (event_type::STEP, 2) => write!(f, "Step End"),
+        (event_type::COLLISION, _) => write!(f, "Collision with {}", kind),
+        (event_type::KEYBOARD, _) => write!(f, "Keyboard <{}>", KeyDisplay(kind)),
+        (event_type::MOUSE, _) => write!(f, "Mouse {}", MouseDisplay(kind)),
+        (event_type::OTHER, _) => write!(f, "Other: {}", OtherDisplay(kind)),
+        (event_type::DRAW, 0) => write!(f, "Draw"),
+        (event_type::KEY_PRESS, _) => write!(f, "Key Press <{}>", KeyDisplay(kind)),
+        (event_type::KEY_RELEASE, _) => write!(f, "Key Release <{}>", KeyDisplay(kind)),
+        (event_type::TRIGGER, _) => write!(f, "Trigger {}", kind),
+        (_, _) => write!(f, "{}({})", event_type, kind),
+    }
+}
+
+/// The name of a virtual key code, as used by keyboard events.
+struct KeyDisplay(i32);
+
+impl fmt::Display for KeyDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let KeyDisplay(key) = *self;
+        let name = match key {
+            0 => "No Key",
+            1 => "Any Key",
+            8 => "Backspace",
+            9 => "Tab",
+            13 => "Enter",
+            16 => "Shift",
+            17 => "Ctrl",
+            18 => "Alt",
+            27 => "Escape",
+            32 => "Space",
+            33 => "Page Up",
+            34 => "Page Down",
+            35 => "End",
+            36 => "Home",
+            37 => "Left",
+            38 => "Up",
+            39 => "Right",
+            40 => "Down",
+            45 => "Insert",
+            46 => "Delete",
+            48..=57 | 65..=90 => return write!(f, "{}", key as u8 as char),
+            96..=105 => return write!(f, "Keypad {}", key - 96),
+            106 => "Keypad *",
+            107 => "Keypad +",
+            109 => "Keypad -",
+            110 => "Keypad .",
+            111 => "Keypad /",
+            112..=123 => return write!(f, "F{}", key - 111),
+            _ => return write!(f, "{}", key),
+        };
+        f.write_str(name)
+    }
+}
+
+/// The name of a mouse event kind.
+struct MouseDisplay(i32);
+
+impl fmt::Display for MouseDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let MouseDisplay(kind) = *self;
+        const BUTTONS: [&str; 3] = ["Left", "Right", "Middle"];
+        match kind {
+            0..=2 => write!(f, "{} Button", BUTTONS[kind as usize]),
+            3 => write!(f, "No Button"),
+            4..=6 => write!(f, "{} Pressed", BUTTONS[kind as usize - 4]),
+            7..=9 => write!(f, "{} Released", BUTTONS[kind as usize - 7]),
+            10 => write!(f, "Enter"),
+            11 => write!(f, "Leave"),
+            16..=28 | 31..=43 => {
+                const DIRECTIONS: [&str; 4] = ["Left", "Right", "Up", "Down"];
+                let (joystick, input) = if kind < 31 { (1, kind - 16) } else { (2, kind - 31) };
+                match input {
+                    0..=3 => write!(f, "Joystick {} {}", joystick, DIRECTIONS[input as usize]),
+                    _ => write!(f, "Joystick {} Button {}", joystick, input - 4),
+                }
+            }
+            50..=52 => write!(f, "Global {} Button", BUTTONS[kind as usize - 50]),
+            53..=55 => write!(f, "Global {} Pressed", BUTTONS[kind as usize - 53]),
+            56..=58 => write!(f, "Global {} Released", BUTTONS[kind as usize - 56]),
+            60 => write!(f, "Wheel Up"),
+            61 => write!(f, "Wheel Down"),
+            _ => write!(f, "{}", kind),
+        }
+    }
+}
+
+/// The name of an "other" event kind.
+struct OtherDisplay(i32);
+
+impl fmt::Display for OtherDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let OtherDisplay(kind) = *self;
+        let name = match kind {
+            0 => "Outside Room",
+            1 => "Intersect Boundary",
+            2 => "Game Start",
+            3 => "Game End",
+            4 => "Room Start",
+            5 => "Room End",
+            6 => "No More Lives",
+            7 => "Animation End",
+            8 => "End of Path",
+            9 => "No More Health",
+            10..=25 => return write!(f, "User Defined {}", kind - 10),
+            30 => "Close Button",
+            _ => return write!(f, "{}", kind),
+        };
+        f.write_str(name)
+    }
+}
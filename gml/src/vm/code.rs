@@ -1,10 +1,14 @@
-use std::{u8, mem, fmt};
+use std::{mem, fmt};
 
+use crate::symbol::Symbol;
 use crate::vm;
 
+#[derive(Clone)]
 pub struct Function {
     pub params: u32,
     pub locals: u32,
+    /// The number of slots for values spilled out of addressable registers.
+    pub spills: u32,
     pub constants: Vec<vm::Value>,
     pub instructions: Vec<Inst>,
 }
@@ -14,17 +18,40 @@ impl Function {
         Function {
             params: 0,
             locals: 0,
+            spills: 0,
             constants: vec![],
             instructions: vec![],
         }
     }
 }
 
-#[derive(Default)]
+impl Function {
+    /// List the function's instructions, using `debug` to find its source and name its callees.
+    pub fn disassemble<'a>(
+        &'a self, debug: &'a vm::Debug, function: crate::Function
+    ) -> Disassembly<'a> {
+        let locations = debug.locations.get(&function);
+        Disassembly { function: self, locations, scripts: &debug.scripts }
+    }
+}
+
+/// A human-readable listing of a `Function`.
+///
+/// Each instruction is printed at its offset with its opcode and operands. Registers are written
+/// `%n` and spill slots `$n`, constants and callees are resolved to their values, and jump targets
+/// are given labels. Each change in source line is marked with a comment quoting that line.
+pub struct Disassembly<'a> {
+    function: &'a Function,
+    locations: Option<&'a vm::Locations>,
+    scripts: &'a [Symbol],
+}
+
+#[derive(Default, Clone)]
 pub struct Locations {
     pub mappings: Vec<SourceMap>,
 }
 
+#[derive(Copy, Clone)]
 pub struct SourceMap {
     pub offset: u32,
     pub location: u32,
@@ -40,10 +67,16 @@ impl Locations {
     }
 }
 
+/// The number of registers an instruction can address.
+pub const REGISTER_LIMIT: usize = 1 << 16;
+
 /// An encoded instruction.
 ///
 /// Fields use this structure, stored in little-endian order:
 /// | op: 8 | dst: 8 | a: 8 | b: 8 |
+///
+/// An instruction may be preceded by `Op::Wide`, whose fields hold the high byte of each of the
+/// following instruction's fields. This extends register and constant indices to 16 bits.
 #[derive(Copy, Clone)]
 pub struct Inst(pub(crate) u32);
 
@@ -57,6 +90,13 @@ impl Inst {
 
         (op, dst as usize, a as usize, b as usize)
     }
+
+    /// Decode an instruction that follows an `Op::Wide` prefix.
+    pub fn decode_wide(&self, prefix: Inst) -> (Op, usize, usize, usize) {
+        let (op, dst, a, b) = self.decode();
+        let (_, dst_high, a_high, b_high) = prefix.decode();
+        (op, dst | dst_high << 8, a | a_high << 8, b | b_high << 8)
+    }
 }
 
 #[repr(u8)]
@@ -64,6 +104,8 @@ impl Inst {
 pub enum Op {
     Imm,
     Move,
+    Spill,
+    Reload,
 
     Neg,
     Not,
@@ -130,6 +172,8 @@ pub enum Op {
 
     Jump,
     BranchFalse,
+
+    Wide,
 }
 
 impl fmt::Debug for Function {
@@ -138,14 +182,27 @@ impl fmt::Debug for Function {
         for param in 0..self.params {
             write!(f, "%{:?}, ", param)?;
         }
-        writeln!(f, ")[{:?}]", self.locals)?;
+        write!(f, ")[{:?}]", self.locals)?;
+        if self.spills > 0 {
+            write!(f, "[${:?}]", self.spills)?;
+        }
+        writeln!(f)?;
 
-        for &inst in &self.instructions {
-            let (op, a, b, c) = inst.decode();
+        let mut instructions = self.instructions.iter();
+        while let Some(&inst) = instructions.next() {
+            let (op, a, b, c) = match inst.decode() {
+                (Op::Wide, ..) => match instructions.next() {
+                    Some(next) => next.decode_wide(inst),
+                    None => break,
+                },
+                decoded => decoded,
+            };
             match op {
                 Op::Imm | Op::Lookup =>
                     writeln!(f, "  %{:?} = {:?} {:?}", a, op, self.constants[b])?,
                 Op::Move => writeln!(f, "  %{:?} = %{:?}", a, b)?,
+                Op::Spill => writeln!(f, "  {:?} %{:?}, ${:?}", op, a, b | (c << 8))?,
+                Op::Reload => writeln!(f, "  %{:?} = {:?} ${:?}", a, op, b | (c << 8))?,
                 Op::DeclareGlobal => writeln!(f, "  {:?} {:?}", op, self.constants[a])?,
                 Op::LoadScope => writeln!(f, "  %{:?} = {:?} {:?}", a, op, b as i32)?,
                 Op::StoreScope => writeln!(f, "  {:?} %{:?}, {:?}", op, a, b as i32)?,
@@ -176,3 +233,134 @@ impl fmt::Debug for Function {
         Ok(())
     }
 }
+
+impl<'a> fmt::Display for Disassembly<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Function { params, locals, spills, ref constants, ref instructions } = *self.function;
+        writeln!(f, "params {}, locals {}, spills {}", params, locals, spills)?;
+
+        // Decode everything up front, so jump targets can be labeled before they are reached.
+        let mut decoded = Vec::with_capacity(instructions.len());
+        let mut offset = 0;
+        while offset < instructions.len() {
+            let (inst, len) = match instructions[offset].decode() {
+                (Op::Wide, ..) if offset + 1 < instructions.len() =>
+                    (instructions[offset + 1].decode_wide(instructions[offset]), 2),
+                inst => (inst, 1),
+            };
+            decoded.push((offset, inst));
+            offset += len;
+        }
+        let mut labels: Vec<_> = decoded.iter()
+            .filter_map(|&(_, inst)| match inst {
+                (Op::Jump, low, high, _) | (Op::BranchFalse, _, low, high) => Some(low | high << 8),
+                _ => None,
+            })
+            .collect();
+        labels.sort_unstable();
+        labels.dedup();
+
+        let label = |target| labels.binary_search(&target).unwrap_or_else(|label| label);
+        let constant = |index: usize| Constant(&constants[index]);
+        let name = |index: usize| Name(&constants[index]);
+
+        let mut last_position = None;
+        for &(offset, (op, a, b, c)) in &decoded {
+            if let Ok(label) = labels.binary_search(&offset) {
+                writeln!(f, "L{}:", label)?;
+            }
+
+            if let Some(locations) = self.locations.filter(|l| !l.locations.mappings.is_empty()) {
+                let location = locations.locations.get_location(offset as u32) as usize;
+                let position = locations.lines.get_position(location);
+                let key = (position.action, position.argument, position.line);
+                if last_position != Some(key) {
+                    write!(f, "    ;")?;
+                    if let Some(action) = position.action {
+                        write!(f, " action {},", action)?;
+                    }
+                    match (position.argument, position.line) {
+                        (_, Some(line)) => write!(f, " line {}", line)?,
+                        (Some(argument), None) => write!(f, " argument {}", argument)?,
+                        (None, None) => write!(f, " start")?,
+                    }
+                    let line = position.line.and_then(|_| locations.lines.get_line(location));
+                    if let Some(line) = line {
+                        let text = String::from_utf8_lossy(&locations.lines.source[line]);
+                        write!(f, ": {}", text.trim())?;
+                    }
+                    writeln!(f)?;
+                    last_position = Some(key);
+                }
+            }
+
+            let operands = match op {
+                Op::Imm => format!("%{}, {}", a, constant(b)),
+                Op::Lookup | Op::Read => format!("%{}, {}", a, name(b)),
+                Op::Spill | Op::Reload => format!("%{}, ${}", a, b | c << 8),
+                Op::DeclareGlobal => format!("{}", name(a)),
+                Op::LoadScope | Op::StoreScope => format!("%{}, {}", a, Scope(b as i8 as i32)),
+                Op::LoadField | Op::LoadFieldDefault | Op::StoreField =>
+                    format!("%{}, %{}, {}", a, b, name(c)),
+                Op::Call => match self.scripts.get(a) {
+                    Some(script) => format!("{}, %{}, {}", script, b, c),
+                    None => format!("script {}, %{}, {}", a, b, c),
+                },
+                Op::CallApi => format!("{}, %{}, {}", name(a), b, c),
+                Op::CallGet | Op::CallSet => format!("{}, %{}", name(a), b),
+                Op::Jump => format!("L{}", label(a | b << 8)),
+                Op::BranchFalse => format!("%{}, L{}", a, label(b | c << 8)),
+                Op::ReleaseWith | Op::Ret | Op::Wide => String::new(),
+                Op::Release | Op::ScopeError => format!("%{}", a),
+                Op::Move | Op::Neg | Op::Not | Op::BitNot | Op::ToArray | Op::ToScalar |
+                Op::LoadPointer | Op::NextPointer | Op::ExistsEntity => format!("%{}, %{}", a, b),
+                _ => format!("%{}, %{}, %{}", a, b, c),
+            };
+            let op = format!("{:?}", op);
+            let line = format!("    {:04}  {:<18}{}", offset, op, operands);
+            writeln!(f, "{}", line.trim_end())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A constant operand, with strings quoted to set them apart from names.
+struct Constant<'a>(&'a vm::Value);
+
+impl<'a> fmt::Display for Constant<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.borrow().decode() {
+            vm::Data::String(string) => write!(f, "{:?}", String::from_utf8_lossy(&string)),
+            _ => write!(f, "{:?}", self.0),
+        }
+    }
+}
+
+/// A constant operand that holds a variable or function name, written without quotes.
+struct Name<'a>(&'a vm::Value);
+
+impl<'a> fmt::Display for Name<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.borrow().decode() {
+            vm::Data::String(string) => write!(f, "{}", string),
+            _ => write!(f, "{}", Constant(self.0)),
+        }
+    }
+}
+
+/// A scope operand, with the special scopes named.
+struct Scope(i32);
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            vm::SELF => write!(f, "self"),
+            vm::OTHER => write!(f, "other"),
+            vm::ALL => write!(f, "all"),
+            vm::NOONE => write!(f, "noone"),
+            vm::GLOBAL => write!(f, "global"),
+            scope => write!(f, "{}", scope),
+        }
+    }
+}
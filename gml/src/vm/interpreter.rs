@@ -3,6 +3,8 @@ use std::convert::TryFrom;
 use std::mem::ManuallyDrop;
 use std::ops::Range;
 
+use project::Version;
+
 use crate::symbol::Symbol;
 use crate::rc_vec::RcVec;
 use crate::Function;
@@ -15,6 +17,8 @@ pub struct Thread {
     calls: Vec<(Function, usize, usize)>,
     withs: Vec<RcVec<Entity>>,
     stack: Vec<Register>,
+    /// Values the compiler spilled out of addressable registers, for each frame in `calls`.
+    spills: Vec<Register>,
 
     self_entity: Entity,
     other_entity: Entity,
@@ -114,6 +118,7 @@ impl Default for Thread {
             calls: Vec::default(),
             withs: Vec::default(),
             stack: Vec::default(),
+            spills: Vec::default(),
 
             self_entity: Entity(0),
             other_entity: Entity(0),
@@ -184,9 +189,16 @@ fn execute_internal(
     let mut code = &assets(engine).code[&function];
     let mut instruction = 0;
     let mut reg_base = thread.stack.len();
+    let mut spill_base = thread.spills.len();
+
+    // Reads that would otherwise fail on a missing value fall back to `0.0` or `empty`.
+    let uninitialized_as_zero = assets(engine).uninitialized_as_zero &&
+        assets(engine).version < Version::GameMakerStudio;
+    let empty = Array::default();
 
     // Don't initialize locals, the compiler handles that.
     thread.stack.resize_with(reg_base + code.locals as usize, Register::default);
+    thread.spills.resize_with(spill_base + code.spills as usize, Register::default);
 
     // Move the arguments onto the stack and initialize any additional parameters to 0.0.
     let registers = thread.stack[reg_base..][..code.params as usize].iter_mut();
@@ -198,7 +210,15 @@ fn execute_internal(
     let kind = loop {
         let registers = &mut thread.stack[reg_base..];
 
-        match code.instructions[instruction].decode() {
+        let inst = match code.instructions[instruction].decode() {
+            (code::Op::Wide, ..) => {
+                instruction += 1;
+                code.instructions[instruction].decode_wide(code.instructions[instruction - 1])
+            }
+            inst => inst,
+        };
+
+        match inst {
             (code::Op::Imm, t, constant, _) => {
                 let value = code.constants[constant].clone();
                 registers[t] = Register { value: ManuallyDrop::new(value) };
@@ -209,6 +229,17 @@ fn execute_internal(
                 registers[t] = mem::take(&mut registers[s]);
             }
 
+            (code::Op::Spill, s, slot_low, slot_high) => {
+                let slot = spill_base + (slot_low | (slot_high << 8));
+                thread.spills[slot] = mem::take(&mut registers[s]);
+            }
+
+            (code::Op::Reload, t, slot_low, slot_high) => {
+                // Unlike `Move`, this leaves the spilled value in place to be reloaded again.
+                let slot = spill_base + (slot_low | (slot_high << 8));
+                registers[t] = unsafe { ptr::read(&thread.spills[slot]) };
+            }
+
             (op @ code::Op::Neg, t, a, _) => {
                 let a = unsafe { registers[a].value_ref };
                 let value = match a.decode() {
@@ -497,7 +528,8 @@ fn execute_internal(
                     GLOBAL => slice::from_ref(&world::GLOBAL),
                     LOCAL => &[], // TODO: error
                     object if (0..=100_000).contains(&object) => {
-                        values = world(engine).objects[&object].clone();
+                        // Instance lists include the instances of descendant objects.
+                        values = world(engine).objects.get(&object).cloned().unwrap_or_default();
                         &values[..]
                     }
                     instance if (100_001..).contains(&instance) =>
@@ -556,7 +588,7 @@ fn execute_internal(
             (op @ code::Op::Read, a, local, _) => {
                 let a = unsafe { registers[a].value_ref };
                 match a.decode() {
-                    Data::Real(a) => if !to_bool(a) {
+                    Data::Real(a) => if !to_bool(a) && !uninitialized_as_zero {
                         let local = get_string(code.constants[local].borrow());
                         break ErrorKind::Name(local);
                     }
@@ -606,6 +638,7 @@ fn execute_internal(
                             Data::Array(_) => break ErrorKind::TypeUnary(op, a),
                             _ => a,
                         }
+                        None if uninitialized_as_zero => Value::default(),
                         None => break ErrorKind::Bounds(0),
                     }
                     // Because `a` is not an array, this clone is a simple copy.
@@ -624,6 +657,7 @@ fn execute_internal(
                 let instance = &world(engine).members[entity];
                 let value = match instance.get(&field) {
                     Some(value) => value.borrow(),
+                    None if uninitialized_as_zero => ValueRef::default(),
                     None => break ErrorKind::Name(field),
                 };
                 registers[t].value_ref = unsafe { erase_ref(value) };
@@ -650,6 +684,9 @@ fn execute_internal(
                             Data::Array(array) => array,
                             _ => break ErrorKind::TypeUnary(op, a.clone()),
                         }
+                        None if uninitialized_as_zero && to_i32(i) >= 0 => unsafe {
+                            ArrayRef::from_raw(empty.borrow().as_raw())
+                        }
                         None => break ErrorKind::Bounds(to_i32(i)),
                     }
                     _ => break ErrorKind::TypeBinary(op, a.clone(), i.clone()),
@@ -664,6 +701,7 @@ fn execute_internal(
                     Data::Real(j) => match r.get_raw(to_i32(j)) {
                         // TODO: consider soundness here
                         Some(value) => unsafe { (*value).borrow() }
+                        None if uninitialized_as_zero && to_i32(j) >= 0 => ValueRef::default(),
                         None => break ErrorKind::Bounds(to_i32(j)),
                     }
                     _ => break ErrorKind::TypeBinary(op, Value::from(r.clone()), j.clone()),
@@ -717,9 +755,11 @@ fn execute_internal(
                 code = &assets(engine).code[&function];
                 instruction = 0;
                 reg_base = reg_base + base;
+                spill_base = thread.spills.len();
 
                 let limit = cmp::max(code.locals as usize, len);
                 thread.stack.resize_with(reg_base + limit, Register::default);
+                thread.spills.resize_with(spill_base + code.spills as usize, Register::default);
 
                 let registers = thread.stack[reg_base..][..code.params as usize].iter_mut();
                 for reg in registers.skip(len) {
@@ -803,6 +843,7 @@ fn execute_internal(
                     Some(frame) => frame,
                     None => {
                         let value = unsafe { registers[0].value_ref };
+                        thread.spills.truncate(spill_base);
                         return Ok(value.clone());
                     }
                 };
@@ -811,6 +852,8 @@ fn execute_internal(
                 code = &assets(engine).code[&function];
                 instruction = caller_instruction;
                 reg_base = caller_base;
+                thread.spills.truncate(spill_base);
+                spill_base = thread.spills.len() - code.spills as usize;
 
                 thread.stack.resize_with(reg_base + code.locals as usize, Register::default);
 
@@ -832,6 +875,8 @@ fn execute_internal(
                     _ => break ErrorKind::TypeUnary(op, a.clone()),
                 }
             }
+
+            (code::Op::Wide, _, _, _) => unreachable!("repeated wide prefix"),
         }
 
         instruction += 1;
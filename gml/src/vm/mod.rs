@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 use std::ops::Range;
 
+use project::Version;
+
 use crate::symbol::Symbol;
 use crate::{Function, front::Lines};
 
@@ -13,10 +15,10 @@ pub use crate::vm::array::{Array, ArrayRef};
 
 pub mod code;
 pub mod world;
+pub mod serialize;
 mod entity_map;
 mod instance_map;
 mod interpreter;
-//mod serialize;
 mod value;
 mod array;
 mod debug;
@@ -26,15 +28,22 @@ pub struct Assets<W: ?Sized, A: ?Sized> {
     pub api: HashMap<Symbol, ApiFunction<W, A>>,
     pub get: HashMap<Symbol, GetFunction<W, A>>,
     pub set: HashMap<Symbol, SetFunction<W, A>>,
+
+    /// The language version the code was compiled for, which selects runtime behavior.
+    pub version: Version,
+    /// Read unknown variables and array elements as `0.0` rather than raising an error. GMS has
+    /// no such setting, so this is ignored for its code.
+    pub uninitialized_as_zero: bool,
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Debug {
     pub locations: HashMap<Function, Locations>,
     pub scripts: Vec<Symbol>,
     pub objects: Vec<Symbol>,
 }
 
+#[derive(Clone)]
 pub struct Locations {
     pub locations: code::Locations,
     pub lines: Lines,
@@ -53,6 +62,9 @@ impl<W: ?Sized, A: ?Sized> Default for Assets<W, A> {
             api: HashMap::default(),
             get: HashMap::default(),
             set: HashMap::default(),
+
+            version: Version::default(),
+            uninitialized_as_zero: false,
         }
     }
 }
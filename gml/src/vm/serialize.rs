@@ -0,0 +1,510 @@
+//! A binary cache of compiled code, so projects need not be rebuilt on every launch.
+//!
+//! The cache holds each function's bytecode and constants, the source locations and script and
+//! object names from `vm::Debug`, and a hash of the source it was built from. Everything is
+//! little-endian, and strings are length-prefixed bytes that are interned again on load.
+//!
+//! Engine functions are referred to by name from the constant table, so a loaded cache is bound to
+//! the running engine the same way freshly built code is.
+
+use std::cmp;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt;
+
+use crate::Function;
+use crate::front::Lines;
+use crate::symbol::Symbol;
+use crate::vm::{self, code};
+
+const MAGIC: &[u8; 4] = b"GMLC";
+
+/// The version of the cache format. This must change whenever the encoding or the instruction set
+/// does, so that caches from older builds are rejected rather than misread.
+const VERSION: u32 = 1;
+
+/// An error encountered while loading a cache.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The data ended in the middle of a structure.
+    UnexpectedEof,
+    /// The data does not start with the cache magic number.
+    Magic,
+    /// The cache was written in an unsupported version of the format.
+    Version(u32),
+    /// The cache was built from different source, and must be rebuilt.
+    Stale,
+    /// The data contains a value that is out of range.
+    Corrupt,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Error::UnexpectedEof => write!(f, "unexpected end of file"),
+            Error::Magic => write!(f, "not a code cache"),
+            Error::Version(version) => write!(f, "unsupported cache version {}", version),
+            Error::Stale => write!(f, "cache is out of date"),
+            Error::Corrupt => write!(f, "corrupt cache"),
+        }
+    }
+}
+
+/// A hash of the source that compiled code depends on.
+///
+/// This is 64-bit FNV-1a. Unlike `DefaultHasher`, its output is the same across builds, so it can
+/// be stored in a cache and compared on a later run.
+pub struct SourceHash(u64);
+
+impl Default for SourceHash {
+    fn default() -> Self { SourceHash(0xcbf2_9ce4_8422_2325) }
+}
+
+impl SourceHash {
+    pub fn write_u32(&mut self, value: u32) {
+        self.write(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    /// Hash a length-prefixed string, so adjacent strings cannot run together.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_u32(bytes.len() as u32);
+        self.write(bytes);
+    }
+
+    pub fn finish(&self) -> u64 { self.0 }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+/// Encode compiled code and its debug information, tagged with the `hash` of its source.
+pub fn write(code: &HashMap<Function, code::Function>, debug: &vm::Debug, hash: u64) -> Vec<u8> {
+    let mut writer = Writer::default();
+    writer.data.extend_from_slice(MAGIC);
+    writer.write_u32(VERSION);
+    writer.write_u64(hash);
+
+    writer.write_symbols(&debug.scripts);
+    writer.write_symbols(&debug.objects);
+
+    // Sort the functions so the same code always produces the same cache.
+    let mut functions: Vec<_> = code.keys().copied().collect();
+    functions.sort_by_key(|&function| match function {
+        Function::Event { object_index, event_type, event_kind } =>
+            (0, object_index, event_type, event_kind),
+        Function::Script { id } => (1, id, 0, 0),
+    });
+    writer.write_len(functions.len());
+    for function in functions {
+        writer.write_function(function);
+        writer.write_code(&code[&function]);
+        match debug.locations.get(&function) {
+            Some(locations) => {
+                writer.write_u8(1);
+                writer.write_locations(locations);
+            }
+            None => writer.write_u8(0),
+        }
+    }
+
+    writer.data
+}
+
+/// Decode a cache written by `write`, checking that it was built from source with this `hash`.
+///
+/// Every instruction is validated, so the interpreter can trust loaded code as much as code it has
+/// just built: operands must name registers, constants, spill slots, jump targets and scripts that
+/// exist, functions must belong to known scripts and objects, and source locations must point into
+/// their source. Any violation is reported as `Error::Corrupt`.
+pub fn read(
+    data: &[u8], hash: u64
+) -> Result<(HashMap<Function, code::Function>, vm::Debug), Error> {
+    let mut reader = Reader { data, position: 0 };
+    if reader.read_bytes(MAGIC.len())? != MAGIC {
+        return Err(Error::Magic);
+    }
+    let version = reader.read_u32()?;
+    if version != VERSION {
+        return Err(Error::Version(version));
+    }
+    if reader.read_u64()? != hash {
+        return Err(Error::Stale);
+    }
+
+    let scripts = reader.read_symbols()?;
+    let objects = reader.read_symbols()?;
+    let mut debug = vm::Debug { scripts, objects, ..vm::Debug::default() };
+
+    let mut code = HashMap::default();
+    for _ in 0..reader.read_len()? {
+        let function = reader.read_function()?;
+        let (index, len) = match function {
+            Function::Event { object_index, .. } => (object_index, debug.objects.len()),
+            Function::Script { id } => (id, debug.scripts.len()),
+        };
+        if index < 0 || index as usize >= len {
+            return Err(Error::Corrupt);
+        }
+
+        let function_code = reader.read_code()?;
+        // Runtime errors are reported at the location of the failing instruction, so every
+        // function needs its locations.
+        let locations = match reader.read_u8()? {
+            1 => reader.read_locations()?,
+            _ => return Err(Error::Corrupt),
+        };
+        validate_locations(&locations, function_code.instructions.len())?;
+
+        code.insert(function, function_code);
+        debug.locations.insert(function, locations);
+    }
+
+    if reader.position != data.len() {
+        return Err(Error::Corrupt);
+    }
+
+    // Calls can only be checked once every function is known.
+    for function in code.values() {
+        for (_, (op, script, _, _)) in instructions(&function.instructions)? {
+            if op == code::Op::Call && !code.contains_key(&Function::Script { id: script as i32 }) {
+                return Err(Error::Corrupt);
+            }
+        }
+    }
+
+    Ok((code, debug))
+}
+
+#[derive(Default)]
+struct Writer {
+    data: Vec<u8>,
+}
+
+impl Writer {
+    fn write_u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_len(&mut self, len: usize) {
+        self.write_u32(len as u32);
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_len(bytes.len());
+        self.data.extend_from_slice(bytes);
+    }
+
+    fn write_symbols(&mut self, symbols: &[Symbol]) {
+        self.write_len(symbols.len());
+        for symbol in symbols {
+            self.write_bytes(symbol);
+        }
+    }
+
+    fn write_function(&mut self, function: Function) {
+        match function {
+            Function::Event { object_index, event_type, event_kind } => {
+                self.write_u8(0);
+                self.write_u32(object_index as u32);
+                self.write_u32(event_type);
+                self.write_u32(event_kind as u32);
+            }
+            Function::Script { id } => {
+                self.write_u8(1);
+                self.write_u32(id as u32);
+            }
+        }
+    }
+
+    fn write_code(&mut self, function: &code::Function) {
+        self.write_u32(function.params);
+        self.write_u32(function.locals);
+        self.write_u32(function.spills);
+
+        self.write_len(function.constants.len());
+        for constant in &function.constants {
+            match constant.borrow().decode() {
+                vm::Data::Real(real) => {
+                    self.write_u8(0);
+                    self.write_u64(real.to_bits());
+                }
+                vm::Data::String(string) => {
+                    self.write_u8(1);
+                    self.write_bytes(&string);
+                }
+                // Constants come from literals and `Item::Constant`, and array items are ignored.
+                vm::Data::Array(_) => unreachable!("array constant"),
+            }
+        }
+
+        self.write_len(function.instructions.len());
+        for &code::Inst(bits) in &function.instructions {
+            self.write_u32(bits);
+        }
+    }
+
+    fn write_locations(&mut self, locations: &vm::Locations) {
+        let mappings = &locations.locations.mappings;
+        self.write_len(mappings.len());
+        for &code::SourceMap { offset, location } in mappings {
+            self.write_u32(offset);
+            self.write_u32(location);
+        }
+
+        let Lines { ref actions, ref arguments, ref lines, ref source } = locations.lines;
+        for items in &[actions, arguments] {
+            self.write_len(items.len());
+            for &(offset, first) in items.iter() {
+                self.write_len(offset);
+                self.write_len(first);
+            }
+        }
+        self.write_len(lines.len());
+        for &offset in lines {
+            self.write_len(offset);
+        }
+        self.write_bytes(source);
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let end = self.position.checked_add(len).ok_or(Error::UnexpectedEof)?;
+        let bytes = self.data.get(self.position..end).ok_or(Error::UnexpectedEof)?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32, Error> {
+        let bytes = self.read_bytes(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, Error> {
+        let bytes = self.read_bytes(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn read_len(&mut self) -> Result<usize, Error> {
+        Ok(self.read_u32()? as usize)
+    }
+
+    fn read_string(&mut self) -> Result<&'a [u8], Error> {
+        let len = self.read_len()?;
+        self.read_bytes(len)
+    }
+
+    /// Read `count` items. Lengths come from the file, so nothing is allocated for them up front.
+    fn read_vec<T, F>(&mut self, mut read: F) -> Result<Vec<T>, Error>
+        where F: FnMut(&mut Self) -> Result<T, Error>
+    {
+        let count = self.read_len()?;
+        (0..count).map(|_| read(self)).collect()
+    }
+
+    fn read_symbols(&mut self) -> Result<Vec<Symbol>, Error> {
+        self.read_vec(|reader| Ok(Symbol::intern(reader.read_string()?)))
+    }
+
+    fn read_function(&mut self) -> Result<Function, Error> {
+        match self.read_u8()? {
+            0 => {
+                let object_index = self.read_u32()? as i32;
+                let event_type = self.read_u32()?;
+                let event_kind = self.read_u32()? as i32;
+                Ok(Function::Event { object_index, event_type, event_kind })
+            }
+            1 => Ok(Function::Script { id: self.read_u32()? as i32 }),
+            _ => Err(Error::Corrupt),
+        }
+    }
+
+    fn read_code(&mut self) -> Result<code::Function, Error> {
+        let params = self.read_u32()?;
+        let locals = self.read_u32()?;
+        let spills = self.read_u32()?;
+
+        let constants = self.read_vec(|reader| match reader.read_u8()? {
+            0 => Ok(vm::Value::from(f64::from_bits(reader.read_u64()?))),
+            1 => Ok(vm::Value::from(Symbol::intern(reader.read_string()?))),
+            _ => Err(Error::Corrupt),
+        })?;
+
+        // `Inst::decode` transmutes the opcode byte, so it must name a real `Op`.
+        let instructions = self.read_vec(|reader| {
+            let bits = reader.read_u32()?;
+            if bits & 0xff > code::Op::Wide as u32 {
+                return Err(Error::Corrupt);
+            }
+            Ok(code::Inst(bits))
+        })?;
+
+        let function = code::Function { params, locals, spills, constants, instructions };
+        validate(&function)?;
+        Ok(function)
+    }
+
+    fn read_locations(&mut self) -> Result<vm::Locations, Error> {
+        let mappings = self.read_vec(|reader| {
+            let offset = reader.read_u32()?;
+            let location = reader.read_u32()?;
+            Ok(code::SourceMap { offset, location })
+        })?;
+        let locations = code::Locations { mappings };
+
+        let actions = self.read_vec(|reader| Ok((reader.read_len()?, reader.read_len()?)))?;
+        let arguments = self.read_vec(|reader| Ok((reader.read_len()?, reader.read_len()?)))?;
+        let lines = self.read_vec(|reader| reader.read_len())?;
+        let source = self.read_string()?.to_vec();
+        let lines = Lines { actions, arguments, lines, source };
+
+        Ok(vm::Locations { locations, lines })
+    }
+}
+
+type Decoded = (usize, (code::Op, usize, usize, usize));
+
+/// Decode a function's instructions, each with the offset it starts at.
+///
+/// Every `Op::Wide` prefix must be followed by a single instruction for it to extend.
+fn instructions(instructions: &[code::Inst]) -> Result<Vec<Decoded>, Error> {
+    let mut decoded = Vec::with_capacity(instructions.len());
+    let mut offsets = instructions.iter().enumerate();
+    while let Some((offset, inst)) = offsets.next() {
+        let inst = match inst.decode() {
+            (code::Op::Wide, ..) => match offsets.next() {
+                Some((_, next)) if next.decode().0 != code::Op::Wide => next.decode_wide(*inst),
+                _ => return Err(Error::Corrupt),
+            },
+            inst => inst,
+        };
+        decoded.push((offset, inst));
+    }
+    Ok(decoded)
+}
+
+/// Check that every operand of a function is in range, as codegen guarantees for fresh code.
+fn validate(function: &code::Function) -> Result<(), Error> {
+    use crate::vm::code::Op::*;
+
+    let decoded = instructions(&function.instructions)?;
+    let target = |offset: usize| decoded.binary_search_by_key(&offset, |&(offset, _)| offset);
+
+    let locals = function.locals as usize;
+    if function.params as usize > locals {
+        return Err(Error::Corrupt);
+    }
+    let constants = function.constants.len();
+    let spills = function.spills as usize;
+    let check = |ok: bool| if ok { Ok(()) } else { Err(Error::Corrupt) };
+    let registers = |base: usize, len: usize| check(base + len <= locals);
+
+    for &(_, inst) in &decoded {
+        match inst {
+            (Imm, t, constant, _) | (Lookup, t, constant, _) | (Read, t, constant, _) => {
+                registers(t, 1)?;
+                check(constant < constants)?;
+            }
+            (Spill, s, low, high) | (Reload, s, low, high) => {
+                registers(s, 1)?;
+                check(low | (high << 8) < spills)?;
+            }
+            (DeclareGlobal, constant, _, _) => check(constant < constants)?,
+            (LoadScope, t, _, _) | (StoreScope, t, _, _) |
+            (ScopeError, t, _, _) | (Release, t, _, _) => registers(t, 1)?,
+            (Move, t, a, _) | (Neg, t, a, _) | (Not, t, a, _) | (BitNot, t, a, _) |
+            (LoadPointer, t, a, _) | (NextPointer, t, a, _) | (ExistsEntity, t, a, _) |
+            (ToArray, t, a, _) | (ToScalar, t, a, _) => {
+                registers(t, 1)?;
+                registers(a, 1)?;
+            }
+            (LoadField, t, a, constant) | (LoadFieldDefault, t, a, constant) |
+            (StoreField, t, a, constant) => {
+                registers(t, 1)?;
+                registers(a, 1)?;
+                check(constant < constants)?;
+            }
+            // The script is checked once all functions are read.
+            (Call, _, base, len) => registers(base, cmp::max(len, 1))?,
+            (CallApi, constant, base, len) => {
+                check(constant < constants)?;
+                registers(base, cmp::max(len, 1))?;
+            }
+            (CallGet, constant, base, _) => {
+                check(constant < constants)?;
+                registers(base, 2)?;
+            }
+            (CallSet, constant, base, _) => {
+                check(constant < constants)?;
+                registers(base, 3)?;
+            }
+            (ReleaseWith, ..) | (Ret, ..) => {}
+            (Jump, low, high, _) => check(target(low | (high << 8)).is_ok())?,
+            (BranchFalse, a, low, high) => {
+                registers(a, 1)?;
+                check(target(low | (high << 8)).is_ok())?;
+            }
+            (Wide, ..) => unreachable!("repeated wide prefix"),
+            (_, t, a, b) => {
+                registers(t, 1)?;
+                registers(a, 1)?;
+                registers(b, 1)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that a function's source map and line table stay within its code and source, so that
+/// finding and quoting the location of any instruction cannot fail.
+fn validate_locations(locations: &vm::Locations, len: usize) -> Result<(), Error> {
+    let Lines { ref actions, ref arguments, ref lines, ref source } = locations.lines;
+
+    // `Locations::get_location` expects the first instruction to be mapped.
+    let mappings = &locations.locations.mappings;
+    let ok = matches!(mappings.first(), Some(first) if first.offset == 0) &&
+        in_order(mappings.iter().map(|map| map.offset as usize), len) &&
+        mappings.iter().all(|map| map.location as usize <= source.len()) &&
+        in_order(actions.iter().map(|&(offset, _)| offset), source.len()) &&
+        in_order(actions.iter().map(|&(_, first)| first), arguments.len()) &&
+        in_order(arguments.iter().map(|&(offset, _)| offset), source.len()) &&
+        in_order(arguments.iter().map(|&(_, first)| first), lines.len()) &&
+        in_order(lines.iter().copied(), source.len());
+    if ok { Ok(()) } else { Err(Error::Corrupt) }
+}
+
+/// Whether `items` never decrease and never exceed `limit`.
+fn in_order(mut items: impl Iterator<Item = usize>, limit: usize) -> bool {
+    let mut last = 0;
+    items.all(|item| {
+        let ok = last <= item && item <= limit;
+        last = item;
+        ok
+    })
+}
@@ -1,4 +1,5 @@
 use std::collections::{HashMap, HashSet};
+use std::iter;
 
 use crate::rc_vec::RcVec;
 use crate::symbol::Symbol;
@@ -8,22 +9,33 @@ pub struct World {
     pub entities: vm::EntityAllocator,
     pub members: vm::EntityMap<HashMap<Symbol, vm::Value>>,
 
+    /// The instances of each object, including the instances of its descendants.
     pub objects: HashMap<i32, RcVec<vm::Entity>>,
     pub instances: vm::InstanceMap<i32, vm::Entity>,
+    /// The parent of each object that has one.
+    pub parents: HashMap<i32, i32>,
 
     pub globals: HashSet<Symbol>,
 }
 
 pub const GLOBAL: vm::Entity = vm::Entity(0);
 
-impl Default for World {
-    fn default() -> Self {
+impl World {
+    /// Create a world for a project, recording its object hierarchy so that the instance lists of
+    /// an object include the instances of its descendants.
+    pub fn new(objects: &[project::Object<'_>]) -> Self {
+        let parents = objects.iter().enumerate()
+            .filter(|&(_, object)| object.parent >= 0)
+            .map(|(object_index, object)| (object_index as i32, object.parent))
+            .collect();
+
         let mut world = World {
             entities: vm::EntityAllocator::default(),
             members: vm::EntityMap::default(),
 
             objects: HashMap::default(),
             instances: vm::InstanceMap::default(),
+            parents,
 
             globals: HashSet::default(),
         };
@@ -33,9 +45,16 @@ impl Default for World {
 
         world
     }
-}
 
-impl World {
+    /// Iterate over an object and its ancestors, nearest first.
+    pub fn ancestors(&self, object_index: i32) -> impl Iterator<Item = i32> + '_ {
+        // Bound the walk in case of a cycle, which Game Maker does not allow but does not detect
+        // in project files.
+        let limit = self.parents.len() + 1;
+        iter::successors(Some(object_index), move |object| self.parents.get(object).copied())
+            .take(limit)
+    }
+
     /// Create an entity with a scope, but do not add it to the instance lists.
     pub fn create_entity(&mut self) -> vm::Entity {
         let entity = self.entities.create();
@@ -49,9 +68,12 @@ impl World {
         self.entities.destroy(entity);
     }
 
-    /// Add an entity to the instance lists.
+    /// Add an entity to the instance lists of its object and that object's ancestors.
     pub fn add_entity(&mut self, entity: vm::Entity, object_index: i32, id: i32) {
-        self.objects.entry(object_index).or_default().push(entity);
+        let ancestors: Vec<_> = self.ancestors(object_index).collect();
+        for object_index in ancestors {
+            self.objects.entry(object_index).or_default().push(entity);
+        }
         self.instances.insert(id, entity);
     }
 
@@ -59,9 +81,12 @@ impl World {
     pub fn remove_entity(&mut self, entity: vm::Entity, object_index: i32, id: i32) {
         self.instances.remove(id);
 
-        if let Some(object_instances) = self.objects.get_mut(&object_index) {
-            if let Some(position) = object_instances.iter().position(move |&e| e == entity) {
-                object_instances.remove(position);
+        let ancestors: Vec<_> = self.ancestors(object_index).collect();
+        for object_index in ancestors {
+            if let Some(object_instances) = self.objects.get_mut(&object_index) {
+                if let Some(position) = object_instances.iter().position(move |&e| e == entity) {
+                    object_instances.remove(position);
+                }
             }
         }
     }
@@ -1,9 +1,9 @@
 use std::collections::HashMap;
 use std::convert::TryFrom;
-use std::io;
 use std::ops::Range;
 
 use gml::{Function, Item, symbol::Symbol, vm};
+use gml::diagnostic::{Printer, Renderer};
 
 /// Read script arguments.
 #[test]
@@ -16,7 +16,7 @@ fn arguments() -> Result<(), vm::Error> {
         return argument0 + argument1
     }" });
 
-    let (code, _) = gml::build(&game, &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
     let mut assets = Assets { code };
     let mut world = World::default();
     let mut thread = vm::Thread::default();
@@ -48,7 +48,7 @@ fn member() -> Result<(), vm::Error> {
         return c
     }" });
 
-    let (code, _) = gml::build(&game, &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
     let mut assets = Assets { code };
     let mut world = World::default();
     let mut thread = vm::Thread::default();
@@ -60,6 +60,50 @@ fn member() -> Result<(), vm::Error> {
     Ok(())
 }
 
+/// Read unknown variables as 0 when the project asks for it.
+#[test]
+fn uninitialized_as_zero() -> Result<(), vm::Error> {
+    let mut game = project::Game::default();
+    let items = HashMap::default();
+
+    let read = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script { name: b"read", body: b"{
+        var l;
+        a[1] = 3
+        return self.missing + global.missing + l + a[5] + a[2, 4] + a + a[1]
+    }" });
+
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
+    let mut assets = Assets { code };
+    let mut world = World::default();
+    let mut thread = vm::Thread::default();
+
+    let (_, entity) = world.create_instance();
+    thread.set_self(entity);
+
+    match thread.execute(&mut world, &mut assets, read, vec![]) {
+        Err(vm::Error { kind: vm::ErrorKind::Name(_), .. }) => {}
+        result => panic!("expected an unknown variable, got {:?}", result.map(|_| ())),
+    }
+
+    game.settings.uninitialized_as_zero = true;
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
+    let mut assets = Assets { code };
+
+    assert_eq!(thread.execute(&mut world, &mut assets, read, vec![])?, vm::Value::from(3));
+
+    // GMS has no such setting.
+    game.version = project::Version::GameMakerStudio;
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
+    let mut assets = Assets { code };
+
+    match thread.execute(&mut world, &mut assets, read, vec![]) {
+        Err(vm::Error { kind: vm::ErrorKind::Name(_), .. }) => {}
+        result => panic!("expected an unknown variable, got {:?}", result.map(|_| ())),
+    }
+    Ok(())
+}
+
 /// Read and write builtin variables.
 #[test]
 fn builtin() -> Result<(), vm::Error> {
@@ -89,7 +133,7 @@ fn builtin() -> Result<(), vm::Error> {
         return global_array[1]
     }" });
 
-    let (code, _) = gml::build(&game, &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
     let mut assets = Assets { code };
     let mut world = World::default();
     let mut thread = vm::Thread::default();
@@ -125,7 +169,7 @@ fn global() -> Result<(), vm::Error> {
         return self.a + a
     }" });
 
-    let (code, _) = gml::build(&game, &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
     let mut assets = Assets { code };
     let mut world = World::default();
     let mut thread = vm::Thread::default();
@@ -158,7 +202,7 @@ fn with_scopes() -> Result<(), vm::Error> {
         return argument0.n + argument1.n + argument0.m + argument1.m
     }" });
 
-    let (code, _) = gml::build(&game, &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
     let mut assets = Assets { code };
     let mut world = World::default();
     let mut thread = vm::Thread::default();
@@ -196,7 +240,7 @@ fn with_iterator() -> Result<(), vm::Error> {
     let create_instance = Symbol::intern(b"create_instance");
     items.insert(create_instance, Item::Native(World::native_create_instance, 0, false));
 
-    let (code, _) = gml::build(&game, &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
     let mut assets = Assets { code };
     let mut world = World::default();
     let mut thread = vm::Thread::default();
@@ -209,6 +253,713 @@ fn with_iterator() -> Result<(), vm::Error> {
     Ok(())
 }
 
+/// Iterate over the instances of an object and its descendants.
+#[test]
+fn with_parent() -> Result<(), vm::Error> {
+    let mut game = project::Game::default();
+    let items = HashMap::default();
+
+    game.objects.push(project::Object { name: b"parent", ..project::Object::default() });
+    game.objects.push(project::Object { name: b"child", parent: 0, ..project::Object::default() });
+    game.objects.push(project::Object { name: b"leaf", parent: 1, ..project::Object::default() });
+    game.objects.push(project::Object { name: b"other", ..project::Object::default() });
+
+    let count = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script { name: b"count", body: b"{
+        var n;
+        n = 0
+        with (argument0) {
+            n += 1
+        }
+        return n
+    }" });
+
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
+    let mut assets = Assets { code };
+    let mut world = World::new(&game.objects);
+    let mut thread = vm::Thread::default();
+
+    let instances: Vec<_> = (0..4).map(|object_index| world.create_object_instance(object_index))
+        .collect();
+
+    for &(object_index, expected) in &[(0, 3), (1, 2), (2, 1), (3, 1)] {
+        let arguments = vec![vm::Value::from(object_index)];
+        let n = thread.execute(&mut world, &mut assets, count, arguments)?;
+        assert_eq!(n, vm::Value::from(expected));
+    }
+
+    let (id, entity) = instances[1];
+    world.world.remove_entity(entity, 1, id);
+    let n = thread.execute(&mut world, &mut assets, count, vec![vm::Value::from(0)])?;
+    assert_eq!(n, vm::Value::from(2));
+
+    Ok(())
+}
+
+/// Inherit events from ancestor objects.
+#[test]
+fn inherited_events() -> Result<(), vm::Error> {
+    let mut game = project::Game::default();
+    let items = HashMap::default();
+
+    let event = |event_type, code| project::Event {
+        event_type,
+        event_kind: 0,
+        actions: vec![project::Action {
+            library: 1,
+            action: 603,
+            action_kind: project::action_kind::CODE,
+            has_target: true,
+            action_type: project::action_type::CODE,
+            parameters_used: 1,
+            parameters: vec![project::argument_type::STRING],
+            target: -1,
+            arguments: vec![code],
+            ..project::Action::default()
+        }],
+    };
+    game.objects.push(project::Object {
+        name: b"parent",
+        events: vec![event(0, &b"a = 3"[..]), event(3, &b"b = 1"[..])],
+        ..project::Object::default()
+    });
+    game.objects.push(project::Object {
+        name: b"child",
+        parent: 0,
+        events: vec![event(3, &b"b = 2"[..])],
+        ..project::Object::default()
+    });
+
+    let read = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script { name: b"read", body: b"{
+        return a * 10 + b
+    }" });
+
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
+    let mut assets = Assets { code };
+    let mut world = World::new(&game.objects);
+    let mut thread = vm::Thread::default();
+
+    let (_, entity) = world.create_object_instance(1);
+    thread.set_self(entity);
+
+    let create = Function::Event { object_index: 1, event_type: 0, event_kind: 0 };
+    let step = Function::Event { object_index: 1, event_type: 3, event_kind: 0 };
+    thread.execute(&mut world, &mut assets, create, vec![])?;
+    thread.execute(&mut world, &mut assets, step, vec![])?;
+    assert_eq!(thread.execute(&mut world, &mut assets, read, vec![])?, vm::Value::from(32.0));
+
+    Ok(())
+}
+
+/// Refer to resources and project constants by name.
+#[test]
+fn constants() -> Result<(), vm::Error> {
+    let mut game = project::Game::default();
+    let items = HashMap::default();
+
+    game.sprites.push(project::Sprite { name: b"spr_player", ..project::Sprite::default() });
+    game.sprites.push(project::Sprite { name: b"", ..project::Sprite::default() });
+    game.sprites.push(project::Sprite { name: b"spr_wall", ..project::Sprite::default() });
+    game.objects.push(project::Object { name: b"obj_wall", ..project::Object::default() });
+    game.objects.push(project::Object { name: b"obj_player", ..project::Object::default() });
+
+    game.constants.push(project::Constant { name: b"size", value: b"10 * 10" });
+    game.constants.push(project::Constant { name: b"area", value: b"size * size div 4" });
+    game.constants.push(project::Constant { name: b"title", value: b"'hero' + \"ine\"" });
+    game.constants.push(project::Constant { name: b"player", value: b"obj_player" });
+
+    let resources = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script { name: b"resources", body: b"{
+        return spr_wall * 10 + obj_player
+    }" });
+
+    let area = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script { name: b"get_area", body: b"return area" });
+
+    let title = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script { name: b"get_title", body: b"return title" });
+
+    let player = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script { name: b"get_player", body: b"return player" });
+
+    let shadow = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script { name: b"shadow", body: b"{
+        var size;
+        size = 3
+        return size + resources
+    }" });
+
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
+    let mut assets = Assets { code };
+    let mut world = World::default();
+    let mut thread = vm::Thread::default();
+
+    assert_eq!(thread.execute(&mut world, &mut assets, resources, vec![])?, vm::Value::from(21));
+    assert_eq!(thread.execute(&mut world, &mut assets, area, vec![])?, vm::Value::from(2500));
+    let heroine = vm::Value::from(Symbol::intern(b"heroine"));
+    assert_eq!(thread.execute(&mut world, &mut assets, title, vec![])?, heroine);
+    assert_eq!(thread.execute(&mut world, &mut assets, player, vec![])?, vm::Value::from(1));
+    assert_eq!(thread.execute(&mut world, &mut assets, shadow, vec![])?, vm::Value::from(3));
+
+    Ok(())
+}
+
+/// Refer to constants defined by the engine.
+#[test]
+fn engine_constants() -> Result<(), vm::Error> {
+    let mut game = project::Game::default();
+    let mut items = HashMap::default();
+
+    let c_red = Symbol::intern(b"c_red");
+    items.insert(c_red, Item::Constant(vm::Value::from(0x0000ffu32)));
+    let greeting = Symbol::intern(b"greeting");
+    items.insert(greeting, Item::Constant(vm::Value::from(Symbol::intern(b"hello"))));
+
+    game.constants.push(project::Constant { name: b"c_dark", value: b"c_red div 2" });
+
+    let colors = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script { name: b"colors", body: b"return c_red + c_dark" });
+
+    let greet = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script { name: b"greet", body: b"return greeting" });
+
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
+    let mut assets = Assets { code };
+    let mut world = World::default();
+    let mut thread = vm::Thread::default();
+
+    assert_eq!(thread.execute(&mut world, &mut assets, colors, vec![])?, vm::Value::from(382));
+    let hello = vm::Value::from(Symbol::intern(b"hello"));
+    assert_eq!(thread.execute(&mut world, &mut assets, greet, vec![])?, hello);
+
+    Ok(())
+}
+
+/// Reject assignment to constants and constants that are not constant expressions.
+#[test]
+fn constant_errors() {
+    let mut game = project::Game::default();
+    let items: HashMap<Symbol, Item<World, Assets>> = HashMap::default();
+
+    game.objects.push(project::Object { name: b"obj_player", ..project::Object::default() });
+    game.constants.push(project::Constant { name: b"speed", value: b"1 / 0" });
+    game.constants.push(project::Constant { name: b"limit", value: b"random(10)" });
+    game.scripts.push(project::Script { name: b"assign", body: b"obj_player = 3" });
+
+    match gml::build(&game, &items) {
+        Err(diagnostics) => assert_eq!(diagnostics.len(), 3),
+        Ok(_) => panic!(),
+    }
+}
+
+/// Reject shifts that are out of range while folding constants.
+#[test]
+fn shift_errors() {
+    let mut game = project::Game::default();
+    game.version = project::Version::GameMakerStudio;
+    let items: HashMap<Symbol, Item<World, Assets>> = HashMap::default();
+
+    game.constants.push(project::Constant { name: b"negative", value: b"1 << -1" });
+    game.scripts.push(project::Script { name: b"declarations", body: b"
+        #macro BIG 1 << 40
+        enum E { a = 1 >> 32 }
+    " });
+
+    let diagnostics = match gml::build(&game, &items) {
+        Err(diagnostics) => diagnostics,
+        Ok(_) => panic!(),
+    };
+    assert_eq!(diagnostics.len(), 3);
+    assert!(diagnostics.iter().all(|diagnostic| diagnostic.message == "shift amount out of range"));
+}
+
+/// Report diagnostics with their position and, optionally, the offending line of source.
+#[test]
+fn diagnostics() {
+    let mut game = project::Game::default();
+    let mut items = HashMap::new();
+
+    let add = Symbol::intern(b"add");
+    items.insert(add, Item::Native(World::native_add, 2, false));
+
+    game.scripts.push(project::Script { name: b"scr_move", body: b"{
+\tx = 3
+\ty = x +
+}" });
+    game.scripts.push(project::Script { name: b"scr_add", body: b"return add(1)" });
+
+    let diagnostics = match gml::build(&game, &items) {
+        Err(diagnostics) => diagnostics,
+        Ok(_) => panic!(),
+    };
+    assert_eq!(diagnostics.len(), 2);
+    let diagnostic = &diagnostics[0];
+    assert!(diagnostic.is_error());
+    assert_eq!((diagnostic.position.line, diagnostic.position.column), (Some(4), Some(1)));
+
+    let mut text = Vec::new();
+    Renderer::default().render(diagnostic, &mut text).unwrap();
+    assert_eq!(String::from_utf8(text).unwrap(),
+        "error in script scr_move:4:1: unexpected }; expected expression\n");
+
+    let mut text = Vec::new();
+    Renderer { snippets: true }.render(diagnostic, &mut text).unwrap();
+    assert_eq!(String::from_utf8(text).unwrap(), "\
+error in script scr_move:4:1: unexpected }; expected expression
+  |
+4 | }
+  | ^
+");
+
+    let mut text = Vec::new();
+    Renderer { snippets: true }.render(&diagnostics[1], &mut text).unwrap();
+    assert_eq!(String::from_utf8(text).unwrap(), "\
+error in script scr_add:1:8: wrong number of arguments to function or script
+  |
+1 | return add(1)
+  |        ^^^
+  = note: add takes 2 arguments
+");
+}
+
+/// Warn about likely mistakes without failing the build.
+#[test]
+fn warnings() {
+    let mut game = project::Game::default();
+    let items: HashMap<Symbol, Item<World, Assets>> = HashMap::default();
+
+    game.scripts.push(project::Script { name: b"scr_check", body: b"{
+        var unused, used;
+        used = 1
+        if (used = 1) { used = 2 }
+        switch (used) {
+        case 1: break
+        case 2: exit
+        case 1: break
+        }
+        scr_first(1, 2)
+        return used
+        used = 3
+    }" });
+    game.scripts.push(project::Script { name: b"scr_first", body: b"return argument0" });
+
+    let diagnostics = match gml::build(&game, &items) {
+        Ok((_, _, diagnostics)) => diagnostics,
+        Err(_) => panic!(),
+    };
+    let messages: Vec<_> = diagnostics.iter()
+        .map(|diagnostic| {
+            assert!(!diagnostic.is_error());
+            let line = diagnostic.position.line.unwrap();
+            (line, &diagnostic.message[..])
+        })
+        .collect();
+    assert_eq!(messages, [
+        (2, "unused local variable unused"),
+        (4, "assignment used as a comparison; did you mean `==`?"),
+        (8, "duplicate case label"),
+        (10, "too many arguments to script scr_first"),
+        (12, "unreachable code"),
+    ]);
+}
+
+/// Warn about the first statement of each run of code that control flow never reaches.
+#[test]
+fn unreachable_code() {
+    let mut game = project::Game::default();
+    let items: HashMap<Symbol, Item<World, Assets>> = HashMap::default();
+
+    game.scripts.push(project::Script { name: b"scr_unreachable", body: b"{
+        while (argument0) {
+            break
+            a = 1
+        }
+        switch (argument0) {
+            b = 2
+            case 1: exit
+        }
+        if (argument0) { return 1 } else { return 2 }
+        c = 3
+        if (argument0) { d = 4 }
+    }" });
+
+    let diagnostics = match gml::build(&game, &items) {
+        Ok((_, _, diagnostics)) => diagnostics,
+        Err(_) => panic!(),
+    };
+    let messages: Vec<_> = diagnostics.iter()
+        .map(|diagnostic| (diagnostic.position.line.unwrap(), &diagnostic.message[..]))
+        .collect();
+    assert_eq!(messages, [
+        (4, "unreachable code"),
+        (7, "unreachable code"),
+        (11, "unreachable code"),
+    ]);
+}
+
+/// Pass any number of arguments to scripts that read them through the `argument` array.
+#[test]
+fn argument_array() {
+    let mut game = project::Game::default();
+    let items: HashMap<Symbol, Item<World, Assets>> = HashMap::default();
+
+    game.scripts.push(project::Script { name: b"scr_call", body: b"{
+        scr_array(1, 2, 3)
+        scr_fixed(1, 2, 3)
+    }" });
+    game.scripts.push(project::Script { name: b"scr_array", body: b"return argument[2]" });
+    game.scripts.push(project::Script { name: b"scr_fixed", body: b"return argument1" });
+
+    let diagnostics = match gml::build(&game, &items) {
+        Ok((_, _, diagnostics)) => diagnostics,
+        Err(_) => panic!(),
+    };
+    let messages: Vec<_> = diagnostics.iter()
+        .map(|diagnostic| (diagnostic.position.line.unwrap(), &diagnostic.message[..]))
+        .collect();
+    assert_eq!(messages, [(3, "too many arguments to script scr_fixed")]);
+}
+
+/// Name events the way Game Maker does in diagnostics.
+#[test]
+fn event_names() {
+    let mut game = project::Game::default();
+    let items: HashMap<Symbol, Item<World, Assets>> = HashMap::default();
+
+    let event = |event_type, event_kind| project::Event {
+        event_type,
+        event_kind,
+        actions: vec![project::Action {
+            library: 1,
+            action: 603,
+            action_kind: project::action_kind::CODE,
+            has_target: true,
+            action_type: project::action_type::CODE,
+            parameters_used: 1,
+            parameters: vec![project::argument_type::STRING],
+            target: -1,
+            arguments: vec![&b"a = 1"[..]],
+            ..project::Action::default()
+        }],
+    };
+    let events = [
+        ((0, 0), "Create"),
+        ((2, 3), "Alarm 3"),
+        ((3, 1), "Step Begin"),
+        ((4, 1), "Collision with obj_wall"),
+        ((5, 37), "Keyboard <Left>"),
+        ((9, 65), "Key Press <A>"),
+        ((6, 53), "Mouse Global Left Pressed"),
+        ((7, 0), "Other: Outside Room"),
+        ((7, 12), "Other: User Defined 2"),
+        ((8, 0), "Draw"),
+    ];
+    game.objects.push(project::Object {
+        name: b"obj_player",
+        events: events.iter().map(|&((event_type, event_kind), _)| event(event_type, event_kind))
+            .collect(),
+        ..project::Object::default()
+    });
+    game.objects.push(project::Object { name: b"obj_wall", ..project::Object::default() });
+
+    let (_, debug, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
+    for &((event_type, event_kind), name) in &events {
+        let function = Function::Event { object_index: 0, event_type, event_kind };
+        let mut errors = gml::ErrorPrinter::from_debug(&debug, function, Printer::new(Vec::new()));
+        let span = gml::front::Span { low: 0, high: 0 };
+        gml::ErrorPrinter::error(&mut errors, span, format_args!("message"));
+
+        let expected = format!("error in event {} for object obj_player, action 1:1:1: message\n",
+            name);
+        assert_eq!(String::from_utf8(errors.sink.write).unwrap(), expected);
+
+        // Code that only has the project, like the formatter, names events the same way.
+        let object = Symbol::intern(b"obj_player");
+        let event_kind = gml::EventDisplay::from_game(&game, event_type, event_kind);
+        let display = gml::FunctionDisplay::Event { object, event_type, event_kind };
+        let function = gml::FunctionDisplay::from_debug(&debug, function);
+        assert_eq!(display.to_string(), function.to_string());
+    }
+}
+
+/// Increments, ternaries, array accessors, and string escapes from GameMaker: Studio.
+#[test]
+fn studio_syntax() -> Result<(), vm::Error> {
+    let mut game = project::Game::default();
+    game.version = project::Version::GameMakerStudio;
+    let items = HashMap::default();
+
+    let increment = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script { name: b"increment", body: b"{
+        var i, a;
+        i = 0
+        i++
+        ++i
+        a[0] = i--
+        a[@ 1] = --i
+        return a[0] * 10 + a[1] + (i == 0 ? 100 : i == 1 ? 200 : 300)
+    }" });
+
+    let escape = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script { name: b"escape", body: br#"{
+        return "say \"hi\"\n"
+    }"# });
+
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
+    let mut assets = Assets { code };
+    let mut world = World::default();
+    let mut thread = vm::Thread::default();
+
+    assert_eq!(thread.execute(&mut world, &mut assets, increment, vec![])?, vm::Value::from(120));
+
+    let string = Symbol::intern(b"say \"hi\"\n");
+    assert_eq!(thread.execute(&mut world, &mut assets, escape, vec![])?, vm::Value::from(string));
+    Ok(())
+}
+
+/// Evaluate `&&` and `||` according to the selected language version.
+#[test]
+fn short_circuit() -> Result<(), vm::Error> {
+    for &(version, expected) in &[
+        (project::Version::GameMaker8, 2),
+        (project::Version::GameMakerStudio, 0),
+    ] {
+        let mut game = project::Game::default();
+        game.version = version;
+        let items = HashMap::default();
+
+        let count = Function::Script { id: game.scripts.len() as i32 };
+        game.scripts.push(project::Script { name: b"count", body: b"{
+            global.count = 0
+            if (false && touch()) { return -1 }
+            if (true || touch()) { return global.count }
+            return -1
+        }" });
+
+        game.scripts.push(project::Script { name: b"touch", body: b"{
+            global.count += 1
+            return true
+        }" });
+
+        let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
+        let mut assets = Assets { code };
+        let mut world = World::default();
+        let mut thread = vm::Thread::default();
+
+        let result = thread.execute(&mut world, &mut assets, count, vec![])?;
+        assert_eq!(result, vm::Value::from(expected));
+    }
+    Ok(())
+}
+
+/// Read backslashes in strings according to the selected language version.
+#[test]
+fn string_quoting() -> Result<(), vm::Error> {
+    for &(version, expected) in &[
+        (project::Version::GameMaker8, &br#"a\\b\n"#[..]),
+        (project::Version::GameMakerStudio, b"a\\b\n"),
+    ] {
+        let mut game = project::Game::default();
+        game.version = version;
+        let items = HashMap::default();
+
+        let quote = Function::Script { id: game.scripts.len() as i32 };
+        game.scripts.push(project::Script { name: b"quote", body: br#"return "a\\b\n""# });
+
+        let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
+        let mut assets = Assets { code };
+        let mut world = World::default();
+        let mut thread = vm::Thread::default();
+
+        let result = thread.execute(&mut world, &mut assets, quote, vec![])?;
+        assert_eq!(result, vm::Value::from(Symbol::intern(expected)));
+    }
+    Ok(())
+}
+
+/// Initialize locals in their GMS `var` declaration.
+#[test]
+fn var_initializers() -> Result<(), vm::Error> {
+    let mut game = project::Game::default();
+    game.version = project::Version::GameMakerStudio;
+    let items = HashMap::default();
+
+    let declare = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script { name: b"declare", body: b"{
+        var a = 3, b, c = a * 2;
+        b = 1
+        return a + b + c
+    }" });
+
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
+    let mut assets = Assets { code };
+    let mut world = World::default();
+    let mut thread = vm::Thread::default();
+
+    assert_eq!(thread.execute(&mut world, &mut assets, declare, vec![])?, vm::Value::from(10));
+    Ok(())
+}
+
+/// Fold macros and enum members declared anywhere in the project.
+#[test]
+fn macros_and_enums() -> Result<(), vm::Error> {
+    let mut game = project::Game::default();
+    game.version = project::Version::GameMakerStudio;
+    let items = HashMap::default();
+
+    let state = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script { name: b"state", body: b"{
+        return State.walk * 100 + State.run * 10 + WALK_SPEED
+    }" });
+
+    game.scripts.push(project::Script { name: b"declarations", body: b"
+        enum State { idle, walk = 5, run }
+        #macro WALK_SPEED State.walk - 2
+    " });
+
+    let shadow = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script { name: b"shadow", body: b"{
+        var WALK_SPEED;
+        WALK_SPEED = 1
+        return WALK_SPEED + State.idle
+    }" });
+
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
+    let mut assets = Assets { code };
+    let mut world = World::default();
+    let mut thread = vm::Thread::default();
+
+    assert_eq!(thread.execute(&mut world, &mut assets, state, vec![])?, vm::Value::from(563));
+    assert_eq!(thread.execute(&mut world, &mut assets, shadow, vec![])?, vm::Value::from(1));
+    Ok(())
+}
+
+/// Refer to macros and enums before they are declared, in the same script or in another one.
+#[test]
+fn declaration_order() -> Result<(), vm::Error> {
+    let mut game = project::Game::default();
+    game.version = project::Version::GameMakerStudio;
+    let items = HashMap::default();
+
+    let total = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script { name: b"total", body: b"
+        #macro TOTAL FIRST * 10 + Order.a
+        #macro FIRST Order.b
+        return TOTAL
+    " });
+    game.scripts.push(project::Script { name: b"declarations", body: b"
+        enum Order { a = SECOND, b }
+        #macro SECOND 2
+    " });
+
+    // Members may refer to earlier and later members of their own enum.
+    let members = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script { name: b"members", body: b"
+        enum Chain { a = 1, b = Chain.a + 2, c, d = Chain.e * 2, e = 5 }
+        return Chain.c * 100 + Chain.d
+    " });
+
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
+    let mut assets = Assets { code };
+    let mut world = World::default();
+    let mut thread = vm::Thread::default();
+
+    assert_eq!(thread.execute(&mut world, &mut assets, total, vec![])?, vm::Value::from(32));
+    assert_eq!(thread.execute(&mut world, &mut assets, members, vec![])?, vm::Value::from(410));
+    Ok(())
+}
+
+/// Reject macros and enums that are defined in terms of themselves.
+#[test]
+fn declaration_cycles() {
+    let mut game = project::Game::default();
+    game.version = project::Version::GameMakerStudio;
+    let items: HashMap<Symbol, Item<World, Assets>> = HashMap::default();
+
+    game.scripts.push(project::Script { name: b"declarations", body: b"
+        #macro A B
+        #macro B A + 1
+        #macro C -C
+        enum Loop { a = Loop.b, b }
+        enum Own { a = Own.a }
+    " });
+    game.scripts.push(project::Script { name: b"uses", body: b"return A + B + C + Loop.b" });
+
+    let diagnostics = match gml::build(&game, &items) {
+        Err(diagnostics) => diagnostics,
+        Ok(_) => panic!(),
+    };
+    let messages: Vec<_> = diagnostics.iter()
+        .map(|diagnostic| (diagnostic.position.line.unwrap(), &diagnostic.message[..]))
+        .collect();
+    assert_eq!(messages, [
+        (3, "circular definition of A"),
+        (4, "circular definition of C"),
+        (5, "circular definition of Loop.a"),
+        (6, "circular definition of Own.a"),
+    ]);
+}
+
+/// Reject redefinitions, unknown enum members, and assignment to enum members.
+#[test]
+fn declaration_errors() {
+    let mut game = project::Game::default();
+    game.version = project::Version::GameMakerStudio;
+    let items: HashMap<Symbol, Item<World, Assets>> = HashMap::default();
+
+    game.scripts.push(project::Script { name: b"declarations", body: b"
+        enum State { idle, walk, idle }
+        #macro declarations 3
+    " });
+    game.scripts.push(project::Script { name: b"uses", body: b"{
+        State.walk = 3
+        return State.run
+    }" });
+
+    let diagnostics = match gml::build(&game, &items) {
+        Err(diagnostics) => diagnostics,
+        Ok(_) => panic!(),
+    };
+    let messages: Vec<_> = diagnostics.iter()
+        .map(|diagnostic| (diagnostic.span.low, diagnostic.span.high, &diagnostic.message[..]))
+        .collect();
+    assert_eq!(messages, [
+        (56, 68, "declarations is already defined"),
+        (34, 38, "duplicate enum member idle"),
+        (10, 20, "State.walk is a constant, not a variable"),
+        (46, 49, "unknown member of enum State: run"),
+    ]);
+}
+
+/// Reject indexing constants, macros, and enum members as though they were arrays.
+#[test]
+fn index_constant() {
+    let mut game = project::Game::default();
+    game.version = project::Version::GameMakerStudio;
+    let items: HashMap<Symbol, Item<World, Assets>> = HashMap::default();
+
+    game.objects.push(project::Object { name: b"obj_player", ..project::Object::default() });
+    game.scripts.push(project::Script { name: b"read", body: b"return obj_player[0]" });
+    game.scripts.push(project::Script { name: b"write", body: b"obj_player[1, 2] = 3" });
+    game.scripts.push(project::Script { name: b"macro", body: b"#macro M 3\nreturn M[0]" });
+    game.scripts.push(project::Script { name: b"enum", body: b"enum E { a }\nreturn E.a[0]" });
+
+    let diagnostics = match gml::build(&game, &items) {
+        Err(diagnostics) => diagnostics,
+        Ok(_) => panic!(),
+    };
+    let messages: Vec<_> = diagnostics.iter()
+        .map(|diagnostic| (diagnostic.position.column.unwrap(), &diagnostic.message[..]))
+        .collect();
+    assert_eq!(messages, [
+        (8, "cannot index a constant"),
+        (1, "cannot index a constant"),
+        (8, "cannot index a constant"),
+        (8, "cannot index a constant"),
+    ]);
+}
+
 /// Read and write arrays.
 #[test]
 fn array() -> Result<(), vm::Error> {
@@ -226,7 +977,7 @@ fn array() -> Result<(), vm::Error> {
         return a + a[1] + b[0] + b[1] + b[2] + c + c[1, 1]
     }" });
 
-    let (code, _) = gml::build(&game, &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
     let mut assets = Assets { code };
     let mut world = World::default();
     let mut thread = vm::Thread::default();
@@ -251,7 +1002,7 @@ fn conditional_initialization() -> Result<(), vm::Error> {
         return t
     }" });
 
-    let _: (vm::Assets<World, Assets>, _) = gml::build(&game, &items, io::stderr)
+    let _: (vm::Assets<World, Assets>, _, _) = gml::build(&game, &items)
         .unwrap_or_else(|_| panic!());
     Ok(())
 }
@@ -270,7 +1021,7 @@ fn dead_undef() -> Result<(), vm::Error> {
         return i
     }" });
 
-    let _: (vm::Assets<World, Assets>, _) = gml::build(&game, &items, io::stderr)
+    let _: (vm::Assets<World, Assets>, _, _) = gml::build(&game, &items)
         .unwrap_or_else(|_| panic!());
     Ok(())
 }
@@ -291,7 +1042,7 @@ fn for_loop() -> Result<(), vm::Error> {
         return j
     }" });
 
-    let (code, _) = gml::build(&game, &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
     let mut assets = Assets { code };
     let mut world = World::default();
     let mut thread = vm::Thread::default();
@@ -321,7 +1072,7 @@ fn switch() -> Result<(), vm::Error> {
         return i
     }" });
 
-    let (code, _) = gml::build(&game, &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
     let mut assets = Assets { code };
     let mut world = World::default();
     let mut thread = vm::Thread::default();
@@ -352,7 +1103,7 @@ fn switch_empty() -> Result<(), vm::Error> {
         }
     }" });
 
-    let _: (vm::Assets<World, Assets>, _) = gml::build(&game, &items, io::stderr)
+    let _: (vm::Assets<World, Assets>, _, _) = gml::build(&game, &items)
         .unwrap_or_else(|_| panic!());
     Ok(())
 }
@@ -377,7 +1128,7 @@ fn switch_fallthrough() -> Result<(), vm::Error> {
         return i
     }" });
 
-    let (code, _) = gml::build(&game, &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
     let mut assets = Assets { code };
     let mut world = World::default();
     let mut thread = vm::Thread::default();
@@ -408,7 +1159,7 @@ fn call_script() -> Result<(), vm::Error> {
     let call = Function::Script { id: game.scripts.len() as i32 };
     game.scripts.push(project::Script { name: b"call", body: b"return id(3) + 5" });
 
-    let (code, _) = gml::build(&game, &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
     let mut assets = Assets { code };
     let mut world = World::default();
     let mut thread = vm::Thread::default();
@@ -432,7 +1183,7 @@ fn recurse() -> Result<(), vm::Error> {
         }
     }" });
 
-    let (code, _) = gml::build(&game, &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
     let mut assets = Assets { code };
     let mut world = World::default();
     let mut thread = vm::Thread::default();
@@ -442,6 +1193,273 @@ fn recurse() -> Result<(), vm::Error> {
     Ok(())
 }
 
+/// Refer to more constants than fit in an instruction's 8-bit fields.
+#[test]
+fn many_constants() -> Result<(), vm::Error> {
+    let mut body = String::from("{ var a; a = argument0\n");
+    for i in 1..3000 {
+        body.push_str(&format!("a += {}\n", i));
+    }
+    body.push_str("return a }");
+
+    let mut game = project::Game::default();
+    let items = HashMap::default();
+
+    let sum = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script { name: b"sum", body: body.as_bytes() });
+
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
+    let mut assets = Assets { code };
+    let mut world = World::default();
+    let mut thread = vm::Thread::default();
+
+    let arguments = vec![vm::Value::from(0.5)];
+    let value = thread.execute(&mut world, &mut assets, sum, arguments)?;
+    assert_eq!(value, vm::Value::from(2999.0 * 3000.0 / 2.0 + 0.5));
+    Ok(())
+}
+
+/// Keep more locals live at once, and across a call, than fit in an instruction's 8-bit fields.
+#[test]
+fn many_locals() -> Result<(), vm::Error> {
+    let code = run_locals(300, vm::code::REGISTER_LIMIT)?;
+    assert_eq!(code.spills, 0);
+    Ok(())
+}
+
+/// Spill values when a function needs more registers than instructions can address.
+#[test]
+fn spill() -> Result<(), vm::Error> {
+    let code = run_locals(20, 16)?;
+    assert!(code.spills > 0);
+    assert_eq!(code.locals, 16);
+    Ok(())
+}
+
+/// Spill hundreds of locals, with registers and spill slots past 8 bits, when they do not fit.
+#[test]
+fn spill_locals() -> Result<(), vm::Error> {
+    let code = run_locals(400, 270)?;
+    assert_eq!(code.locals, 270);
+    assert!(code.spills > 256);
+    Ok(())
+}
+
+/// Run a recursive script that keeps `count` locals live through a loop and across a call,
+/// compiled with at most `register_limit` registers, and return its code.
+fn run_locals(count: usize, register_limit: usize) -> Result<vm::code::Function, vm::Error> {
+    let names: Vec<_> = (0..count).map(|i| format!("v{}", i)).collect();
+    let mut body = format!("{{ var {}, i, total;\n", names.join(", "));
+    body.push_str("if (argument0 <= 0) return 0\n");
+    for (i, name) in names.iter().enumerate() {
+        body.push_str(&format!("{} = argument0 + {}\n", name, i));
+    }
+    body.push_str("for (i = 0; i < 3; i += 1) {\n");
+    for name in &names {
+        body.push_str(&format!("{} += i\n", name));
+    }
+    body.push_str("}\n");
+    body.push_str("total = v0 + sum(argument0 - 1)\n");
+    for name in &names[1..] {
+        body.push_str(&format!("total += {}\n", name));
+    }
+    body.push_str("return total }");
+
+    let mut game = project::Game::default();
+    let items = HashMap::default();
+
+    let sum = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script { name: b"sum", body: body.as_bytes() });
+
+    let options = gml::Options { register_limit, ..gml::Options::default() };
+    let (code, _, _) = gml::build_with(&game, &items, &options).unwrap_or_else(|_| panic!());
+    let function = code.code[&sum].clone();
+    let mut assets = Assets { code };
+    let mut world = World::default();
+    let mut thread = vm::Thread::default();
+
+    // Each level adds `count * argument0 + (0 + 1 + ... + count - 1) + count * (0 + 1 + 2)`.
+    let level = |n: usize| count * n + count * (count - 1) / 2 + count * 3;
+    let arguments = vec![vm::Value::from(2)];
+    let value = thread.execute(&mut world, &mut assets, sum, arguments)?;
+    assert_eq!(value, vm::Value::from((level(1) + level(2)) as f64));
+    Ok(function)
+}
+
+/// Reject functions whose operands do not fit in instructions.
+#[test]
+fn too_large() {
+    let mut game = project::Game::default();
+    let mut items = HashMap::new();
+
+    let add = Symbol::intern(b"add");
+    items.insert(add, Item::Native(World::native_add, 2, false));
+
+    game.scripts.push(project::Script { name: b"fits", body: b"return 1 + 2" });
+    game.scripts.push(project::Script { name: b"call", body: b"return add(1, 2)" });
+
+    // Three scratch registers leave room for one value, but not for the two arguments.
+    let options = gml::Options { register_limit: 4, ..gml::Options::default() };
+    let diagnostics = match gml::build_with(&game, &items, &options) {
+        Err(diagnostics) => diagnostics,
+        Ok(_) => panic!(),
+    };
+    let messages: Vec<_> = diagnostics.iter()
+        .map(|diagnostic| &diagnostic.message[..])
+        .collect();
+    assert_eq!(messages, ["function is too large"]);
+}
+
+/// Produce the same results and errors with and without SSA optimization.
+#[test]
+fn optimize() {
+    let mut game = project::Game::default();
+    game.version = project::Version::GameMakerStudio;
+    let items = HashMap::default();
+
+    let scripts: &[(&[u8], &[u8])] = &[
+        (b"arithmetic", b"{
+            return (1 + 2) * 3 - 4 / 8 + 7 mod 4 + 7 div 2 + (5 & 3) + (5 | 3) + (5 ^ 3)
+                + (1 << 4) + (256 >> 2) + -(2) + ~5 + !0 + (2 < 3) + (2 == 2.0) + (1 xor 1)
+        }"),
+        (b"strings", b"{
+            var s;
+            s = \"ab\" + \"c\"
+            if s == \"abc\" && \"abc\" < \"abd\" {
+                return s + \"!\"
+            }
+            return \"no\"
+        }"),
+        (b"dead_branches", b"{
+            var a;
+            a = 3
+            if 1 > 2 {
+                a = 5
+            } else if a == 3 {
+                a = 7
+            }
+            while (0) {
+                a = 9
+            }
+            return a
+        }"),
+        (b"loop", b"{
+            var i, s, t;
+            s = 0
+            t = 4
+            for (i = 0; i < 10; i += 1) {
+                s += i * 2 + t
+            }
+            return s + argument0
+        }"),
+        (b"return_from_loop", b"{
+            var i;
+            i = 0
+            while (1) {
+                i += 1
+                if i > 5 {
+                    return i
+                }
+            }
+        }"),
+        (b"array", b"{
+            var a;
+            a[2] = 3
+            a[1] = 1 + 1
+            b[0] = a[1] * a[2]
+            return b[0] + b
+        }"),
+        (b"divide_by_zero", b"{
+            return 1 / (2 - 2)
+        }"),
+        (b"uninitialized", b"{
+            var a;
+            if 0 {
+                a = 1
+            }
+            return a
+        }"),
+        (b"type_error", b"{
+            return \"a\" - 1
+        }"),
+    ];
+    for &(name, body) in scripts {
+        game.scripts.push(project::Script { name, body });
+    }
+
+    let options = gml::Options { optimize: false, ..gml::Options::default() };
+    let (code, _, _) = gml::build_with(&game, &items, &options).unwrap_or_else(|_| panic!());
+    let mut unoptimized = Assets { code };
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
+    let mut optimized = Assets { code };
+
+    let arithmetic = Function::Script { id: 0 };
+    let len = |assets: &Assets| assets.code.code[&arithmetic].instructions.len();
+    assert!(len(&optimized) < len(&unoptimized));
+
+    let mut world = World::default();
+    let mut thread = vm::Thread::default();
+    let (_, entity) = world.create_instance();
+    thread.set_self(entity);
+
+    for id in 0..scripts.len() as i32 {
+        let script = Function::Script { id };
+        let arguments = || vec![vm::Value::from(100)];
+        let expected = thread.execute(&mut world, &mut unoptimized, script, arguments())
+            .map_err(|error| error.kind);
+        let actual = thread.execute(&mut world, &mut optimized, script, arguments())
+            .map_err(|error| error.kind);
+        assert_eq!(format!("{:?}", actual), format!("{:?}", expected));
+    }
+}
+
+/// List compiled bytecode with resolved constants, callees, jump labels, and source lines.
+#[test]
+fn disassemble() {
+    let mut game = project::Game::default();
+    let items: HashMap<Symbol, Item<World, Assets>> = HashMap::default();
+
+    game.scripts.push(project::Script { name: b"double", body: b"return argument0 * 2" });
+    let main = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script { name: b"main", body: b"{
+        var i, s;
+        s = 0
+        for (i = 0; i < 3; i += 1) {
+            s += double(i)
+        }
+        if s > 4 {
+            return \"big\"
+        }
+        return s
+    }" });
+
+    let (code, debug, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
+    let listing = code.code[&main].disassemble(&debug, main).to_string();
+    let lines: Vec<_> = listing.lines().collect();
+    let operands = |op| -> Vec<String> {
+        lines.iter()
+            .filter_map(|line| {
+                let mut words = line.split_whitespace().skip(1);
+                if words.next()? != op { return None; }
+                Some(words.collect::<Vec<_>>().join(" "))
+            })
+            .collect()
+    };
+
+    assert!(lines[0].starts_with("params 1, locals "), "{}", listing);
+    assert!(lines.contains(&"    ; line 5: s += double(i)"), "{}", listing);
+    assert!(lines.contains(&"    ; line 8: return \"big\""), "{}", listing);
+    assert!(operands("Imm").iter().any(|imm| imm.ends_with(", \"big\"")), "{}", listing);
+    assert!(operands("Read").iter().any(|read| read.ends_with(", s")), "{}", listing);
+    for call in operands("Call") {
+        assert!(call.starts_with("double, %") && call.ends_with(", 1"), "{}", listing);
+    }
+    for target in Iterator::chain(operands("Jump").into_iter(), operands("BranchFalse")) {
+        let label = format!("{}:", target.rsplit(' ').next().unwrap());
+        assert!(lines.contains(&&label[..]), "{}", listing);
+    }
+}
+
 /// Call a native function.
 #[test]
 fn ffi() -> Result<(), vm::Error> {
@@ -461,7 +1479,7 @@ fn ffi() -> Result<(), vm::Error> {
         return add(3, 5) + 8
     }" });
 
-    let (code, _) = gml::build(&game, &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (code, _, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
     let mut assets = Assets { code };
     let mut world = World::default();
     let mut thread = vm::Thread::default();
@@ -470,6 +1488,152 @@ fn ffi() -> Result<(), vm::Error> {
     Ok(())
 }
 
+/// Save built code, load it back bound to the same engine, and reject stale or damaged caches.
+#[test]
+fn cache() -> Result<(), vm::Error> {
+    let mut game = project::Game::default();
+    let mut items = HashMap::new();
+
+    let add = Symbol::intern(b"add");
+    items.insert(add, Item::Native(World::native_add, 2, false));
+
+    let caller = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script { name: b"caller", body: b"{
+        var a;
+        a = add(3, double(5))
+        if a > 10 {
+            return \"big\"
+        }
+        return a
+    }" });
+    game.scripts.push(project::Script { name: b"double", body: b"return argument0 * 2" });
+
+    let options = gml::Options::default();
+    let (code, debug, _) = gml::build(&game, &items).unwrap_or_else(|_| panic!());
+    let data = gml::save(&code, &debug, &game, &items, &options);
+    let (loaded, loaded_debug) =
+        gml::load(&data, &game, &items, &options).unwrap_or_else(|_| panic!());
+
+    assert_eq!(loaded_debug.scripts, debug.scripts);
+    for function in &[caller, Function::Script { id: 1 }] {
+        let built = code.code[function].disassemble(&debug, *function).to_string();
+        let restored = loaded.code[function].disassemble(&loaded_debug, *function).to_string();
+        assert_eq!(restored, built);
+    }
+
+    let mut assets = Assets { code: loaded };
+    let mut world = World::default();
+    let mut thread = vm::Thread::default();
+    let big = vm::Value::from(Symbol::intern(b"big"));
+    assert_eq!(thread.execute(&mut world, &mut assets, caller, vec![])?, big);
+
+    use gml::vm::serialize::Error;
+    fn error(
+        data: &[u8], game: &project::Game, items: &HashMap<Symbol, Item<World, Assets>>,
+        options: &gml::Options
+    ) -> Option<Error> {
+        gml::load(data, game, items, options).err()
+    }
+    let unoptimized = gml::Options { optimize: false, ..gml::Options::default() };
+    assert_eq!(error(&data[..data.len() - 1], &game, &items, &options), Some(Error::UnexpectedEof));
+    assert_eq!(error(b"not a cache", &game, &items, &options), Some(Error::Magic));
+    assert_eq!(error(&data, &game, &HashMap::new(), &options), Some(Error::Stale));
+    assert_eq!(error(&data, &game, &items, &unoptimized), Some(Error::Stale));
+
+    // Clear the first function's `locals`, after the header, the script and object names, the
+    // function count and the function's tag, id and `params`.
+    let mut corrupt = data.clone();
+    let locals = 16 + (4 + 10 + 10) + 4 + 4 + 5 + 4;
+    corrupt[locals..locals + 4].copy_from_slice(&0u32.to_le_bytes());
+    assert_eq!(error(&corrupt, &game, &items, &options), Some(Error::Corrupt));
+
+    game.scripts[1].body = b"return argument0 * 3";
+    assert_eq!(error(&data, &game, &items, &options), Some(Error::Stale));
+
+    Ok(())
+}
+
+/// Reject caches whose functions, indices or source locations would fail once loaded.
+#[test]
+fn cache_validation() {
+    use gml::vm::serialize::{self, Error};
+
+    let mut game = project::Game::default();
+    let items = HashMap::default();
+
+    game.objects.push(project::Object {
+        name: b"object",
+        events: vec![project::Event {
+            event_type: 0,
+            event_kind: 0,
+            actions: vec![project::Action {
+                library: 1,
+                action: 603,
+                action_kind: project::action_kind::CODE,
+                has_target: true,
+                action_type: project::action_type::CODE,
+                parameters_used: 1,
+                parameters: vec![project::argument_type::STRING],
+                target: -1,
+                arguments: vec![&b"a = double(1)"[..]],
+                ..project::Action::default()
+            }],
+        }],
+        ..project::Object::default()
+    });
+    let caller = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script { name: b"caller", body: b"{
+        var a;
+        a = double(5)
+        return a
+    }" });
+    game.scripts.push(project::Script { name: b"double", body: b"return argument0 * 2" });
+    let create = Function::Event { object_index: 0, event_type: 0, event_kind: 0 };
+
+    let (assets, debug, _) =
+        gml::build::<World, Assets>(&game, &items).unwrap_or_else(|_| panic!());
+    let code = assets.code;
+    let read = |code: &HashMap<Function, vm::code::Function>, debug: &vm::Debug| {
+        serialize::read(&serialize::write(code, debug, 0), 0).err()
+    };
+    assert_eq!(read(&code, &debug), None);
+
+    // Functions and calls must refer to scripts and objects that exist.
+    let mut scripts = debug.clone();
+    scripts.scripts.truncate(1);
+    assert_eq!(read(&code, &scripts), Some(Error::Corrupt));
+    let mut objects = debug.clone();
+    objects.objects.clear();
+    assert_eq!(read(&code, &objects), Some(Error::Corrupt));
+
+    // Parameters are passed in registers.
+    let mut params = code.clone();
+    let function = params.get_mut(&caller).unwrap();
+    function.params = function.locals + 1;
+    assert_eq!(read(&params, &debug), Some(Error::Corrupt));
+
+    // Every function must have locations, starting at its first instruction.
+    let mut missing = debug.clone();
+    missing.locations.remove(&create);
+    assert_eq!(read(&code, &missing), Some(Error::Corrupt));
+    let corrupt = |change: &dyn Fn(&mut vm::Locations)| {
+        let mut debug = debug.clone();
+        change(debug.locations.get_mut(&caller).unwrap());
+        read(&code, &debug)
+    };
+    assert_eq!(corrupt(&|l| l.locations.mappings[0].offset = 1), Some(Error::Corrupt));
+    assert_eq!(corrupt(&|l| l.locations.mappings.clear()), Some(Error::Corrupt));
+    assert_eq!(corrupt(&|l| l.locations.mappings.reverse()), Some(Error::Corrupt));
+    assert_eq!(corrupt(&|l| l.locations.mappings[0].location = 1000), Some(Error::Corrupt));
+
+    // Line and action offsets must be in order and inside the source.
+    assert_eq!(corrupt(&|l| l.lines.source.truncate(1)), Some(Error::Corrupt));
+    assert_eq!(corrupt(&|l| l.lines.lines.push(1000)), Some(Error::Corrupt));
+    assert_eq!(corrupt(&|l| l.lines.lines.reverse()), Some(Error::Corrupt));
+    assert_eq!(corrupt(&|l| l.lines.actions.push((1000, 0))), Some(Error::Corrupt));
+    assert_eq!(corrupt(&|l| l.lines.arguments.push((0, 1000))), Some(Error::Corrupt));
+}
+
 struct World {
     world: vm::World,
 
@@ -491,9 +1655,13 @@ impl vm::Api<'_, Assets> for World {
 }
 
 impl Default for World {
-    fn default() -> Self {
+    fn default() -> Self { World::new(&[]) }
+}
+
+impl World {
+    fn new(objects: &[project::Object<'_>]) -> Self {
         World {
-            world: vm::World::default(),
+            world: vm::World::new(objects),
 
             next_id: 100001,
             instances: HashMap::default(),
@@ -502,9 +1670,7 @@ impl Default for World {
             global_array: <[f32; 2]>::default(),
         }
     }
-}
 
-impl World {
     fn native_add(
         &mut self, _: &mut Assets, thread: &mut vm::Thread, arguments: Range<usize>
     ) -> Result<vm::Value, vm::ErrorKind> {
@@ -525,11 +1691,15 @@ impl World {
     }
 
     fn create_instance(&mut self) -> (i32, vm::Entity) {
+        self.create_object_instance(0)
+    }
+
+    fn create_object_instance(&mut self, object_index: i32) -> (i32, vm::Entity) {
         let id = self.next_id;
         self.next_id += 1;
 
         let entity = self.world.create_entity();
-        self.world.add_entity(entity, 0, id);
+        self.world.add_entity(entity, object_index, id);
         (id, entity)
     }
 
@@ -0,0 +1,306 @@
+//! Just enough JSON for the Language Server Protocol.
+
+use std::{char, fmt, str};
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    /// Fields are kept in order, so responses are written out the way they were built.
+    Object(Vec<(String, Value)>),
+}
+
+static NULL: Value = Value::Null;
+
+/// Build an object from a list of fields.
+pub fn object(fields: Vec<(&str, Value)>) -> Value {
+    Value::Object(fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+}
+
+impl Value {
+    pub fn parse(text: &[u8]) -> Option<Value> {
+        let mut parser = Parser { text, position: 0 };
+        let value = parser.parse_value()?;
+        parser.skip_whitespace();
+        if parser.position != text.len() {
+            return None;
+        }
+        Some(value)
+    }
+
+    /// Look up a field of an object, or `Value::Null` if there is no such field.
+    pub fn get(&self, key: &str) -> &Value {
+        match *self {
+            Value::Object(ref fields) => fields.iter()
+                .find(|&&(ref field, _)| field == key)
+                .map_or(&NULL, |&(_, ref value)| value),
+            _ => &NULL,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::String(ref string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Number(number) => Some(number),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match *self {
+            Value::Array(ref array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool { *self == Value::Null }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Value { Value::Bool(value) }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Value { Value::Number(value) }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Value { Value::Number(value as f64) }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Value { Value::String(value.to_string()) }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Value { Value::String(value) }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(value: Vec<Value>) -> Value { Value::Array(value) }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Number(value) if value.is_finite() => write!(f, "{}", value),
+            Value::Number(_) => write!(f, "null"),
+            Value::String(ref value) => write_string(value, f),
+            Value::Array(ref values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 { write!(f, ",")?; }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Value::Object(ref fields) => {
+                write!(f, "{{")?;
+                for (i, &(ref key, ref value)) in fields.iter().enumerate() {
+                    if i > 0 { write!(f, ",")?; }
+                    write_string(key, f)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(value: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn parse_value(&mut self) -> Option<Value> {
+        self.skip_whitespace();
+        match *self.text.get(self.position)? {
+            b'n' => self.parse_keyword(b"null", Value::Null),
+            b't' => self.parse_keyword(b"true", Value::Bool(true)),
+            b'f' => self.parse_keyword(b"false", Value::Bool(false)),
+            b'"' => self.parse_string().map(Value::String),
+            b'[' => {
+                self.position += 1;
+                let mut values = Vec::new();
+                if !self.eat(b']') {
+                    loop {
+                        values.push(self.parse_value()?);
+                        if self.eat(b']') { break; }
+                        if !self.eat(b',') { return None; }
+                    }
+                }
+                Some(Value::Array(values))
+            }
+            b'{' => {
+                self.position += 1;
+                let mut fields = Vec::new();
+                if !self.eat(b'}') {
+                    loop {
+                        self.skip_whitespace();
+                        let key = self.parse_string()?;
+                        if !self.eat(b':') { return None; }
+                        fields.push((key, self.parse_value()?));
+                        if self.eat(b'}') { break; }
+                        if !self.eat(b',') { return None; }
+                    }
+                }
+                Some(Value::Object(fields))
+            }
+            _ => self.parse_number(),
+        }
+    }
+
+    fn parse_keyword(&mut self, keyword: &[u8], value: Value) -> Option<Value> {
+        if !self.text[self.position..].starts_with(keyword) {
+            return None;
+        }
+        self.position += keyword.len();
+        Some(value)
+    }
+
+    fn parse_number(&mut self) -> Option<Value> {
+        let low = self.position;
+        while let Some(&c) = self.text.get(self.position) {
+            match c {
+                b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E' => self.position += 1,
+                _ => break,
+            }
+        }
+        let number = str::from_utf8(&self.text[low..self.position]).ok()?;
+        number.parse().ok().map(Value::Number)
+    }
+
+    fn parse_string(&mut self) -> Option<String> {
+        if !self.eat(b'"') {
+            return None;
+        }
+
+        let mut bytes = Vec::new();
+        loop {
+            let c = *self.text.get(self.position)?;
+            self.position += 1;
+            match c {
+                b'"' => break,
+                b'\\' => {
+                    let c = *self.text.get(self.position)?;
+                    self.position += 1;
+                    let c = match c {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let high = self.parse_hex()?;
+                            let unit = if (0xd800..0xdc00).contains(&high) {
+                                if !self.text[self.position..].starts_with(b"\\u") {
+                                    return None;
+                                }
+                                self.position += 2;
+                                let low = self.parse_hex()?;
+                                0x10000 + ((high - 0xd800) << 10) + (low.checked_sub(0xdc00)?)
+                            } else {
+                                high
+                            };
+                            char::from_u32(unit)?
+                        }
+                        _ => return None,
+                    };
+                    let mut buffer = [0; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+                }
+                c => bytes.push(c),
+            }
+        }
+
+        String::from_utf8(bytes).ok()
+    }
+
+    fn parse_hex(&mut self) -> Option<u32> {
+        let digits = self.text.get(self.position..self.position + 4)?;
+        self.position += 4;
+        u32::from_str_radix(str::from_utf8(digits).ok()?, 16).ok()
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        self.skip_whitespace();
+        if self.text.get(self.position) == Some(&c) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(&c) = self.text.get(self.position) {
+            match c {
+                b' ' | b'\t' | b'\n' | b'\r' => self.position += 1,
+                _ => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Value, object};
+
+    #[test]
+    fn round_trip() {
+        let text = br#" {"id": 1, "params": {
+            "text": "a\"b\\c\n\u00e9\ud83d\ude00", "list": [true, false, null, -2.5e1]
+        }} "#;
+        let value = Value::parse(text).unwrap();
+        assert_eq!(value.get("id").as_f64(), Some(1.0));
+        assert_eq!(value.get("params").get("text").as_str(), Some("a\"b\\c\n\u{e9}\u{1f600}"));
+        assert_eq!(value.get("params").get("list"), &Value::Array(vec![
+            Value::Bool(true), Value::Bool(false), Value::Null, Value::Number(-25.0),
+        ]));
+        assert!(value.get("missing").is_null());
+
+        let text = value.to_string();
+        assert_eq!(text, "{\"id\":1,\"params\":{\"text\":\"a\\\"b\\\\c\\n\u{e9}\u{1f600}\",\
+            \"list\":[true,false,null,-25]}}");
+        assert_eq!(Value::parse(text.as_bytes()), Some(value));
+    }
+
+    #[test]
+    fn invalid() {
+        for &text in &[&b""[..], b"{", b"[1,]", b"{\"a\" 1}", b"\"\\x\"", b"nul", b"1 2"] {
+            assert_eq!(Value::parse(text), None, "{:?}", String::from_utf8_lossy(text));
+        }
+        assert_eq!(object(vec![("a", Value::Null)]).to_string(), "{\"a\":null}");
+    }
+}
@@ -0,0 +1,190 @@
+//! A language server for GML, spoken over stdio.
+//!
+//! Usage: `lsp <project.gmk|project.project.gmx>`. Diagnostics come from checking the whole
+//! project each time a script changes, and names resolve the same way they do in the build.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::{env, fs, process};
+
+use gml::symbol::Symbol;
+
+use crate::json::Value;
+use crate::server::Server;
+
+mod json;
+mod server;
+
+fn main() {
+    let path = match env::args_os().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: lsp <project>");
+            process::exit(1);
+        }
+    };
+    let path = Path::new(&path);
+
+    let game = read_project(path).unwrap_or_else(|error| {
+        eprintln!("error: {}: {}", path.display(), error);
+        process::exit(1);
+    });
+    let mut files = HashMap::new();
+    if let Some(directory) = path.parent() {
+        find_scripts(directory, &mut files);
+    }
+
+    let mut server = Server::new(game, files);
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    if let Err(error) = serve(&mut server, stdin.lock(), stdout.lock()) {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    }
+}
+
+fn read_project(path: &Path) -> Result<project::owned::Game, project::Error> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("gmk") | Some("gm81") => {
+            let data = fs::read(path)?;
+            let blocks = project::gmk::Blocks::read(&data)?;
+            let game = blocks.game()?;
+            Ok(project::owned::Game::from(&game))
+        }
+        _ => project::gmx::read(path),
+    }
+}
+
+/// Find the .gml files under a project directory, which hold the scripts of Studio projects.
+fn find_scripts(directory: &Path, files: &mut HashMap<Symbol, String>) {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        if path.is_dir() {
+            find_scripts(&path, files);
+        } else if path.extension().map_or(false, |extension| extension == "gml") {
+            let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(name) => Symbol::intern(name.as_bytes()),
+                None => continue,
+            };
+            let path = fs::canonicalize(&path).unwrap_or(path);
+            files.insert(name, format!("file://{}", path.display()));
+        }
+    }
+}
+
+/// Exchange messages until the client says to exit.
+fn serve<R: BufRead, W: Write>(server: &mut Server, mut read: R, mut write: W) -> io::Result<()> {
+    while !server.exit {
+        let message = match read_message(&mut read)? {
+            Some(message) => message,
+            None => break,
+        };
+        let responses = match Value::parse(&message) {
+            Some(message) => server.handle(&message),
+            None => vec![json::object(vec![
+                ("jsonrpc", Value::from("2.0")),
+                ("id", Value::Null),
+                ("error", json::object(vec![
+                    ("code", Value::from(-32700.0)),
+                    ("message", Value::from("parse error")),
+                ])),
+            ])],
+        };
+        for response in responses {
+            write_message(&mut write, &response)?;
+        }
+    }
+    Ok(())
+}
+
+/// Read the content of a message, or `None` at the end of the stream.
+fn read_message<R: BufRead>(read: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if read.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let mut parts = line.splitn(2, ':');
+        if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+
+    let length = length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header")
+    })?;
+    let mut content = vec![0; length];
+    read.read_exact(&mut content)?;
+    Ok(Some(content))
+}
+
+fn write_message<W: Write>(write: &mut W, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+    write!(write, "Content-Length: {}\r\n\r\n{}", content.len(), content)?;
+    write.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::str;
+    use crate::json::Value;
+    use crate::server::Server;
+
+    fn frame(content: &str) -> String {
+        format!("Content-Length: {}\r\n\r\n{}", content.len(), content)
+    }
+
+    #[test]
+    fn exchange() {
+        let mut game = project::owned::Game::default();
+        game.version = project::Version::GameMakerStudio;
+        game.scripts.push(project::owned::Script { name: b"scr".to_vec(), body: vec![] });
+        let mut server = Server::new(game, HashMap::new());
+
+        let input = [
+            frame(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#),
+            frame(r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":
+                {"uri":"file:///scr.gml","languageId":"gml","version":1,"text":"a = ;"}}}"#),
+            frame(r#"{"jsonrpc":"2.0","id":2,"#),
+            frame(r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#),
+            frame(r#"{"jsonrpc":"2.0","method":"exit"}"#),
+            frame(r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#),
+        ].concat();
+        let mut output = Vec::new();
+        super::serve(&mut server, input.as_bytes(), &mut output).unwrap();
+
+        // Split the output back into messages, and check their headers along the way.
+        let mut output = str::from_utf8(&output).unwrap();
+        let mut messages = Vec::new();
+        while !output.is_empty() {
+            let header_end = output.find("\r\n\r\n").unwrap();
+            let length: usize = output[..header_end]["Content-Length: ".len()..].parse().unwrap();
+            let content = &output[header_end + 4..header_end + 4 + length];
+            messages.push(Value::parse(content.as_bytes()).unwrap());
+            output = &output[header_end + 4 + length..];
+        }
+
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0].get("id").as_f64(), Some(1.0));
+        let diagnostics = messages[1].get("params").get("diagnostics").as_array().unwrap();
+        assert_eq!(diagnostics[0].get("message").as_str(),
+            Some("unexpected ;; expected expression"));
+        assert_eq!(messages[2].to_string(),
+            r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32700,"message":"parse error"}}"#);
+        assert_eq!(messages[3].to_string(), r#"{"jsonrpc":"2.0","id":2,"result":null}"#);
+    }
+}
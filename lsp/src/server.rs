@@ -0,0 +1,590 @@
+use std::collections::HashMap;
+use std::str;
+
+use gml::{FunctionDisplay, Item};
+use gml::back::ssa;
+use gml::diagnostic::{Diagnostic, Renderer, Severity};
+use gml::front::Span;
+use gml::front::cst::SyntaxTokens;
+use gml::front::token::Token;
+use gml::symbol::Symbol;
+use engine::{World, Assets};
+
+use crate::json::{self, Value};
+
+/// The state of a language server session over a single project.
+///
+/// Scripts are matched to documents by file name, so `scripts/scr_move.gml` is `scr_move`.
+/// While a document is open, its text replaces the script body it was loaded with.
+pub struct Server {
+    game: project::owned::Game,
+    items: HashMap<Symbol, Item<World, Assets>>,
+    prototypes: HashMap<Symbol, ssa::Prototype>,
+
+    /// The URI of each script's file, for go-to-definition.
+    files: HashMap<Symbol, String>,
+    /// The open documents, by URI.
+    documents: HashMap<String, Vec<u8>>,
+
+    pub shutdown: bool,
+    pub exit: bool,
+}
+
+impl Server {
+    pub fn new(game: project::owned::Game, files: HashMap<Symbol, String>) -> Server {
+        let mut items = HashMap::default();
+        World::register(&mut items);
+        let prototypes = gml::prototypes(&game.borrow(), &items);
+
+        Server {
+            game, items, prototypes, files,
+            documents: HashMap::default(),
+            shutdown: false,
+            exit: false,
+        }
+    }
+
+    /// Handle a request or notification, and return the messages to send in reply.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let id = message.get("id");
+        let params = message.get("params");
+        let method = message.get("method").as_str().unwrap_or("");
+
+        let result = match method {
+            "initialize" => Ok(json::object(vec![
+                ("capabilities", json::object(vec![
+                    ("textDocumentSync", Value::from(1usize)),
+                    ("hoverProvider", Value::from(true)),
+                    ("definitionProvider", Value::from(true)),
+                    ("completionProvider", json::object(vec![])),
+                ])),
+                ("serverInfo", json::object(vec![("name", Value::from("gml"))])),
+            ])),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "exit" => {
+                self.exit = true;
+                return vec![];
+            }
+
+            "textDocument/didOpen" => {
+                let document = params.get("textDocument");
+                let text = document.get("text").as_str().unwrap_or("");
+                return self.update(document.get("uri"), Some(text));
+            }
+            "textDocument/didChange" => {
+                let changes = params.get("contentChanges").as_array().unwrap_or(&[]);
+                let text = changes.last().and_then(|change| change.get("text").as_str());
+                return self.update(params.get("textDocument").get("uri"), text);
+            }
+            "textDocument/didClose" => return self.close(params.get("textDocument").get("uri")),
+
+            "textDocument/hover" => Ok(self.hover(params)),
+            "textDocument/definition" => Ok(self.definition(params)),
+            "textDocument/completion" => Ok(self.completion()),
+
+            _ => Err(json::object(vec![
+                ("code", Value::from(-32601.0)),
+                ("message", Value::from(format!("unknown method {}", method))),
+            ])),
+        };
+
+        // Notifications get no response, even when they are not understood.
+        if id.is_null() {
+            return vec![];
+        }
+        let (key, value) = match result {
+            Ok(result) => ("result", result),
+            Err(error) => ("error", error),
+        };
+        vec![json::object(vec![("jsonrpc", Value::from("2.0")), ("id", id.clone()), (key, value)])]
+    }
+
+    fn update(&mut self, uri: &Value, text: Option<&str>) -> Vec<Value> {
+        let (uri, text) = match (uri.as_str(), text) {
+            (Some(uri), Some(text)) => (uri, text),
+            _ => return vec![],
+        };
+        self.documents.insert(uri.to_string(), text.as_bytes().to_vec());
+
+        let name = script_name(uri);
+        if let Some(script) = self.game.scripts.iter_mut().find(|script| script.name == name) {
+            script.body = text.as_bytes().to_vec();
+        }
+        let (prototypes, diagnostics) = gml::check(&self.game.borrow(), &self.items);
+        self.prototypes = prototypes;
+
+        self.publish_diagnostics(&diagnostics)
+    }
+
+    fn close(&mut self, uri: &Value) -> Vec<Value> {
+        let uri = match uri.as_str() {
+            Some(uri) => uri,
+            None => return vec![],
+        };
+        self.documents.remove(uri);
+        vec![publish(uri, vec![])]
+    }
+
+    /// Report each open document's diagnostics.
+    ///
+    /// Events, timelines, and constants have no documents, so their diagnostics are logged instead.
+    fn publish_diagnostics(&self, diagnostics: &[Diagnostic]) -> Vec<Value> {
+        let mut uris: Vec<_> = self.documents.keys().collect();
+        uris.sort();
+        let published = uris.into_iter().map(|uri| {
+            let text = &self.documents[uri];
+            let script = Symbol::intern(&script_name(uri));
+            let diagnostics = diagnostics.iter()
+                .filter(|diagnostic| match diagnostic.function {
+                    FunctionDisplay::Script { script: name } => name == script,
+                    _ => false,
+                })
+                .map(|diagnostic| self.diagnostic(text, diagnostic))
+                .collect();
+            publish(uri, diagnostics)
+        });
+
+        let logged = diagnostics.iter()
+            .filter(|diagnostic| !matches!(diagnostic.function, FunctionDisplay::Script { .. }))
+            .map(log);
+
+        published.chain(logged).collect()
+    }
+
+    fn diagnostic(&self, text: &[u8], diagnostic: &Diagnostic) -> Value {
+        let severity: usize = match diagnostic.severity {
+            Severity::Error => 1,
+            Severity::Warning => 2,
+        };
+        let mut message = diagnostic.message.clone();
+        for note in &diagnostic.notes {
+            message.push_str("\nnote: ");
+            message.push_str(note);
+        }
+
+        json::object(vec![
+            ("range", range(text, diagnostic.span)),
+            ("severity", Value::from(severity)),
+            ("source", Value::from("gml")),
+            ("message", Value::from(message)),
+        ])
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        let (symbol, span, text) = match self.symbol_at(params) {
+            Some(symbol) => symbol,
+            None => return Value::Null,
+        };
+        let contents = match self.prototypes.get(&symbol) {
+            Some(&ssa::Prototype::Script { .. }) => format!("script `{}`", symbol),
+            Some(&ssa::Prototype::Native { arity, variadic }) => {
+                let more = if variadic { " or more" } else { "" };
+                let s = if arity == 1 && !variadic { "" } else { "s" };
+                format!("function `{}`, taking {}{} argument{}", symbol, arity, more, s)
+            }
+            Some(&ssa::Prototype::Member) => format!("built-in variable `{}`", symbol),
+            Some(&ssa::Prototype::Constant(ssa::Constant::Real(value))) =>
+                format!("constant `{}` = {}", symbol, value),
+            Some(&ssa::Prototype::Constant(ssa::Constant::String(value))) =>
+                format!("constant `{}` = \"{}\"", symbol, value),
+            Some(&ssa::Prototype::Enum(ref members)) => {
+                let mut members: Vec<_> = members.iter().collect();
+                members.sort_by(|a, b| f64::total_cmp(a.1, b.1));
+                let members: Vec<_> = members.iter()
+                    .map(|&(member, value)| format!("{} = {}", member, value))
+                    .collect();
+                format!("enum `{}` {{ {} }}", symbol, members.join(", "))
+            }
+            None => return Value::Null,
+        };
+
+        json::object(vec![
+            ("contents", json::object(vec![
+                ("kind", Value::from("markdown")),
+                ("value", Value::from(contents)),
+            ])),
+            ("range", range(text, span)),
+        ])
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        let (symbol, _, _) = match self.symbol_at(params) {
+            Some(symbol) => symbol,
+            None => return Value::Null,
+        };
+        let uri = match (self.prototypes.get(&symbol), self.files.get(&symbol)) {
+            (Some(&ssa::Prototype::Script { .. }), Some(uri)) => uri,
+            _ => return Value::Null,
+        };
+
+        let zero = Value::from(0usize);
+        let start = json::object(vec![("line", zero.clone()), ("character", zero)]);
+        json::object(vec![
+            ("uri", Value::from(uri.as_str())),
+            ("range", json::object(vec![("start", start.clone()), ("end", start)])),
+        ])
+    }
+
+    fn completion(&self) -> Value {
+        let mut items: Vec<_> = self.prototypes.iter()
+            .map(|(&name, prototype)| {
+                // These are the `CompletionItemKind`s of the protocol.
+                let kind = match *prototype {
+                    ssa::Prototype::Script { .. } | ssa::Prototype::Native { .. } => 3,
+                    ssa::Prototype::Member => 6,
+                    ssa::Prototype::Constant(_) => 21,
+                    ssa::Prototype::Enum(_) => 13,
+                };
+                (name.to_string(), kind as usize)
+            })
+            .collect();
+        items.sort();
+
+        Value::Array(items.into_iter()
+            .map(|(label, kind)| json::object(vec![
+                ("label", Value::from(label)),
+                ("kind", Value::from(kind)),
+            ]))
+            .collect())
+    }
+
+    /// Find the identifier at a `TextDocumentPositionParams`.
+    fn symbol_at(&self, params: &Value) -> Option<(Symbol, Span, &[u8])> {
+        let uri = params.get("textDocument").get("uri").as_str()?;
+        let text = self.documents.get(uri)?;
+        let position = params.get("position");
+        let line = position.get("line").as_f64()? as usize;
+        let character = position.get("character").as_f64()? as usize;
+        let offset = offset(text, line, character)?;
+
+        let tokens = SyntaxTokens::lex(text, self.game.version);
+        let token = tokens.token_at(offset)?;
+        match token.token {
+            Token::Ident(symbol) => Some((symbol, token.span, text)),
+            _ => None,
+        }
+    }
+}
+
+/// A `window/logMessage` notification with a diagnostic that has no document.
+fn log(diagnostic: &Diagnostic) -> Value {
+    let kind: usize = match diagnostic.severity {
+        Severity::Error => 1,
+        Severity::Warning => 2,
+    };
+    let mut message = Vec::new();
+    let _ = Renderer::default().render(diagnostic, &mut message);
+    let message = String::from_utf8_lossy(&message);
+
+    json::object(vec![
+        ("jsonrpc", Value::from("2.0")),
+        ("method", Value::from("window/logMessage")),
+        ("params", json::object(vec![
+            ("type", Value::from(kind)),
+            ("message", Value::from(message.trim_end())),
+        ])),
+    ])
+}
+
+fn publish(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json::object(vec![
+        ("jsonrpc", Value::from("2.0")),
+        ("method", Value::from("textDocument/publishDiagnostics")),
+        ("params", json::object(vec![
+            ("uri", Value::from(uri)),
+            ("diagnostics", Value::Array(diagnostics)),
+        ])),
+    ])
+}
+
+/// The name of the script a document holds: its file name, without the extension.
+///
+/// Clients percent-encode `file://` URIs, so the name is decoded.
+fn script_name(uri: &str) -> Vec<u8> {
+    let name = uri.rsplit(|c| c == '/' || c == '\\').next().unwrap_or(uri);
+    let name = name.split('.').next().unwrap_or(name);
+    percent_decode(name.as_bytes())
+}
+
+/// Decode `%XX` escapes, leaving malformed ones as they are.
+fn percent_decode(text: &[u8]) -> Vec<u8> {
+    let hex = |c: u8| (c as char).to_digit(16).map(|digit| digit as u8);
+    let mut decoded = Vec::with_capacity(text.len());
+    let mut i = 0;
+    while i < text.len() {
+        let escape = match text.get(i + 1..i + 3) {
+            Some(&[high, low]) if text[i] == b'%' => hex(high).and_then(|high| {
+                hex(low).map(|low| high << 4 | low)
+            }),
+            _ => None,
+        };
+        match escape {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(text[i]);
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
+fn range(text: &[u8], span: Span) -> Value {
+    json::object(vec![("start", position(text, span.low)), ("end", position(text, span.high))])
+}
+
+/// Convert a byte offset to a protocol position, which counts UTF-16 code units.
+fn position(text: &[u8], offset: usize) -> Value {
+    let offset = usize::min(offset, text.len());
+    let start = text[..offset].iter().rposition(|&c| c == b'\n').map_or(0, |i| i + 1);
+    let line = text[..start].iter().filter(|&&c| c == b'\n').count();
+    let character = match str::from_utf8(&text[start..offset]) {
+        Ok(prefix) => prefix.encode_utf16().count(),
+        Err(_) => offset - start,
+    };
+    json::object(vec![("line", Value::from(line)), ("character", Value::from(character))])
+}
+
+/// Convert a protocol position to a byte offset.
+fn offset(text: &[u8], line: usize, character: usize) -> Option<usize> {
+    let start = match line {
+        0 => 0,
+        _ => text.iter().enumerate().filter(|&(_, &c)| c == b'\n').nth(line - 1)?.0 + 1,
+    };
+    let end = text[start..].iter().position(|&c| c == b'\n').map_or(text.len(), |i| start + i);
+
+    let line = match str::from_utf8(&text[start..end]) {
+        Ok(line) => line,
+        Err(_) => return Some(usize::min(start + character, end)),
+    };
+    let mut units = 0;
+    for (i, c) in line.char_indices() {
+        if units >= character {
+            return Some(start + i);
+        }
+        units += c.len_utf16();
+    }
+    Some(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use gml::symbol::Symbol;
+    use project::owned::{Action, Event, Game, Object, Script};
+    use crate::json::{self, Value};
+    use super::Server;
+
+    fn server() -> Server {
+        let mut game = Game::default();
+        game.version = project::Version::GameMakerStudio;
+        game.scripts.push(Script {
+            name: b"scr_move".to_vec(),
+            body: b"return argument0 + 1".to_vec(),
+        });
+        game.scripts.push(Script { name: b"scr_init".to_vec(), body: b"".to_vec() });
+
+        let mut files = HashMap::new();
+        files.insert(Symbol::intern(b"scr_move"), "file:///p/scripts/scr_move.gml".to_string());
+        files.insert(Symbol::intern(b"scr_init"), "file:///p/scripts/scr_init.gml".to_string());
+        Server::new(game, files)
+    }
+
+    fn request(id: usize, method: &str, params: Value) -> Value {
+        json::object(vec![
+            ("jsonrpc", Value::from("2.0")),
+            ("id", Value::from(id)),
+            ("method", Value::from(method)),
+            ("params", params),
+        ])
+    }
+
+    fn notification(method: &str, params: Value) -> Value {
+        json::object(vec![
+            ("jsonrpc", Value::from("2.0")),
+            ("method", Value::from(method)),
+            ("params", params),
+        ])
+    }
+
+    fn at(line: usize, character: usize) -> Value {
+        json::object(vec![
+            ("textDocument", json::object(vec![
+                ("uri", Value::from("file:///p/scripts/scr_init.gml")),
+            ])),
+            ("position", json::object(vec![
+                ("line", Value::from(line)),
+                ("character", Value::from(character)),
+            ])),
+        ])
+    }
+
+    fn open(text: &str) -> Value {
+        notification("textDocument/didOpen", json::object(vec![
+            ("textDocument", json::object(vec![
+                ("uri", Value::from("file:///p/scripts/scr_init.gml")),
+                ("languageId", Value::from("gml")),
+                ("version", Value::from(1usize)),
+                ("text", Value::from(text)),
+            ])),
+        ]))
+    }
+
+    #[test]
+    fn lifecycle() {
+        let mut server = server();
+
+        let responses = server.handle(&request(1, "initialize", json::object(vec![])));
+        let capabilities = responses[0].get("result").get("capabilities");
+        assert_eq!(capabilities.get("hoverProvider"), &Value::Bool(true));
+        assert!(server.handle(&notification("initialized", json::object(vec![]))).is_empty());
+
+        let responses = server.handle(&request(2, "textDocument/rename", json::object(vec![])));
+        assert_eq!(responses[0].get("error").get("code").as_f64(), Some(-32601.0));
+
+        let responses = server.handle(&request(3, "shutdown", Value::Null));
+        assert_eq!(responses[0].to_string(), r#"{"jsonrpc":"2.0","id":3,"result":null}"#);
+        assert!(server.shutdown);
+        assert!(server.handle(&notification("exit", Value::Null)).is_empty());
+        assert!(server.exit);
+    }
+
+    #[test]
+    fn diagnostics() {
+        let mut server = server();
+
+        let responses = server.handle(&open("y = 0;\nx = 1 +;"));
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].get("method").as_str(), Some("textDocument/publishDiagnostics"));
+        let diagnostics = responses[0].get("params").get("diagnostics").as_array().unwrap();
+        let messages: Vec<_> = diagnostics.iter()
+            .map(|diagnostic| diagnostic.get("message").as_str().unwrap())
+            .collect();
+        assert_eq!(messages, ["unexpected ;; expected expression"]);
+        assert_eq!(diagnostics[0].get("range").to_string(),
+            r#"{"start":{"line":1,"character":7},"end":{"line":1,"character":8}}"#);
+
+        let change = notification("textDocument/didChange", json::object(vec![
+            ("textDocument", json::object(vec![
+                ("uri", Value::from("file:///p/scripts/scr_init.gml")),
+            ])),
+            ("contentChanges", Value::Array(vec![json::object(vec![
+                ("text", Value::from("scr_move(1, 2)")),
+            ])])),
+        ]));
+        let responses = server.handle(&change);
+        let diagnostics = responses[0].get("params").get("diagnostics").as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].get("severity").as_f64(), Some(2.0));
+        assert_eq!(diagnostics[0].get("message").as_str(),
+            Some("too many arguments to script scr_move\nnote: scr_move reads 1 arguments"));
+
+        let close = notification("textDocument/didClose", json::object(vec![
+            ("textDocument", json::object(vec![
+                ("uri", Value::from("file:///p/scripts/scr_init.gml")),
+            ])),
+        ]));
+        let responses = server.handle(&close);
+        assert_eq!(responses[0].get("params").get("diagnostics"), &Value::Array(vec![]));
+    }
+
+    /// Report constant misuse as diagnostics rather than failing the build.
+    #[test]
+    fn constant_errors() {
+        let mut game = Game::default();
+        game.version = project::Version::GameMakerStudio;
+        game.objects.push(Object { name: b"obj".to_vec(), ..Object::default() });
+        game.scripts.push(Script { name: b"scr init".to_vec(), body: b"".to_vec() });
+        let mut server = Server::new(game, HashMap::new());
+
+        let responses = server.handle(&notification("textDocument/didOpen", json::object(vec![
+            ("textDocument", json::object(vec![
+                ("uri", Value::from("file:///p/scripts/scr%20init.gml")),
+                ("languageId", Value::from("gml")),
+                ("version", Value::from(1usize)),
+                ("text", Value::from("#macro BIG 1 << 40\nreturn obj[0]")),
+            ])),
+        ])));
+        let diagnostics = responses[0].get("params").get("diagnostics").as_array().unwrap();
+        let messages: Vec<_> = diagnostics.iter()
+            .map(|diagnostic| diagnostic.get("message").as_str().unwrap())
+            .collect();
+        assert_eq!(messages, ["shift amount out of range", "cannot index a constant"]);
+
+        let responses = server.handle(&request(1, "shutdown", Value::Null));
+        assert!(responses[0].get("result").is_null());
+    }
+
+    /// Log the diagnostics of code without a document.
+    #[test]
+    fn event_diagnostics() {
+        let mut server = server();
+        server.game.objects.push(Object {
+            name: b"obj".to_vec(),
+            events: vec![Event {
+                event_type: 3,
+                event_kind: 0,
+                actions: vec![Action {
+                    library: 1,
+                    action: 603,
+                    action_kind: project::action_kind::CODE,
+                    has_target: true,
+                    action_type: project::action_type::CODE,
+                    parameters_used: 1,
+                    parameters: vec![project::argument_type::STRING],
+                    target: -1,
+                    arguments: vec![b"x = 1 +".to_vec()],
+                    ..Action::default()
+                }],
+            }],
+            ..Object::default()
+        });
+
+        let responses = server.handle(&open("x = 1"));
+        assert_eq!(responses.len(), 2);
+        let diagnostics = responses[0].get("params").get("diagnostics").as_array().unwrap();
+        assert!(diagnostics.is_empty());
+        assert_eq!(responses[1].get("method").as_str(), Some("window/logMessage"));
+        assert_eq!(responses[1].get("params").get("type").as_f64(), Some(1.0));
+        assert_eq!(responses[1].get("params").get("message").as_str(),
+            Some("error in event Step for object obj, action 1:1:8: unexpected end of file; \
+                expected expression"));
+    }
+
+    #[test]
+    fn navigation() {
+        let mut server = server();
+        let text = "enum dir { up, down = 5 }\nx = scr_move(dir.down)\nshow_debug_message(x)";
+        server.handle(&open(text));
+
+        let hover = |server: &mut Server, line, character| {
+            let responses = server.handle(&request(1, "textDocument/hover", at(line, character)));
+            responses[0].get("result").get("contents").get("value").as_str().map(String::from)
+        };
+        assert_eq!(hover(&mut server, 1, 6).as_deref(), Some("script `scr_move`"));
+        assert_eq!(hover(&mut server, 1, 13).as_deref(), Some("enum `dir` { up = 0, down = 5 }"));
+        assert_eq!(hover(&mut server, 2, 0).as_deref(),
+            Some("function `show_debug_message`, taking 0 or more arguments"));
+        assert_eq!(hover(&mut server, 1, 0).as_deref(), Some("built-in variable `x`"));
+        assert_eq!(hover(&mut server, 0, 0).as_deref(), None);
+
+        let responses = server.handle(&request(2, "textDocument/definition", at(1, 4)));
+        assert_eq!(responses[0].get("result").get("uri").as_str(),
+            Some("file:///p/scripts/scr_move.gml"));
+        let responses = server.handle(&request(3, "textDocument/definition", at(2, 0)));
+        assert!(responses[0].get("result").is_null());
+
+        let responses = server.handle(&request(4, "textDocument/completion", at(2, 0)));
+        let items = responses[0].get("result").as_array().unwrap();
+        let labels: Vec<_> = items.iter().map(|item| item.get("label").as_str().unwrap()).collect();
+        assert!(labels.contains(&"scr_move"));
+        assert!(labels.contains(&"instance_create"));
+        assert!(labels.contains(&"dir"));
+    }
+}
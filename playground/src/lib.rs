@@ -5,6 +5,7 @@ use std::{str, io::{self, Write}};
 use wasm_bindgen::prelude::*;
 
 use gml::{Function, ErrorPrinter};
+use gml::diagnostic::{Printer, Renderer};
 use gml::front::Span;
 use engine::World;
 
@@ -20,9 +21,18 @@ pub fn run(source: &str) {
     let script = Function::Script { id: game.scripts.len() as i32 };
     game.scripts.push(project::Script { name: b"script", body: source.as_bytes() });
 
-    let (mut assets, debug) = match engine::build(&game, &items, HostErr) {
-        Ok(assets) => assets,
-        Err(errors) => {
+    let renderer = Renderer { snippets: true };
+    let build = engine::build(&game, &items);
+    let diagnostics = match build {
+        Ok((_, _, ref diagnostics)) | Err(ref diagnostics) => diagnostics,
+    };
+    for diagnostic in diagnostics {
+        let _ = renderer.render(diagnostic, &mut HostErr());
+    }
+    let (mut assets, debug) = match build {
+        Ok((assets, debug, _)) => (assets, debug),
+        Err(diagnostics) => {
+            let errors = diagnostics.iter().filter(|diagnostic| diagnostic.is_error()).count();
             if errors > 1 {
                 let _ = write!(HostErr(), "aborting due to {} previous errors", errors);
             } else {
@@ -32,7 +42,7 @@ pub fn run(source: &str) {
         }
     };
 
-    let mut world = World::default();
+    let mut world = World::new(&game.objects);
     world.show.set_write(Box::new(HostOut));
     let id = world.instance.instance_create(&mut world.world, &mut world.motion, 0.0, 0.0, 0)
         .unwrap_or_else(|_| { let _ = writeln!(HostErr(), "object does not exist"); panic!() });
@@ -40,7 +50,8 @@ pub fn run(source: &str) {
     let mut thread = gml::vm::Thread::default();
     thread.set_self(world.world.instances[id]);
     if let Err(error) = thread.execute(&mut world, &mut assets, script, vec![]) {
-        let mut errors = ErrorPrinter::from_debug(&debug, error.function, HostErr());
+        let printer = Printer { renderer, write: HostErr() };
+        let mut errors = ErrorPrinter::from_debug(&debug, error.function, printer);
         let offset = error.instruction as u32;
         let location = debug.locations[&error.function].locations.get_location(offset);
         let span = Span { low: location as usize, high: location as usize };
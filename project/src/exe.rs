@@ -0,0 +1,536 @@
+//! Compiled Game Maker executables.
+//!
+//! A Game Maker executable is the runner followed by an embedded copy of the game data. GM 8.0
+//! stores the data at a fixed offset. GM 8.1 hides its header near a different fixed offset and
+//! xors the rest of the file with a pair of seeded generators. Both versions then encrypt the
+//! resources with a byte substitution table and a chained swap, and store them in the same block
+//! encoding as project files.
+//!
+//! Earlier runners (GM 5.3 through 7.0) store their header at other fixed offsets, followed by a
+//! seed. Everything after the seed is encrypted with a substitution table generated from it, and
+//! the resources are stored unencrypted within that. Extensions were added in GM 7.0, so earlier
+//! versions have no extensions section.
+
+use std::convert::TryInto;
+
+use crate::{Game, Error};
+use crate::gmk::{self, Blocks};
+use crate::resource::{self, Format};
+use crate::stream::{Reader, Writer};
+
+const MAGIC: u32 = 1234321;
+
+/// The offset of the GM 8.0 game data header.
+const GM80_HEADER: usize = 2_000_000;
+
+/// The offsets of the game data headers of earlier runners.
+const GM70_HEADER: usize = 1_980_000;
+const GM6_HEADER: usize = 700_000;
+const GM5_HEADER: usize = 1_500_000;
+
+/// The runners that store their header at a fixed offset, and the versions that follow it.
+const FIXED_HEADERS: &[(usize, &[u32])] = &[
+    (GM80_HEADER, &[800]),
+    (GM70_HEADER, &[700, 701, 702]),
+    (GM6_HEADER, &[600, 610]),
+    (GM5_HEADER, &[500, 510, 520, 530]),
+];
+
+/// The offset where the search for the GM 8.1 game data header begins.
+const GM81_HEADER: usize = 3_800_004;
+const GM81_SEARCH: usize = 1024;
+
+/// The GM 8.1 header is a pair of words whose interleaved bytes match this mask.
+const GM81_MASK: u32 = 0xF714_0067;
+
+/// Locate, decrypt, and inflate the game data embedded in an executable.
+pub fn read(data: &[u8]) -> Result<Blocks, Error> {
+    let mut data = data.to_vec();
+    let (version, start) = locate(&mut data)?;
+    let start = if version < 800 {
+        decrypt_seeded(data.get_mut(start..).ok_or(Error::UnexpectedEof)?)?;
+        start + 4
+    } else {
+        start
+    };
+
+    let mut reader = Reader::new(&data[start..]);
+
+    let settings = reader.read_block()?;
+
+    let data = if version < 800 {
+        let start = start + reader.position();
+        &data[start..]
+    } else {
+        // The Direct3D helper library that the runner extracts at startup.
+        let _name = reader.read_string()?;
+        let _library = reader.read_string()?;
+
+        let start = start + reader.position();
+        decrypt(&mut data[start..])?
+    };
+    let mut reader = Reader::new(data);
+
+    let garbage = reader.read_u32()?;
+    reader.skip_words(garbage)?;
+    let _pro = reader.read_bool()?;
+    let _game_id = reader.read_u32()?;
+    let _guid = reader.read_bytes(16)?;
+
+    if version >= 700 {
+        skip_extensions(&mut reader)?;
+    }
+
+    Ok(Blocks {
+        version,
+        format: Format::Exe,
+        settings,
+        triggers: gmk::read_resources(&mut reader)?,
+        constants: gmk::read_constants(&mut reader)?,
+        sounds: gmk::read_resources(&mut reader)?,
+        sprites: gmk::read_resources(&mut reader)?,
+        backgrounds: gmk::read_resources(&mut reader)?,
+        paths: gmk::read_resources(&mut reader)?,
+        scripts: gmk::read_resources(&mut reader)?,
+        fonts: gmk::read_resources(&mut reader)?,
+        timelines: gmk::read_resources(&mut reader)?,
+        objects: gmk::read_resources(&mut reader)?,
+        rooms: gmk::read_resources(&mut reader)?,
+    })
+}
+
+/// Find the game data header, returning the format version and the offset of the data after it.
+///
+/// GM 8.1 data is decrypted in place.
+fn locate(data: &mut [u8]) -> Result<(u32, usize), Error> {
+    for &(header, versions) in FIXED_HEADERS {
+        if read_u32_at(data, header) != Some(MAGIC) {
+            continue;
+        }
+        return match read_u32_at(data, header + 4) {
+            Some(version) if versions.contains(&version) => Ok((version, header + 8)),
+            Some(version) => Err(Error::Version(version)),
+            None => Err(Error::UnexpectedEof),
+        };
+    }
+
+    for header in GM81_HEADER..GM81_HEADER + GM81_SEARCH {
+        let (a, b) = match (read_u32_at(data, header), read_u32_at(data, header + 4)) {
+            (Some(a), Some(b)) => (a, b),
+            _ => break,
+        };
+        let mask = (a & 0xFF00_FF00) | (b & 0x00FF_00FF);
+        if mask != GM81_MASK {
+            continue;
+        }
+
+        let start = header + 8;
+        xor(data.get_mut(start..).ok_or(Error::UnexpectedEof)?)?;
+        return match read_u32_at(data, start + 8) {
+            Some(810) => Ok((810, start + 12)),
+            Some(version) => Err(Error::Version(version)),
+            None => Err(Error::UnexpectedEof),
+        };
+    }
+
+    Err(Error::Magic)
+}
+
+fn read_u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+/// Apply the GM 8.1 xor layer, which is its own inverse.
+///
+/// The data starts with two seeds, which are left as-is. Every following word is xored with the
+/// combined output of two multiply-with-carry generators.
+fn xor(data: &mut [u8]) -> Result<(), Error> {
+    let mut seed1 = read_u32_at(data, 0).ok_or(Error::UnexpectedEof)?;
+    let mut seed2 = read_u32_at(data, 4).ok_or(Error::UnexpectedEof)?;
+    for chunk in data[8..].chunks_exact_mut(4) {
+        seed1 = (seed1 & 0xFFFF).wrapping_mul(0x9069).wrapping_add(seed1 >> 16);
+        seed2 = (seed2 & 0xFFFF).wrapping_mul(0x4650).wrapping_add(seed2 >> 16);
+        let mask = (seed1 << 16).wrapping_add(seed2 & 0xFFFF);
+
+        let word = u32::from_le_bytes((&*chunk).try_into().unwrap()) ^ mask;
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    Ok(())
+}
+
+/// Apply the GM 5.3 through 7.0 encryption layer in place.
+///
+/// The data starts with a seed, which is left as-is. Every following byte was substituted through
+/// the table that `seeded_table` generates from it.
+fn decrypt_seeded(data: &mut [u8]) -> Result<(), Error> {
+    let seed = read_u32_at(data, 0).ok_or(Error::UnexpectedEof)?;
+    let table = seeded_table(seed);
+
+    let mut reverse = [0; 256];
+    for (i, &byte) in table.iter().enumerate() {
+        reverse[byte as usize] = i as u8;
+    }
+
+    for byte in &mut data[4..] {
+        *byte = reverse[*byte as usize];
+    }
+    Ok(())
+}
+
+/// Generate the GM 5.3 through 7.0 substitution table by swapping adjacent entries of the identity
+/// permutation, at positions derived from `seed`.
+fn seeded_table(seed: u32) -> [u8; 256] {
+    let mut table = [0; 256];
+    for (i, byte) in table.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+
+    let a = 6 + seed % 250;
+    let b = seed / 250;
+    for i in 1..10001u32 {
+        let j = 1 + (i.wrapping_mul(a).wrapping_add(b) % 254) as usize;
+        table.swap(j, j + 1);
+    }
+    table
+}
+
+/// Decrypt the substitution-encrypted resource section in place and return it.
+///
+/// The section header is surrounded by garbage words, and holds the substitution table and the
+/// section length. Each byte was substituted after being offset by the previous encrypted byte and
+/// its own position, and the data was shuffled by swapping each byte with an earlier one.
+fn decrypt(data: &mut [u8]) -> Result<&[u8], Error> {
+    let mut reader = Reader::new(data);
+    let garbage1 = reader.read_u32()?;
+    let garbage2 = reader.read_u32()?;
+    reader.skip_words(garbage1)?;
+    let table: [u8; 256] = reader.read_bytes(256)?.try_into().unwrap();
+    reader.skip_words(garbage2)?;
+    let len = reader.read_u32()? as usize;
+    let start = reader.position();
+
+    let data = data.get_mut(start..start + len).ok_or(Error::UnexpectedEof)?;
+
+    let mut reverse = [0; 256];
+    for (i, &byte) in table.iter().enumerate() {
+        reverse[byte as usize] = i as u8;
+    }
+
+    for i in (0..len).rev() {
+        let previous = if i > 0 { data[i - 1] } else { 0 };
+        data[i] = reverse[data[i] as usize].wrapping_sub(previous).wrapping_sub(i as u8);
+    }
+    for i in (0..len).rev() {
+        data.swap(i, i.saturating_sub(table[i & 0xFF] as usize));
+    }
+
+    Ok(data)
+}
+
+/// The inverse of `decrypt`.
+fn encrypt(data: &mut [u8], table: &[u8; 256]) {
+    let len = data.len();
+    for i in 0..len {
+        data.swap(i, i.saturating_sub(table[i & 0xFF] as usize));
+    }
+    for i in 0..len {
+        let previous = if i > 0 { data[i - 1] } else { 0 };
+        data[i] = table[data[i].wrapping_add(previous).wrapping_add(i as u8) as usize];
+    }
+}
+
+fn skip_extensions(reader: &mut Reader<'_>) -> Result<(), Error> {
+    reader.read_version(&[700])?;
+    let count = reader.read_u32()?;
+    for _ in 0..count {
+        reader.read_version(&[700])?;
+        let _name = reader.read_string()?;
+        let _folder = reader.read_string()?;
+
+        let files = reader.read_u32()?;
+        for _ in 0..files {
+            reader.read_version(&[700])?;
+            let _name = reader.read_string()?;
+            let _kind = reader.read_u32()?;
+            let _initialize = reader.read_string()?;
+            let _finalize = reader.read_string()?;
+
+            let functions = reader.read_u32()?;
+            for _ in 0..functions {
+                reader.read_version(&[700])?;
+                let _name = reader.read_string()?;
+                let _external_name = reader.read_string()?;
+                let _convention = reader.read_u32()?;
+                let _id = reader.read_u32()?;
+                let _arity = reader.read_u32()?;
+                reader.skip_words(17)?;
+                let _returns = reader.read_u32()?;
+            }
+
+            let constants = reader.read_u32()?;
+            for _ in 0..constants {
+                reader.read_version(&[700])?;
+                let _name = reader.read_string()?;
+                let _value = reader.read_string()?;
+            }
+        }
+
+        let _contents = reader.read_string()?;
+    }
+    Ok(())
+}
+
+/// Build a minimal executable around `game`, in the format of GM 8.1 (`810`) or of any version
+/// with a fixed header offset (`530` through `800`).
+///
+/// The runner itself is left blank, so the result is only useful as input to `read`.
+pub fn write(game: &Game<'_>, version: u32) -> Vec<u8> {
+    let header = FIXED_HEADERS.iter()
+        .find(|&&(_, versions)| versions.contains(&version))
+        .map(|&(header, _)| header);
+    assert!(header.is_some() || version == 810, "unsupported version {}", version);
+
+    let mut body = Writer::default();
+    body.write_u32(3);
+    body.write_bytes(&[0xCC; 12]);
+    body.write_bool(false);
+    body.write_u32(0);
+    body.write_bytes(&[0; 16]);
+
+    if version >= 700 {
+        // Extensions.
+        body.write_u32(700);
+        body.write_u32(0);
+    }
+
+    gmk::write_sections(&mut body, game, Format::Exe);
+
+    let mut body = body.into_inner();
+
+    let mut settings = Writer::default();
+    resource::write_settings(&mut settings, &game.settings, Format::Exe);
+
+    let mut data = Writer::default();
+    data.write_block(&settings.into_inner());
+    if version < 800 {
+        data.write_bytes(&body);
+    } else {
+        let table = shuffle(0x5EED);
+        encrypt(&mut body, &table);
+
+        data.write_string(b"D3DX8.dll");
+        data.write_string(&[]);
+        data.write_u32(2);
+        data.write_u32(1);
+        data.write_bytes(&[0xCC; 8]);
+        data.write_bytes(&table);
+        data.write_bytes(&[0xCC; 4]);
+        data.write_string(&body);
+    }
+    let mut data = data.into_inner();
+
+    let mut exe = Vec::default();
+    exe.extend_from_slice(b"MZ");
+    if let Some(header) = header {
+        exe.resize(header, 0);
+        exe.extend_from_slice(&MAGIC.to_le_bytes());
+        exe.extend_from_slice(&version.to_le_bytes());
+        if version < 800 {
+            let seed = 0x5EED;
+            let table = seeded_table(seed);
+            for byte in &mut data {
+                *byte = table[*byte as usize];
+            }
+            exe.extend_from_slice(&seed.to_le_bytes());
+        }
+        exe.extend_from_slice(&data);
+    } else {
+        exe.resize(GM81_HEADER + 16, 0);
+        exe.extend_from_slice(&(GM81_MASK & 0xFF00_FF00 | 0x0012_0034).to_le_bytes());
+        exe.extend_from_slice(&(GM81_MASK & 0x00FF_00FF | 0x5600_7800).to_le_bytes());
+
+        let start = exe.len();
+        exe.extend_from_slice(&0x1234_5678u32.to_le_bytes());
+        exe.extend_from_slice(&0x9ABC_DEF0u32.to_le_bytes());
+        exe.extend_from_slice(&810u32.to_le_bytes());
+        exe.extend_from_slice(&data);
+        xor(&mut exe[start..]).unwrap();
+    }
+    exe
+}
+
+/// Generate a substitution table from a seed.
+fn shuffle(mut seed: u32) -> [u8; 256] {
+    let mut table = [0; 256];
+    for (i, byte) in table.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    for i in (1..256).rev() {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        table.swap(i, (seed >> 16) as usize % (i + 1));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Constant, Sprite, Image, Mask, Script, Font, Object, Event, Action, Room, Instance,
+        action_kind, action_type,
+    };
+    use super::*;
+
+    fn game() -> Game<'static> {
+        let mut game = Game::default();
+        game.settings.uninitialized_as_zero = true;
+        game.constants.push(Constant { name: b"SPEED", value: b"4" });
+        game.sprites.push(Sprite {
+            name: b"spr_player",
+            frames: vec![Image { width: 2, height: 1, data: &[0xFF; 8] }],
+            masks: vec![Mask {
+                width: 2,
+                height: 1,
+                right: 1,
+                data: vec![true, false],
+                ..Mask::default()
+            }],
+            ..Sprite::default()
+        });
+        game.fonts.push(Font { name: b"fnt_main", system_name: b"Arial", ..Font::default() });
+        game.scripts.push(Script { name: b"scr_move", body: b"x += argument0" });
+        game.objects.push(Object {
+            name: b"obj_player",
+            events: vec![Event {
+                event_type: 3,
+                event_kind: 0,
+                actions: vec![Action {
+                    library: 1,
+                    action: 603,
+                    action_kind: action_kind::CODE,
+                    has_target: true,
+                    action_type: action_type::CODE,
+                    parameters_used: 1,
+                    parameters: vec![1, 0, 0, 0, 0, 0, 0, 0],
+                    target: -1,
+                    arguments: vec![b"scr_move(4)", b"", b"", b"", b"", b"", b"", b""],
+                    ..Action::default()
+                }],
+            }],
+            ..Object::default()
+        });
+        game.rooms.push(Room {
+            name: b"rm_start",
+            instances: vec![Instance { object: 0, id: 100_001, ..Instance::default() }],
+            ..Room::default()
+        });
+        game
+    }
+
+    fn check(blocks: &Blocks) {
+        let game = blocks.game().unwrap();
+        assert!(game.settings.uninitialized_as_zero);
+        assert_eq!(game.constants, [Constant { name: b"SPEED", value: b"4" }]);
+        assert_eq!(game.sprites[0].masks[0].data, [true, false]);
+        assert_eq!(game.fonts[0].system_name, b"Arial");
+        assert_eq!(game.rooms[0].instances[0].id, 100_001);
+
+        assert_eq!(game.scripts.len(), 1);
+        assert_eq!(game.scripts[0].name, b"scr_move");
+        assert_eq!(game.scripts[0].body, b"x += argument0");
+
+        assert_eq!(game.objects.len(), 1);
+        let object = &game.objects[0];
+        assert_eq!(object.name, b"obj_player");
+        assert_eq!(object.events.len(), 1);
+        assert_eq!((object.events[0].event_type, object.events[0].event_kind), (3, 0));
+
+        let action = &object.events[0].actions[0];
+        assert_eq!(action.action_kind, action_kind::CODE);
+        assert_eq!(action.arguments[0], b"scr_move(4)");
+        assert_eq!(action.parameters, [1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(action.target, -1);
+    }
+
+    #[test]
+    fn gm80() {
+        let exe = write(&game(), 800);
+        let blocks = read(&exe).unwrap();
+        assert_eq!(blocks.version, 800);
+        check(&blocks);
+    }
+
+    #[test]
+    fn gm81() {
+        let exe = write(&game(), 810);
+        let blocks = read(&exe).unwrap();
+        assert_eq!(blocks.version, 810);
+        check(&blocks);
+    }
+
+    /// An executable with only a fixed game data header.
+    fn fixed_header(header: usize, version: u32) -> Vec<u8> {
+        let mut exe = Vec::default();
+        exe.extend_from_slice(b"MZ");
+        exe.resize(header, 0);
+        exe.extend_from_slice(&MAGIC.to_le_bytes());
+        exe.extend_from_slice(&version.to_le_bytes());
+        exe
+    }
+
+    /// Check that an executable from an earlier runner is located and decrypted.
+    fn check_seeded(version: u32, header: usize) {
+        let mut exe = write(&game(), version);
+        assert_eq!(locate(&mut exe).unwrap(), (version, header + 8));
+
+        let blocks = read(&exe).unwrap();
+        assert_eq!(blocks.version, version);
+        check(&blocks);
+    }
+
+    #[test]
+    fn gm70() {
+        check_seeded(700, GM70_HEADER);
+    }
+
+    #[test]
+    fn gm6() {
+        check_seeded(600, GM6_HEADER);
+        check_seeded(610, GM6_HEADER);
+    }
+
+    #[test]
+    fn gm5() {
+        check_seeded(530, GM5_HEADER);
+    }
+
+    /// A header at a known offset with an unknown version is rejected rather than skipped.
+    #[test]
+    fn unknown_version() {
+        let mut exe = fixed_header(GM6_HEADER, 800);
+        assert!(matches!(locate(&mut exe), Err(Error::Version(800))));
+    }
+
+    #[test]
+    fn encryption() {
+        let table = shuffle(42);
+        let plain: Vec<u8> = (0..1000).map(|i| (i * 7 % 251) as u8).collect();
+
+        let mut data = Writer::default();
+        data.write_u32(0);
+        data.write_u32(0);
+        data.write_bytes(&table);
+        let mut cipher = plain.clone();
+        encrypt(&mut cipher, &table);
+        assert_ne!(cipher, plain);
+        data.write_string(&cipher);
+
+        let mut data = data.into_inner();
+        assert_eq!(decrypt(&mut data).unwrap(), &plain[..]);
+    }
+
+    #[test]
+    fn not_an_executable() {
+        assert!(matches!(read(b"MZ"), Err(Error::Magic)));
+    }
+}
@@ -0,0 +1,623 @@
+//! Game Maker 8 project files (.gmk and .gm81).
+//!
+//! A project file is a stream of resource sections. Each section holds a version number and a
+//! list of individually zlib-compressed resource blocks, with a flag at the start of each block
+//! that marks deleted resources. Deleted resources still occupy an index.
+
+use crate::{Game, Version, Constant, Error};
+use crate::resource::{self, Format};
+use crate::stream::{Reader, Writer};
+
+const MAGIC: u32 = 1234321;
+
+/// The inflated resource blocks of a project file.
+///
+/// `Game` borrows its strings, so the blocks are inflated up front and parsed by `game`.
+pub struct Blocks {
+    pub version: u32,
+    pub(crate) format: Format,
+    pub(crate) settings: Vec<u8>,
+    pub(crate) triggers: Vec<Vec<u8>>,
+    pub(crate) constants: Constants,
+    pub(crate) sounds: Vec<Vec<u8>>,
+    pub(crate) sprites: Vec<Vec<u8>>,
+    pub(crate) backgrounds: Vec<Vec<u8>>,
+    pub(crate) paths: Vec<Vec<u8>>,
+    pub(crate) scripts: Vec<Vec<u8>>,
+    pub(crate) fonts: Vec<Vec<u8>>,
+    pub(crate) timelines: Vec<Vec<u8>>,
+    pub(crate) objects: Vec<Vec<u8>>,
+    pub(crate) rooms: Vec<Vec<u8>>,
+}
+
+/// Constant names and values, which are stored uncompressed.
+pub(crate) type Constants = Vec<(Vec<u8>, Vec<u8>)>;
+
+impl Blocks {
+    pub fn read(data: &[u8]) -> Result<Blocks, Error> {
+        let mut reader = Reader::new(data);
+
+        if reader.read_u32()? != MAGIC {
+            return Err(Error::Magic);
+        }
+        let version = reader.read_version(&[800, 810])?;
+        let _game_id = reader.read_u32()?;
+        let _guid = reader.read_bytes(16)?;
+
+        reader.read_version(&[800, 810])?;
+        let settings = reader.read_block()?;
+
+        let triggers = read_resources(&mut reader)?;
+        let _changed = reader.read_f64()?;
+
+        let constants = read_constants(&mut reader)?;
+        let _changed = reader.read_f64()?;
+
+        Ok(Blocks {
+            version,
+            format: Format::Gmk,
+            settings,
+            triggers,
+            constants,
+            sounds: read_resources(&mut reader)?,
+            sprites: read_resources(&mut reader)?,
+            backgrounds: read_resources(&mut reader)?,
+            paths: read_resources(&mut reader)?,
+            scripts: read_resources(&mut reader)?,
+            fonts: read_resources(&mut reader)?,
+            timelines: read_resources(&mut reader)?,
+            objects: read_resources(&mut reader)?,
+            rooms: read_resources(&mut reader)?,
+        })
+    }
+
+    pub fn game(&self) -> Result<Game<'_>, Error> {
+        let format = self.format;
+        let settings = resource::read_settings(&mut Reader::new(&self.settings), format)?;
+        let constants = self.constants.iter()
+            .map(|(name, value)| Constant { name, value })
+            .collect();
+
+        Ok(Game {
+            version: Version::GameMaker8,
+            settings,
+            triggers: resource::read_blocks(&self.triggers, format, resource::read_trigger)?,
+            constants,
+            sounds: resource::read_blocks(&self.sounds, format, resource::read_sound)?,
+            sprites: resource::read_blocks(&self.sprites, format, resource::read_sprite)?,
+            backgrounds: resource::read_blocks(
+                &self.backgrounds, format, resource::read_background
+            )?,
+            paths: resource::read_blocks(&self.paths, format, resource::read_path)?,
+            scripts: resource::read_blocks(&self.scripts, format, resource::read_script)?,
+            fonts: resource::read_blocks(&self.fonts, format, resource::read_font)?,
+            timelines: resource::read_blocks(&self.timelines, format, resource::read_timeline)?,
+            objects: resource::read_blocks(&self.objects, format, resource::read_object)?,
+            rooms: resource::read_blocks(&self.rooms, format, resource::read_room)?,
+        })
+    }
+}
+
+pub(crate) fn read_resources(reader: &mut Reader<'_>) -> Result<Vec<Vec<u8>>, Error> {
+    reader.read_version(&[800, 810])?;
+    let count = reader.read_u32()?;
+    (0..count).map(|_| reader.read_block()).collect()
+}
+
+/// Read the constants section.
+pub(crate) fn read_constants(reader: &mut Reader<'_>) -> Result<Constants, Error> {
+    reader.read_version(&[800])?;
+    let count = reader.read_u32()?;
+    (0..count)
+        .map(|_| {
+            let name = reader.read_string()?.to_vec();
+            let value = reader.read_string()?.to_vec();
+            Ok((name, value))
+        })
+        .collect()
+}
+
+/// Write a project file that Game Maker 8 can open.
+///
+/// Resources with empty names are written as deleted. Editor state that is not present in `Game`
+/// is written with Game Maker's defaults.
+pub fn write(game: &Game<'_>) -> Vec<u8> {
+    let mut writer = Writer::default();
+    writer.write_u32(MAGIC);
+    writer.write_u32(800);
+    writer.write_u32(0);
+    writer.write_bytes(&[0; 16]);
+
+    let mut settings = Writer::default();
+    resource::write_settings(&mut settings, &game.settings, Format::Gmk);
+    writer.write_u32(800);
+    writer.write_block(&settings.into_inner());
+
+    write_sections(&mut writer, game, Format::Gmk);
+
+    // The last instance and tile ids.
+    let instances = game.rooms.iter().flat_map(|room| room.instances.iter().map(|i| i.id));
+    let tiles = game.rooms.iter().flat_map(|room| room.tiles.iter().map(|tile| tile.id));
+    writer.write_i32(instances.fold(100_000, |last, id| i32::max(last, id + 1)));
+    writer.write_i32(tiles.fold(10_000_000, |last, id| i32::max(last, id + 1)));
+
+    // Included files and extension packages.
+    writer.write_u32(800);
+    writer.write_u32(0);
+    writer.write_u32(700);
+    writer.write_u32(0);
+
+    writer.write_u32(800);
+    writer.write_block(&write_information());
+
+    // Library creation code.
+    writer.write_u32(500);
+    writer.write_u32(0);
+
+    writer.write_u32(700);
+    let rooms = game.rooms.iter()
+        .enumerate()
+        .filter(|(_, room)| !room.name.is_empty())
+        .map(|(index, _)| index as u32);
+    writer.write_u32(rooms.clone().count() as u32);
+    for index in rooms {
+        writer.write_u32(index);
+    }
+
+    // The resource tree.
+    for &(kind, name) in TREE.iter() {
+        let children: Vec<&[u8]> = match kind {
+            tree::SPRITE => game.sprites.iter().map(|sprite| sprite.name).collect(),
+            tree::SOUND => game.sounds.iter().map(|sound| sound.name).collect(),
+            tree::BACKGROUND => game.backgrounds.iter().map(|back| back.name).collect(),
+            tree::PATH => game.paths.iter().map(|path| path.name).collect(),
+            tree::SCRIPT => game.scripts.iter().map(|script| script.name).collect(),
+            tree::FONT => game.fonts.iter().map(|font| font.name).collect(),
+            tree::TIMELINE => game.timelines.iter().map(|timeline| timeline.name).collect(),
+            tree::OBJECT => game.objects.iter().map(|object| object.name).collect(),
+            tree::ROOM => game.rooms.iter().map(|room| room.name).collect(),
+            _ => Vec::default(),
+        };
+
+        writer.write_u32(tree::PRIMARY);
+        writer.write_u32(kind);
+        writer.write_u32(0);
+        writer.write_string(name);
+        writer.write_u32(children.iter().filter(|name| !name.is_empty()).count() as u32);
+        for (index, &name) in children.iter().enumerate() {
+            if name.is_empty() {
+                continue;
+            }
+            writer.write_u32(tree::SECONDARY);
+            writer.write_u32(kind);
+            writer.write_u32(index as u32);
+            writer.write_string(name);
+            writer.write_u32(0);
+        }
+    }
+
+    writer.into_inner()
+}
+
+/// Write the triggers, constants, and resource lists, which are shared with executables.
+///
+/// Project files follow the trigger and constant sections with a timestamp.
+pub(crate) fn write_sections(writer: &mut Writer, game: &Game<'_>, format: Format) {
+    writer.write_u32(800);
+    writer.write_u32(game.triggers.len() as u32);
+    for trigger in &game.triggers {
+        resource::write_block(writer, trigger, trigger.name, format, resource::write_trigger);
+    }
+    if format == Format::Gmk {
+        writer.write_f64(0.0);
+    }
+
+    writer.write_u32(800);
+    writer.write_u32(game.constants.len() as u32);
+    for constant in &game.constants {
+        writer.write_string(constant.name);
+        writer.write_string(constant.value);
+    }
+    if format == Format::Gmk {
+        writer.write_f64(0.0);
+    }
+
+    macro_rules! write_resources {
+        ($resources:expr, $write:path) => {
+            writer.write_u32(800);
+            writer.write_u32($resources.len() as u32);
+            for resource in &$resources {
+                resource::write_block(writer, resource, resource.name, format, $write);
+            }
+        };
+    }
+
+    write_resources!(game.sounds, resource::write_sound);
+    write_resources!(game.sprites, resource::write_sprite);
+    write_resources!(game.backgrounds, resource::write_background);
+    write_resources!(game.paths, resource::write_path);
+    write_resources!(game.scripts, resource::write_script);
+    write_resources!(game.fonts, resource::write_font);
+    write_resources!(game.timelines, resource::write_timeline);
+    write_resources!(game.objects, resource::write_object);
+    write_resources!(game.rooms, resource::write_room);
+}
+
+mod tree {
+    pub const PRIMARY: u32 = 1;
+    pub const SECONDARY: u32 = 3;
+
+    pub const OBJECT: u32 = 1;
+    pub const SPRITE: u32 = 2;
+    pub const SOUND: u32 = 3;
+    pub const ROOM: u32 = 4;
+    pub const BACKGROUND: u32 = 6;
+    pub const SCRIPT: u32 = 7;
+    pub const PATH: u32 = 8;
+    pub const FONT: u32 = 9;
+    pub const INFORMATION: u32 = 10;
+    pub const SETTINGS: u32 = 11;
+    pub const TIMELINE: u32 = 12;
+    pub const EXTENSIONS: u32 = 13;
+}
+
+/// The top-level folders of the resource tree, in the order Game Maker expects them.
+const TREE: [(u32, &[u8]); 12] = [
+    (tree::SPRITE, b"Sprites"),
+    (tree::SOUND, b"Sounds"),
+    (tree::BACKGROUND, b"Backgrounds"),
+    (tree::PATH, b"Paths"),
+    (tree::SCRIPT, b"Scripts"),
+    (tree::FONT, b"Fonts"),
+    (tree::TIMELINE, b"Time Lines"),
+    (tree::OBJECT, b"Objects"),
+    (tree::ROOM, b"Rooms"),
+    (tree::INFORMATION, b"Game Information"),
+    (tree::SETTINGS, b"Global Game Settings"),
+    (tree::EXTENSIONS, b"Extension packages"),
+];
+
+/// Write the default game information window.
+fn write_information() -> Vec<u8> {
+    let mut writer = Writer::default();
+    writer.write_u32(0xFFFFE1); // background color
+    writer.write_bool(false); // mimic the game window
+    writer.write_string(b"Game Information");
+    writer.write_i32(-1); // left
+    writer.write_i32(-1); // top
+    writer.write_u32(600); // width
+    writer.write_u32(400); // height
+    writer.write_bool(true); // show the border
+    writer.write_bool(true); // allow resizing
+    writer.write_bool(false); // stay on top
+    writer.write_bool(true); // pause the game
+    writer.write_f64(0.0);
+    writer.write_string(b"");
+    writer.into_inner()
+}
+
+#[cfg(test)]
+mod tests {
+    use miniz_oxide::deflate;
+
+    use crate::{
+        Trigger, Sound, Sprite, Image, Background, Path, PathPoint, Script, Font, Timeline, Moment,
+        Object, Event, Action, Room, RoomBackground, View, Instance, Tile,
+        action_kind, action_type, argument_type,
+    };
+    use super::*;
+
+    /// Hand-assembled file contents, to test the reader independently of `write`.
+    #[derive(Default)]
+    struct Bytes(Vec<u8>);
+
+    impl Bytes {
+        fn u32(&mut self, value: u32) -> &mut Self {
+            self.0.extend_from_slice(&value.to_le_bytes());
+            self
+        }
+
+        fn f64(&mut self, value: f64) -> &mut Self {
+            self.0.extend_from_slice(&value.to_le_bytes());
+            self
+        }
+
+        fn string(&mut self, value: &[u8]) -> &mut Self {
+            self.u32(value.len() as u32);
+            self.0.extend_from_slice(value);
+            self
+        }
+
+        fn block(&mut self, block: &Bytes) -> &mut Self {
+            let data = deflate::compress_to_vec_zlib(&block.0, 6);
+            self.u32(data.len() as u32);
+            self.0.extend_from_slice(&data);
+            self
+        }
+
+        fn empty(&mut self) -> &mut Self {
+            self.u32(800).u32(0)
+        }
+    }
+
+    fn settings() -> Bytes {
+        let mut settings = Bytes::default();
+        settings.u32(1);
+        // The remaining display, key and loading bar settings, with no custom images.
+        for _ in 1..28 {
+            settings.u32(0);
+        }
+        settings.string(b"icon");
+        settings.u32(0).u32(0).u32(0).u32(1);
+        settings.string(b"author").string(b"").f64(0.0).string(b"");
+        settings.u32(1).u32(0).u32(0).u32(0);
+        settings.string(b"").string(b"").string(b"").string(b"").f64(0.0);
+        settings
+    }
+
+    fn project(scripts: &[Bytes], objects: &[Bytes]) -> Vec<u8> {
+        let mut file = Bytes::default();
+        file.u32(MAGIC).u32(810).u32(12345).0.extend_from_slice(&[0; 16]);
+        file.u32(810).block(&settings());
+        file.empty().f64(0.0);
+        file.u32(800).u32(1).string(b"LIVES").string(b"3").f64(0.0);
+        file.empty().empty().empty().empty();
+        file.u32(800).u32(scripts.len() as u32);
+        for script in scripts {
+            file.block(script);
+        }
+        file.empty().empty();
+        file.u32(800).u32(objects.len() as u32);
+        for object in objects {
+            file.block(object);
+        }
+        file.empty();
+        file.0
+    }
+
+    #[test]
+    fn scripts() {
+        let mut script = Bytes::default();
+        script.u32(1).string(b"scr_hello").f64(0.0).u32(800).string(b"show_message('hi')");
+        let mut deleted = Bytes::default();
+        deleted.u32(0);
+
+        let data = project(&[deleted, script], &[]);
+        let blocks = Blocks::read(&data).unwrap();
+        assert_eq!(blocks.version, 810);
+
+        let game = blocks.game().unwrap();
+        assert!(game.settings.fullscreen);
+        assert!(game.settings.uninitialized_as_zero);
+        assert_eq!(game.settings.icon, b"icon");
+        assert_eq!(game.settings.author, b"author");
+        assert_eq!(game.settings.major, 1);
+        assert_eq!(game.constants, [Constant { name: b"LIVES", value: b"3" }]);
+
+        assert_eq!(game.scripts.len(), 2);
+        assert_eq!(game.scripts[0].name, b"");
+        assert_eq!(game.scripts[1].name, b"scr_hello");
+        assert_eq!(game.scripts[1].body, b"show_message('hi')");
+    }
+
+    #[test]
+    fn objects() {
+        let mut object = Bytes::default();
+        object.u32(1).string(b"obj_player").f64(0.0).u32(430);
+        object.u32(-1i32 as u32).u32(0).u32(1).u32(0).u32(0).u32(-1i32 as u32).u32(-1i32 as u32);
+        object.u32(11);
+        for event_type in 0..=11 {
+            if event_type == 3 {
+                object.u32(0).u32(400).u32(1);
+                object.u32(440).u32(1).u32(603).u32(action_kind::CODE);
+                object.u32(0).u32(0).u32(1).u32(action_type::CODE).string(b"").string(b"");
+                object.u32(1).u32(8).u32(argument_type::STRING);
+                for _ in 1..8 { object.u32(argument_type::EXPR); }
+                object.u32(-2i32 as u32).u32(0);
+                object.u32(8).string(b"x += 1");
+                for _ in 1..8 { object.string(b""); }
+                object.u32(0);
+            }
+            object.u32(-1i32 as u32);
+        }
+
+        let data = project(&[], &[object]);
+        let blocks = Blocks::read(&data).unwrap();
+        let game = blocks.game().unwrap();
+        assert_eq!(game.objects.len(), 1);
+
+        let object = &game.objects[0];
+        assert_eq!(object.name, b"obj_player");
+        assert_eq!((object.sprite, object.parent, object.mask), (-1, -1, -1));
+        assert!(object.visible && !object.solid && !object.persistent);
+        assert_eq!(object.events.len(), 1);
+        assert_eq!(object.events[0].event_type, 3);
+        assert_eq!(object.events[0].event_kind, 0);
+
+        let action = &object.events[0].actions[0];
+        assert_eq!((action.library, action.action), (1, 603));
+        assert_eq!(action.action_kind, action_kind::CODE);
+        assert!(action.has_target);
+        assert_eq!(action.parameters_used, 1);
+        assert_eq!(action.parameters.len(), 8);
+        assert_eq!(action.target, -2);
+        assert_eq!(action.arguments[0], b"x += 1");
+        assert!(!action.negate);
+    }
+
+    fn game() -> Game<'static> {
+        let mut game = Game::default();
+        game.settings.fullscreen = true;
+        game.settings.icon = b"icon";
+        game.settings.uninitialized_as_zero = true;
+        game.settings.loading_bar = 2;
+        game.settings.loading_back = Some(b"back");
+        game.settings.loading_front = None;
+        game.settings.author = b"author";
+        game.triggers.push(Trigger {
+            name: b"trg_low",
+            condition: b"hp < 10",
+            moment: 1,
+            constant: b"ev_trg_low",
+        });
+        game.constants.push(Constant { name: b"MAX_HP", value: b"100" });
+        game.sounds.push(Sound {
+            name: b"snd_jump",
+            extension: b".wav",
+            data: Some(b"RIFF"),
+            volume: 0.5,
+            preload: true,
+            ..Sound::default()
+        });
+        game.sprites.push(Sprite {
+            name: b"spr_player",
+            origin_x: 8,
+            origin_y: 16,
+            frames: vec![
+                Image { width: 1, height: 2, data: &[0xFF; 8] },
+                Image { width: 0, height: 0, data: &[] },
+            ],
+            bbox_right: 15,
+            bbox_bottom: 31,
+            ..Sprite::default()
+        });
+        game.backgrounds.push(Background {
+            name: b"bg_tiles",
+            tileset: true,
+            tile_width: 16,
+            tile_height: 16,
+            image: Image { width: 2, height: 1, data: &[0x80; 8] },
+            ..Background::default()
+        });
+        game.paths.push(Path {
+            name: b"pth_patrol",
+            closed: true,
+            precision: 4,
+            points: vec![
+                PathPoint { x: 0.0, y: 0.0, speed: 100.0 },
+                PathPoint { x: 64.0, y: 32.5, speed: 50.0 },
+            ],
+            ..Path::default()
+        });
+        game.fonts.push(Font {
+            name: b"fnt_main",
+            system_name: b"Arial",
+            size: 12,
+            bold: true,
+            first: 32,
+            last: 127,
+            charset: 1,
+            antialias: 3,
+            ..Font::default()
+        });
+        game.timelines.push(Timeline {
+            name: b"tl_intro",
+            moments: vec![Moment { position: 30, actions: vec![Action::default()] }],
+        });
+        game.rooms.push(Room {
+            name: b"rm_start",
+            caption: b"Start",
+            width: 640,
+            height: 480,
+            speed: 30,
+            creation_code: b"score = 0",
+            backgrounds: vec![RoomBackground { visible: true, ..RoomBackground::default() }],
+            enable_views: true,
+            views: vec![View { visible: true, width: 320, height: 240, ..View::default() }],
+            instances: vec![Instance { x: 32, y: 48, object: 1, id: 100_001, creation_code: b"" }],
+            tiles: vec![Tile { width: 16, height: 16, id: 10_000_001, ..Tile::default() }],
+            ..Room::default()
+        });
+        game.rooms.push(Room::default());
+        game.scripts.push(Script::default());
+        game.scripts.push(Script { name: b"scr_hello", body: b"show_message('hi')" });
+        game.objects.push(Object {
+            name: b"obj_player",
+            events: vec![
+                Event { event_type: 0, event_kind: 0, actions: vec![] },
+                Event {
+                    event_type: 3,
+                    event_kind: 0,
+                    actions: vec![Action {
+                        library: 1,
+                        action: 603,
+                        action_kind: action_kind::CODE,
+                        has_target: true,
+                        action_type: action_type::CODE,
+                        parameters_used: 1,
+                        parameters: vec![argument_type::STRING, 0, 0, 0, 0, 0, 0, 0],
+                        target: -2,
+                        arguments: vec![b"x += 1", b"", b"", b"", b"", b"", b"", b""],
+                        ..Action::default()
+                    }],
+                },
+                Event {
+                    event_type: 4,
+                    event_kind: 1,
+                    actions: vec![Action {
+                        library: 1,
+                        action: 211,
+                        action_kind: action_kind::NORMAL,
+                        has_relative: true,
+                        is_question: true,
+                        has_target: true,
+                        action_type: action_type::FUNCTION,
+                        name: b"action_if_variable",
+                        parameters_used: 3,
+                        parameters: vec![
+                            argument_type::STRING, argument_type::EXPR, argument_type::MENU,
+                        ],
+                        target: 1,
+                        relative: true,
+                        arguments: vec![b"hp", b"0", b"1"],
+                        negate: true,
+                        ..Action::default()
+                    }],
+                },
+            ],
+            ..Object::default()
+        });
+        game.objects.push(Object::default());
+        game.objects.push(Object {
+            name: b"obj_wall",
+            sprite: 0,
+            solid: true,
+            depth: 10,
+            parent: 2,
+            ..Object::default()
+        });
+        game
+    }
+
+    #[test]
+    fn round_trip() {
+        let game = game();
+        let data = write(&game);
+        let blocks = Blocks::read(&data).unwrap();
+        assert_eq!(blocks.version, 800);
+        assert_eq!(blocks.game().unwrap(), game);
+    }
+
+    /// GM 8.1 marks the file header and the settings section with version 810.
+    #[test]
+    fn version_810() {
+        let game = game();
+        let mut data = write(&game);
+        data[4..8].copy_from_slice(&810u32.to_le_bytes());
+        data[28..32].copy_from_slice(&810u32.to_le_bytes());
+
+        let blocks = Blocks::read(&data).unwrap();
+        assert_eq!(blocks.version, 810);
+        assert_eq!(blocks.game().unwrap(), game);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(Blocks::read(b"GMK"), Err(Error::UnexpectedEof)));
+        assert!(matches!(Blocks::read(&[0; 8]), Err(Error::Magic)));
+
+        let mut data = write(&Game::default());
+        data[4..8].copy_from_slice(&530u32.to_le_bytes());
+        assert!(matches!(Blocks::read(&data), Err(Error::Version(530))));
+    }
+}
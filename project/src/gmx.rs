@@ -0,0 +1,413 @@
+//! GameMaker: Studio projects (.project.gmx).
+//!
+//! A Studio project is a directory. The .project.gmx file lists each resource by path, in nested
+//! groups, and each resource lives in its own file: scripts as plain .gml source, and objects as
+//! .object.gmx XML documents. Resources refer to each other by name rather than by index.
+//!
+//! Only scripts, objects, and constants are imported in full. Other resources are imported by
+//! name, so that indices and references to them still line up.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::{fs, str};
+
+use roxmltree::{Document, Node};
+
+use crate::Error;
+use crate::owned::{
+    Game, Constant, Sound, Sprite, Background, Path as GamePath, Script, Font, Timeline, Object,
+    Event, Action, Room, Version,
+};
+
+/// The resource groups of a project file, and the element name of their entries.
+const RESOURCES: [(&str, &str); 9] = [
+    ("sprites", "sprite"),
+    ("sounds", "sound"),
+    ("backgrounds", "background"),
+    ("paths", "path"),
+    ("scripts", "script"),
+    ("fonts", "font"),
+    ("timelines", "timeline"),
+    ("objects", "object"),
+    ("rooms", "room"),
+];
+
+/// Import the project described by a .project.gmx file.
+pub fn read(path: &Path) -> Result<Game, Error> {
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let text = read_xml(path)?;
+    let document = parse_xml(&text, path)?;
+    let root = document.root_element();
+
+    // Resources are referred to by name, but `Game` refers to them by index.
+    let mut paths = HashMap::new();
+    for &(group, entry) in RESOURCES.iter() {
+        let entries: Vec<_> = root.children()
+            .filter(|node| node.has_tag_name(group))
+            .flat_map(|node| node.descendants())
+            .filter(|node| node.has_tag_name(entry))
+            .map(|node| resource_path(node.text().unwrap_or("")))
+            .collect();
+        paths.insert(entry, entries);
+    }
+    let mut names = HashMap::new();
+    for (&entry, entries) in &paths {
+        for (index, path) in entries.iter().enumerate() {
+            names.insert((entry, resource_name(path)), index as i32);
+        }
+    }
+
+    let mut scripts = Vec::default();
+    for path in &paths["script"] {
+        let name = resource_name(path).as_bytes().to_vec();
+        let body = fs::read(directory.join(path))?;
+        scripts.push(Script { name, body });
+    }
+
+    let mut objects = Vec::default();
+    for path in &paths["object"] {
+        let name = resource_name(path);
+        let path = directory.join(format!("{}.object.gmx", path.display()));
+        objects.push(read_object(name, &path, &names)?);
+    }
+
+    let constants = root.children()
+        .filter(|node| node.has_tag_name("constants"))
+        .flat_map(|node| elements(node, "constant"))
+        .map(|node| {
+            let name = node.attribute("name").unwrap_or("").as_bytes().to_vec();
+            let value = node.text().unwrap_or("").as_bytes().to_vec();
+            Constant { name, value }
+        })
+        .collect();
+
+    Ok(Game {
+        version: Version::GameMakerStudio,
+        constants,
+        sounds: names_of(&paths["sound"]).map(|name| Sound { name, ..Sound::default() }).collect(),
+        sprites: names_of(&paths["sprite"])
+            .map(|name| Sprite { name, ..Sprite::default() })
+            .collect(),
+        backgrounds: names_of(&paths["background"])
+            .map(|name| Background { name, ..Background::default() })
+            .collect(),
+        paths: names_of(&paths["path"])
+            .map(|name| GamePath { name, ..GamePath::default() })
+            .collect(),
+        scripts,
+        fonts: names_of(&paths["font"]).map(|name| Font { name, ..Font::default() }).collect(),
+        timelines: names_of(&paths["timeline"])
+            .map(|name| Timeline { name, ..Timeline::default() })
+            .collect(),
+        objects,
+        rooms: names_of(&paths["room"]).map(|name| Room { name, ..Room::default() }).collect(),
+        ..Game::default()
+    })
+}
+
+fn names_of(paths: &[PathBuf]) -> impl Iterator<Item = Vec<u8>> + '_ {
+    paths.iter().map(|path| resource_name(path).as_bytes().to_vec())
+}
+
+type Names<'a> = HashMap<(&'static str, &'a str), i32>;
+
+fn read_object(name: &str, path: &Path, names: &Names<'_>) -> Result<Object, Error> {
+    let text = read_xml(path)?;
+    let document = parse_xml(&text, path)?;
+    let root = document.root_element();
+
+    let mut events = Vec::default();
+    for event in elements(root, "events").flat_map(|node| elements(node, "event")) {
+        let event_type = parse_attribute(event, "eventtype")?;
+        let event_kind = match event.attribute("ename") {
+            Some(object) => lookup(names, "object", object)?,
+            None => parse_attribute(event, "enumb")?,
+        };
+
+        let actions = elements(event, "action")
+            .map(|action| read_action(action, names))
+            .collect::<Result<_, _>>()?;
+        events.push(Event { event_type, event_kind, actions });
+    }
+
+    Ok(Object {
+        name: name.as_bytes().to_vec(),
+        sprite: lookup(names, "sprite", child_text(root, "spriteName"))?,
+        solid: parse_bool(root, "solid")?,
+        visible: parse_bool(root, "visible")?,
+        depth: parse_child(root, "depth")?,
+        persistent: parse_bool(root, "persistent")?,
+        parent: lookup(names, "object", child_text(root, "parentName"))?,
+        mask: lookup(names, "sprite", child_text(root, "maskName"))?,
+        events,
+    })
+}
+
+fn read_action(node: Node<'_, '_>, names: &Names<'_>) -> Result<Action, Error> {
+    let target = match child_text(node, "whoName") {
+        "self" | "" => -1,
+        "other" => -2,
+        object => lookup(names, "object", object)?,
+    };
+
+    let mut parameters = Vec::default();
+    let mut arguments = Vec::default();
+    for argument in elements(node, "arguments").flat_map(|node| elements(node, "argument")) {
+        parameters.push(parse_child(argument, "kind")?);
+        let value = read_argument(argument, names)?;
+        arguments.push(value);
+    }
+
+    Ok(Action {
+        library: parse_child(node, "libid")?,
+        action: parse_child(node, "id")?,
+        action_kind: parse_child(node, "kind")?,
+        has_relative: parse_bool(node, "userelative")?,
+        is_question: parse_bool(node, "isquestion")?,
+        has_target: parse_bool(node, "useapplyto")?,
+        action_type: parse_child(node, "exetype")?,
+        name: child_text(node, "functionname").as_bytes().to_vec(),
+        code: child_text(node, "codestring").as_bytes().to_vec(),
+        parameters_used: arguments.len() as u32,
+        parameters,
+        target,
+        relative: parse_bool(node, "relative")?,
+        arguments,
+        negate: parse_bool(node, "isnot")?,
+    })
+}
+
+fn read_argument(argument: Node<'_, '_>, names: &Names<'_>) -> Result<Vec<u8>, Error> {
+    let value = argument.children().find(|node| node.is_element() && !node.has_tag_name("kind"));
+    let node = match value {
+        Some(node) => node,
+        None => return Ok(Vec::default()),
+    };
+    let text = node.text().unwrap_or("");
+    if node.has_tag_name("string") {
+        return Ok(text.as_bytes().to_vec());
+    }
+
+    // Resource arguments are stored by name, under an element named for their type.
+    let entry = RESOURCES.iter()
+        .map(|&(_, entry)| entry)
+        .find(|&entry| node.has_tag_name(entry))
+        .ok_or_else(|| Error::Xml(format!("invalid argument {}", node.tag_name().name())))?;
+    Ok(lookup(names, entry, text)?.to_string().into_bytes())
+}
+
+fn read_xml(path: &Path) -> Result<String, Error> {
+    let text = fs::read_to_string(path)?;
+    Ok(text.trim_start_matches('\u{feff}').to_string())
+}
+
+fn parse_xml<'a>(text: &'a str, path: &Path) -> Result<Document<'a>, Error> {
+    Document::parse(text).map_err(|error| Error::Xml(format!("{}: {}", path.display(), error)))
+}
+
+/// Convert a project file entry, which uses Windows separators, into a relative path.
+fn resource_path(entry: &str) -> PathBuf {
+    entry.trim().split('\\').collect()
+}
+
+fn resource_name(path: &Path) -> &str {
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+    name.split('.').next().unwrap_or("")
+}
+
+fn lookup(names: &Names<'_>, entry: &'static str, name: &str) -> Result<i32, Error> {
+    match name {
+        "" | "<undefined>" => Ok(-1),
+        _ => names.get(&(entry, name)).copied()
+            .ok_or_else(|| Error::Resource(format!("unknown {} {}", entry, name))),
+    }
+}
+
+fn elements<'a, 'i>(
+    node: Node<'a, 'i>, name: &'static str
+) -> impl Iterator<Item = Node<'a, 'i>> {
+    node.children().filter(move |node| node.has_tag_name(name))
+}
+
+fn child_text<'a>(node: Node<'a, '_>, name: &'static str) -> &'a str {
+    elements(node, name).next().and_then(|node| node.text()).unwrap_or("")
+}
+
+fn parse_child<T: str::FromStr>(node: Node<'_, '_>, name: &'static str) -> Result<T, Error> {
+    let text = child_text(node, name).trim();
+    text.parse().map_err(|_| Error::Xml(format!("invalid {} {:?}", name, text)))
+}
+
+fn parse_attribute<T: str::FromStr>(node: Node<'_, '_>, name: &'static str) -> Result<T, Error> {
+    let text = node.attribute(name).unwrap_or("").trim();
+    text.parse().map_err(|_| Error::Xml(format!("invalid {} {:?}", name, text)))
+}
+
+/// Studio writes `true` as -1.
+fn parse_bool(node: Node<'_, '_>, name: &'static str) -> Result<bool, Error> {
+    let value: i32 = parse_child(node, name)?;
+    Ok(value != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use crate::{action_kind, action_type, argument_type};
+    use super::*;
+
+    const PROJECT: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<!--This Document is generated by GameMaker, if you edit it by hand then you do so at your own risk!-->
+<assets>
+  <Configs name="configs">
+    <Config>Configs\Default</Config>
+  </Configs>
+  <sprites name="sprites">
+    <sprite>sprites\spr_player</sprite>
+  </sprites>
+  <scripts name="scripts">
+    <script>scripts\scr_init.gml</script>
+    <scripts name="movement">
+      <script>scripts\scr_move.gml</script>
+    </scripts>
+  </scripts>
+  <objects name="objects">
+    <object>objects\obj_wall</object>
+    <object>objects\obj_player</object>
+  </objects>
+</assets>
+"#;
+
+    const PLAYER: &str = r#"<!--This Document is generated by GameMaker, if you edit it by hand then you do so at your own risk!-->
+<object>
+  <spriteName>spr_player</spriteName>
+  <solid>0</solid>
+  <visible>-1</visible>
+  <depth>-10</depth>
+  <persistent>0</persistent>
+  <parentName>obj_wall</parentName>
+  <maskName>&lt;undefined&gt;</maskName>
+  <events>
+    <event eventtype="3" enumb="0">
+      <action>
+        <libid>1</libid>
+        <id>603</id>
+        <kind>7</kind>
+        <userelative>0</userelative>
+        <isquestion>0</isquestion>
+        <useapplyto>-1</useapplyto>
+        <exetype>2</exetype>
+        <functionname></functionname>
+        <codestring></codestring>
+        <whoName>self</whoName>
+        <relative>0</relative>
+        <isnot>0</isnot>
+        <arguments>
+          <argument>
+            <kind>1</kind>
+            <string>if x &lt; 10 { scr_move(4) }</string>
+          </argument>
+        </arguments>
+      </action>
+    </event>
+    <event eventtype="4" ename="obj_wall">
+      <action>
+        <libid>1</libid>
+        <id>201</id>
+        <kind>0</kind>
+        <userelative>0</userelative>
+        <isquestion>0</isquestion>
+        <useapplyto>-1</useapplyto>
+        <exetype>1</exetype>
+        <functionname>action_change_object</functionname>
+        <codestring></codestring>
+        <whoName>obj_wall</whoName>
+        <relative>0</relative>
+        <isnot>0</isnot>
+        <arguments>
+          <argument>
+            <kind>10</kind>
+            <object>obj_player</object>
+          </argument>
+          <argument>
+            <kind>3</kind>
+            <string>1</string>
+          </argument>
+        </arguments>
+      </action>
+    </event>
+  </events>
+  <PhysicsObject>0</PhysicsObject>
+</object>
+"#;
+
+    const WALL: &str = r#"<object>
+  <spriteName>&lt;undefined&gt;</spriteName>
+  <solid>-1</solid>
+  <visible>0</visible>
+  <depth>0</depth>
+  <persistent>0</persistent>
+  <parentName>&lt;undefined&gt;</parentName>
+  <maskName>&lt;undefined&gt;</maskName>
+  <events/>
+</object>
+"#;
+
+    #[test]
+    fn import() {
+        let root = env::temp_dir().join(format!("dejavu-gmx-{}", process::id()));
+        fs::create_dir_all(root.join("scripts")).unwrap();
+        fs::create_dir_all(root.join("objects")).unwrap();
+        fs::write(root.join("test.project.gmx"), PROJECT).unwrap();
+        fs::write(root.join("scripts/scr_init.gml"), "global.score = 0").unwrap();
+        fs::write(root.join("scripts/scr_move.gml"), "x += argument0").unwrap();
+        fs::write(root.join("objects/obj_wall.object.gmx"), WALL).unwrap();
+        fs::write(root.join("objects/obj_player.object.gmx"), PLAYER).unwrap();
+
+        let game = read(&root.join("test.project.gmx"));
+        fs::remove_dir_all(&root).unwrap();
+        let game = game.unwrap();
+        let game = game.borrow();
+
+        assert_eq!(game.version, Version::GameMakerStudio);
+        assert_eq!(game.scripts.len(), 2);
+        assert_eq!(game.scripts[0].name, b"scr_init");
+        assert_eq!(game.scripts[1].name, b"scr_move");
+        assert_eq!(game.scripts[1].body, b"x += argument0");
+
+        assert_eq!(game.objects.len(), 2);
+        let wall = &game.objects[0];
+        assert_eq!(wall.name, b"obj_wall");
+        assert_eq!((wall.sprite, wall.parent), (-1, -1));
+        assert!(wall.solid && !wall.visible);
+        assert!(wall.events.is_empty());
+
+        let player = &game.objects[1];
+        assert_eq!(player.name, b"obj_player");
+        assert_eq!((player.sprite, player.parent, player.mask), (0, 0, -1));
+        assert_eq!(player.depth, -10);
+        assert!(!player.solid && player.visible);
+        assert_eq!(player.events.len(), 2);
+
+        let step = &player.events[0];
+        assert_eq!((step.event_type, step.event_kind), (3, 0));
+        let code = &step.actions[0];
+        assert_eq!((code.library, code.action), (1, 603));
+        assert_eq!(code.action_kind, action_kind::CODE);
+        assert_eq!(code.action_type, action_type::CODE);
+        assert!(code.has_target);
+        assert_eq!(code.target, -1);
+        assert_eq!(code.parameters, [argument_type::STRING]);
+        assert_eq!(code.arguments, [&b"if x < 10 { scr_move(4) }"[..]]);
+
+        let collision = &player.events[1];
+        assert_eq!((collision.event_type, collision.event_kind), (4, 0));
+        let change = &collision.actions[0];
+        assert_eq!(change.action_type, action_type::FUNCTION);
+        assert_eq!(change.name, b"action_change_object");
+        assert_eq!(change.target, 0);
+        assert_eq!(change.parameters_used, 2);
+        assert_eq!(change.parameters, [argument_type::OBJECT, argument_type::BOOL]);
+        assert_eq!(change.arguments, [&b"1"[..], &b"1"[..]]);
+    }
+}
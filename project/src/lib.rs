@@ -1,28 +1,251 @@
+use std::{error, fmt, io};
+
+pub mod gmk;
+pub mod exe;
+pub mod gmx;
+pub mod owned;
+
+mod resource;
+mod stream;
+
+#[derive(Default, PartialEq, Debug)]
 pub struct Game<'a> {
+    pub version: Version,
+    pub settings: Settings<'a>,
+    pub triggers: Vec<Trigger<'a>>,
+    pub constants: Vec<Constant<'a>>,
+    pub sounds: Vec<Sound<'a>>,
+    pub sprites: Vec<Sprite<'a>>,
+    pub backgrounds: Vec<Background<'a>>,
+    pub paths: Vec<Path<'a>>,
     pub scripts: Vec<Script<'a>>,
+    pub fonts: Vec<Font<'a>>,
+    pub timelines: Vec<Timeline<'a>>,
     pub objects: Vec<Object<'a>>,
+    pub rooms: Vec<Room<'a>>,
+}
+
+/// The dialect of GML that a project's code is written in.
+///
+/// Versions are ordered by release, so `version >= Version::GameMakerStudio` tests for a GMS-era
+/// feature. GM 5.x through 8.x share one dialect, so projects from any of them use `GameMaker8`.
+///
+/// GMS differs from GM8 in these ways:
+/// - strings may escape characters with `\`,
+/// - `&&` and `||` skip their right operand when the left one decides the result,
+/// - `var` declarations may initialize their locals,
+/// - uninitialized variables are always an error, regardless of the project's settings.
+///
+/// GMS array copy-on-write is not modelled, so GMS arrays behave like GM8 arrays.
+#[derive(Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Version {
+    #[default]
+    GameMaker8,
+    GameMakerStudio,
+}
+
+/// Global game settings.
+///
+/// Loading images are zlib-compressed bitmaps, stored as they appear in project files.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Settings<'a> {
+    pub fullscreen: bool,
+    pub interpolate: bool,
+    pub no_border: bool,
+    pub show_cursor: bool,
+    pub scaling: i32,
+    pub resizable: bool,
+    pub always_on_top: bool,
+    pub clear_color: u32,
+    pub set_resolution: bool,
+    pub color_depth: u32,
+    pub resolution: u32,
+    pub frequency: u32,
+    pub no_buttons: bool,
+    pub vsync: bool,
+    pub disable_screensaver: bool,
+    pub f4_fullscreen: bool,
+    pub f1_information: bool,
+    pub escape_ends_game: bool,
+    pub f5_save_f6_load: bool,
+    pub f9_screenshot: bool,
+    pub close_as_escape: bool,
+    pub priority: u32,
+    pub freeze_unfocused: bool,
+    pub loading_bar: u32,
+    pub loading_back: Option<&'a [u8]>,
+    pub loading_front: Option<&'a [u8]>,
+    pub loading_image: Option<&'a [u8]>,
+    pub loading_transparent: bool,
+    pub loading_alpha: u32,
+    pub scale_progress_bar: bool,
+    pub icon: &'a [u8],
+    pub show_errors: bool,
+    pub log_errors: bool,
+    pub abort_on_error: bool,
+    pub uninitialized_as_zero: bool,
+    pub author: &'a [u8],
+    pub version: &'a [u8],
+    pub information: &'a [u8],
+    pub major: u32,
+    pub minor: u32,
+    pub release: u32,
+    pub build: u32,
+    pub company: &'a [u8],
+    pub product: &'a [u8],
+    pub copyright: &'a [u8],
+    pub description: &'a [u8],
+}
+
+#[derive(Default, PartialEq, Eq, Debug)]
+pub struct Trigger<'a> {
+    pub name: &'a [u8],
+    pub condition: &'a [u8],
+    pub moment: u32,
+    pub constant: &'a [u8],
+}
+
+#[derive(Default, PartialEq, Eq, Debug)]
+pub struct Constant<'a> {
+    pub name: &'a [u8],
+    pub value: &'a [u8],
+}
+
+#[derive(Default, PartialEq, Debug)]
+pub struct Sound<'a> {
+    pub name: &'a [u8],
+    pub kind: u32,
+    pub extension: &'a [u8],
+    pub file_name: &'a [u8],
+    pub data: Option<&'a [u8]>,
+    pub effects: u32,
+    pub volume: f64,
+    pub pan: f64,
+    pub preload: bool,
+}
+
+#[derive(Default, PartialEq, Eq, Debug)]
+pub struct Sprite<'a> {
+    pub name: &'a [u8],
+    pub origin_x: i32,
+    pub origin_y: i32,
+    pub frames: Vec<Image<'a>>,
+    pub shape: u32,
+    pub alpha_tolerance: u32,
+    pub separate_masks: bool,
+    pub bbox_mode: u32,
+    pub bbox_left: i32,
+    pub bbox_right: i32,
+    pub bbox_bottom: i32,
+    pub bbox_top: i32,
+    /// Precomputed collision masks, which are only present in executables.
+    pub masks: Vec<Mask>,
+}
+
+/// An image as 32-bit BGRA pixels.
+#[derive(Default, PartialEq, Eq, Debug)]
+pub struct Image<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub data: &'a [u8],
+}
+
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+pub struct Mask {
+    pub width: u32,
+    pub height: u32,
+    pub left: i32,
+    pub right: i32,
+    pub bottom: i32,
+    pub top: i32,
+    pub data: Vec<bool>,
+}
+
+#[derive(Default, PartialEq, Eq, Debug)]
+pub struct Background<'a> {
+    pub name: &'a [u8],
+    pub tileset: bool,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub tile_x_offset: u32,
+    pub tile_y_offset: u32,
+    pub tile_x_separation: u32,
+    pub tile_y_separation: u32,
+    pub image: Image<'a>,
 }
 
-#[derive(Default)]
+#[derive(Default, PartialEq, Debug)]
+pub struct Path<'a> {
+    pub name: &'a [u8],
+    pub kind: u32,
+    pub closed: bool,
+    pub precision: u32,
+    pub points: Vec<PathPoint>,
+}
+
+#[derive(Default, Copy, Clone, PartialEq, Debug)]
+pub struct PathPoint {
+    pub x: f64,
+    pub y: f64,
+    pub speed: f64,
+}
+
+#[derive(Default, PartialEq, Eq, Debug)]
 pub struct Script<'a> {
     pub name: &'a [u8],
     pub body: &'a [u8],
 }
 
-#[derive(Default)]
+#[derive(Default, PartialEq, Eq, Debug)]
+pub struct Font<'a> {
+    pub name: &'a [u8],
+    pub system_name: &'a [u8],
+    pub size: u32,
+    pub bold: bool,
+    pub italic: bool,
+    pub first: u32,
+    pub last: u32,
+    pub charset: u32,
+    pub antialias: u32,
+}
+
+#[derive(Default, PartialEq, Eq, Debug)]
+pub struct Timeline<'a> {
+    pub name: &'a [u8],
+    pub moments: Vec<Moment<'a>>,
+}
+
+#[derive(Default, PartialEq, Eq, Debug)]
+pub struct Moment<'a> {
+    pub position: u32,
+    pub actions: Vec<Action<'a>>,
+}
+
+/// An object, with the defaults for its instances.
+///
+/// `sprite`, `mask`, and `parent` are resource indices, or -1 for none. Instances of an object
+/// are also instances of its ancestors, and inherit any events it does not define.
+#[derive(PartialEq, Eq, Debug)]
 pub struct Object<'a> {
     pub name: &'a [u8],
+    pub sprite: i32,
+    pub solid: bool,
+    pub visible: bool,
+    pub depth: i32,
+    pub persistent: bool,
+    pub parent: i32,
+    pub mask: i32,
     pub events: Vec<Event<'a>>,
 }
 
-#[derive(Default)]
+#[derive(Default, PartialEq, Eq, Debug)]
 pub struct Event<'a> {
     pub event_type: u32,
     pub event_kind: i32,
     pub actions: Vec<Action<'a>>,
 }
 
-#[derive(Default)]
+#[derive(Default, PartialEq, Eq, Debug)]
 pub struct Action<'a> {
     pub library: u32,
     pub action: u32,
@@ -41,6 +264,93 @@ pub struct Action<'a> {
     pub negate: bool,
 }
 
+#[derive(Default, PartialEq, Eq, Debug)]
+pub struct Room<'a> {
+    pub name: &'a [u8],
+    pub caption: &'a [u8],
+    pub width: u32,
+    pub height: u32,
+    pub speed: u32,
+    pub persistent: bool,
+    pub background_color: u32,
+    pub draw_background_color: bool,
+    pub creation_code: &'a [u8],
+    pub backgrounds: Vec<RoomBackground>,
+    pub enable_views: bool,
+    pub views: Vec<View>,
+    pub instances: Vec<Instance<'a>>,
+    pub tiles: Vec<Tile>,
+}
+
+#[derive(Default, Copy, Clone, PartialEq, Eq, Debug)]
+pub struct RoomBackground {
+    pub visible: bool,
+    pub foreground: bool,
+    pub background: i32,
+    pub x: i32,
+    pub y: i32,
+    pub tile_x: bool,
+    pub tile_y: bool,
+    pub speed_x: i32,
+    pub speed_y: i32,
+    pub stretch: bool,
+}
+
+#[derive(Default, Copy, Clone, PartialEq, Eq, Debug)]
+pub struct View {
+    pub visible: bool,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub port_x: i32,
+    pub port_y: i32,
+    pub port_width: u32,
+    pub port_height: u32,
+    pub border_x: u32,
+    pub border_y: u32,
+    pub speed_x: i32,
+    pub speed_y: i32,
+    pub target: i32,
+}
+
+#[derive(Default, PartialEq, Eq, Debug)]
+pub struct Instance<'a> {
+    pub x: i32,
+    pub y: i32,
+    pub object: i32,
+    pub id: i32,
+    pub creation_code: &'a [u8],
+}
+
+#[derive(Default, Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Tile {
+    pub x: i32,
+    pub y: i32,
+    pub background: i32,
+    pub tile_x: i32,
+    pub tile_y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub depth: i32,
+    pub id: i32,
+}
+
+pub mod event_type {
+    pub const CREATE: u32 = 0;
+    pub const DESTROY: u32 = 1;
+    pub const ALARM: u32 = 2;
+    pub const STEP: u32 = 3;
+    pub const COLLISION: u32 = 4;
+    pub const KEYBOARD: u32 = 5;
+    pub const MOUSE: u32 = 6;
+    pub const OTHER: u32 = 7;
+    pub const DRAW: u32 = 8;
+    pub const KEY_PRESS: u32 = 9;
+    pub const KEY_RELEASE: u32 = 10;
+    pub const TRIGGER: u32 = 11;
+}
+
 pub mod action_kind {
     pub const NORMAL: u32 = 0;
     pub const BEGIN: u32 = 1;
@@ -80,11 +390,112 @@ pub mod argument_type {
     pub const FONT_STRING: u32 = 15;
 }
 
-impl<'a> Default for Game<'a> {
-    fn default() -> Game<'a> {
-        Game {
-            scripts: Vec::default(),
-            objects: Vec::default(),
+/// The settings of a new Game Maker 8 project.
+impl<'a> Default for Settings<'a> {
+    fn default() -> Settings<'a> {
+        Settings {
+            fullscreen: false,
+            interpolate: false,
+            no_border: false,
+            show_cursor: true,
+            scaling: -1,
+            resizable: false,
+            always_on_top: false,
+            clear_color: 0,
+            set_resolution: false,
+            color_depth: 0,
+            resolution: 0,
+            frequency: 0,
+            no_buttons: false,
+            vsync: false,
+            disable_screensaver: true,
+            f4_fullscreen: true,
+            f1_information: true,
+            escape_ends_game: true,
+            f5_save_f6_load: true,
+            f9_screenshot: true,
+            close_as_escape: true,
+            priority: 0,
+            freeze_unfocused: false,
+            loading_bar: 1,
+            loading_back: None,
+            loading_front: None,
+            loading_image: None,
+            loading_transparent: false,
+            loading_alpha: 255,
+            scale_progress_bar: true,
+            icon: &[],
+            show_errors: true,
+            log_errors: false,
+            abort_on_error: false,
+            uninitialized_as_zero: false,
+            author: b"",
+            version: b"100",
+            information: b"",
+            major: 1,
+            minor: 0,
+            release: 0,
+            build: 0,
+            company: b"",
+            product: b"",
+            copyright: b"",
+            description: b"",
+        }
+    }
+}
+
+/// The properties of a new Game Maker 8 object.
+impl<'a> Default for Object<'a> {
+    fn default() -> Object<'a> {
+        Object {
+            name: b"",
+            sprite: -1,
+            solid: false,
+            visible: true,
+            depth: 0,
+            persistent: false,
+            parent: -1,
+            mask: -1,
+            events: Vec::default(),
+        }
+    }
+}
+
+/// An error encountered while loading a project.
+#[derive(Debug)]
+pub enum Error {
+    /// The data ended in the middle of a structure.
+    UnexpectedEof,
+    /// The data does not start with the Game Maker magic number.
+    Magic,
+    /// A section was saved by an unsupported version of Game Maker.
+    Version(u32),
+    /// A compressed block could not be inflated.
+    Inflate,
+    /// A project file could not be read.
+    Io(io::Error),
+    /// A project file is not well-formed XML, or is missing a required value.
+    Xml(String),
+    /// A resource refers to another resource that does not exist.
+    Resource(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Error::UnexpectedEof => write!(f, "unexpected end of file"),
+            Error::Magic => write!(f, "not a Game Maker file"),
+            Error::Version(version) => write!(f, "unsupported version {}", version),
+            Error::Inflate => write!(f, "corrupt compressed block"),
+            Error::Io(ref error) => write!(f, "{}", error),
+            Error::Xml(ref message) => write!(f, "{}", message),
+            Error::Resource(ref message) => write!(f, "{}", message),
         }
     }
 }
+
+impl error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error { Error::Io(error) }
+}
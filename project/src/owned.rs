@@ -0,0 +1,849 @@
+//! An owned counterpart to the borrowed project model.
+//!
+//! These types own their strings, so they can be built up incrementally, stored, and edited
+//! without pinning any backing buffers. `borrow` produces the borrowed form that the compiler
+//! consumes without copying any strings. Types that borrow nothing are shared with the borrowed
+//! model.
+
+pub use crate::{Version, Mask, PathPoint, RoomBackground, View, Tile};
+
+#[derive(Default, Clone, PartialEq, Debug)]
+pub struct Game {
+    pub version: Version,
+    pub settings: Settings,
+    pub triggers: Vec<Trigger>,
+    pub constants: Vec<Constant>,
+    pub sounds: Vec<Sound>,
+    pub sprites: Vec<Sprite>,
+    pub backgrounds: Vec<Background>,
+    pub paths: Vec<Path>,
+    pub scripts: Vec<Script>,
+    pub fonts: Vec<Font>,
+    pub timelines: Vec<Timeline>,
+    pub objects: Vec<Object>,
+    pub rooms: Vec<Room>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Settings {
+    pub fullscreen: bool,
+    pub interpolate: bool,
+    pub no_border: bool,
+    pub show_cursor: bool,
+    pub scaling: i32,
+    pub resizable: bool,
+    pub always_on_top: bool,
+    pub clear_color: u32,
+    pub set_resolution: bool,
+    pub color_depth: u32,
+    pub resolution: u32,
+    pub frequency: u32,
+    pub no_buttons: bool,
+    pub vsync: bool,
+    pub disable_screensaver: bool,
+    pub f4_fullscreen: bool,
+    pub f1_information: bool,
+    pub escape_ends_game: bool,
+    pub f5_save_f6_load: bool,
+    pub f9_screenshot: bool,
+    pub close_as_escape: bool,
+    pub priority: u32,
+    pub freeze_unfocused: bool,
+    pub loading_bar: u32,
+    pub loading_back: Option<Vec<u8>>,
+    pub loading_front: Option<Vec<u8>>,
+    pub loading_image: Option<Vec<u8>>,
+    pub loading_transparent: bool,
+    pub loading_alpha: u32,
+    pub scale_progress_bar: bool,
+    pub icon: Vec<u8>,
+    pub show_errors: bool,
+    pub log_errors: bool,
+    pub abort_on_error: bool,
+    pub uninitialized_as_zero: bool,
+    pub author: Vec<u8>,
+    pub version: Vec<u8>,
+    pub information: Vec<u8>,
+    pub major: u32,
+    pub minor: u32,
+    pub release: u32,
+    pub build: u32,
+    pub company: Vec<u8>,
+    pub product: Vec<u8>,
+    pub copyright: Vec<u8>,
+    pub description: Vec<u8>,
+}
+
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+pub struct Trigger {
+    pub name: Vec<u8>,
+    pub condition: Vec<u8>,
+    pub moment: u32,
+    pub constant: Vec<u8>,
+}
+
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+pub struct Constant {
+    pub name: Vec<u8>,
+    pub value: Vec<u8>,
+}
+
+#[derive(Default, Clone, PartialEq, Debug)]
+pub struct Sound {
+    pub name: Vec<u8>,
+    pub kind: u32,
+    pub extension: Vec<u8>,
+    pub file_name: Vec<u8>,
+    pub data: Option<Vec<u8>>,
+    pub effects: u32,
+    pub volume: f64,
+    pub pan: f64,
+    pub preload: bool,
+}
+
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+pub struct Sprite {
+    pub name: Vec<u8>,
+    pub origin_x: i32,
+    pub origin_y: i32,
+    pub frames: Vec<Image>,
+    pub shape: u32,
+    pub alpha_tolerance: u32,
+    pub separate_masks: bool,
+    pub bbox_mode: u32,
+    pub bbox_left: i32,
+    pub bbox_right: i32,
+    pub bbox_bottom: i32,
+    pub bbox_top: i32,
+    pub masks: Vec<Mask>,
+}
+
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+pub struct Background {
+    pub name: Vec<u8>,
+    pub tileset: bool,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub tile_x_offset: u32,
+    pub tile_y_offset: u32,
+    pub tile_x_separation: u32,
+    pub tile_y_separation: u32,
+    pub image: Image,
+}
+
+#[derive(Default, Clone, PartialEq, Debug)]
+pub struct Path {
+    pub name: Vec<u8>,
+    pub kind: u32,
+    pub closed: bool,
+    pub precision: u32,
+    pub points: Vec<PathPoint>,
+}
+
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+pub struct Script {
+    pub name: Vec<u8>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+pub struct Font {
+    pub name: Vec<u8>,
+    pub system_name: Vec<u8>,
+    pub size: u32,
+    pub bold: bool,
+    pub italic: bool,
+    pub first: u32,
+    pub last: u32,
+    pub charset: u32,
+    pub antialias: u32,
+}
+
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+pub struct Timeline {
+    pub name: Vec<u8>,
+    pub moments: Vec<Moment>,
+}
+
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+pub struct Moment {
+    pub position: u32,
+    pub actions: Vec<Action>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Object {
+    pub name: Vec<u8>,
+    pub sprite: i32,
+    pub solid: bool,
+    pub visible: bool,
+    pub depth: i32,
+    pub persistent: bool,
+    pub parent: i32,
+    pub mask: i32,
+    pub events: Vec<Event>,
+}
+
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+pub struct Event {
+    pub event_type: u32,
+    pub event_kind: i32,
+    pub actions: Vec<Action>,
+}
+
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+pub struct Action {
+    pub library: u32,
+    pub action: u32,
+    pub action_kind: u32,
+    pub has_relative: bool,
+    pub is_question: bool,
+    pub has_target: bool,
+    pub action_type: u32,
+    pub name: Vec<u8>,
+    pub code: Vec<u8>,
+    pub parameters_used: u32,
+    pub parameters: Vec<u32>,
+    pub target: i32,
+    pub relative: bool,
+    pub arguments: Vec<Vec<u8>>,
+    pub negate: bool,
+}
+
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+pub struct Room {
+    pub name: Vec<u8>,
+    pub caption: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub speed: u32,
+    pub persistent: bool,
+    pub background_color: u32,
+    pub draw_background_color: bool,
+    pub creation_code: Vec<u8>,
+    pub backgrounds: Vec<RoomBackground>,
+    pub enable_views: bool,
+    pub views: Vec<View>,
+    pub instances: Vec<Instance>,
+    pub tiles: Vec<Tile>,
+}
+
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+pub struct Instance {
+    pub x: i32,
+    pub y: i32,
+    pub object: i32,
+    pub id: i32,
+    pub creation_code: Vec<u8>,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings::from(&crate::Settings::default())
+    }
+}
+
+impl Default for Object {
+    fn default() -> Object {
+        Object::from(&crate::Object::default())
+    }
+}
+
+impl Game {
+    pub fn borrow(&self) -> crate::Game<'_> {
+        crate::Game {
+            version: self.version,
+            settings: self.settings.borrow(),
+            triggers: self.triggers.iter().map(Trigger::borrow).collect(),
+            constants: self.constants.iter().map(Constant::borrow).collect(),
+            sounds: self.sounds.iter().map(Sound::borrow).collect(),
+            sprites: self.sprites.iter().map(Sprite::borrow).collect(),
+            backgrounds: self.backgrounds.iter().map(Background::borrow).collect(),
+            paths: self.paths.iter().map(Path::borrow).collect(),
+            scripts: self.scripts.iter().map(Script::borrow).collect(),
+            fonts: self.fonts.iter().map(Font::borrow).collect(),
+            timelines: self.timelines.iter().map(Timeline::borrow).collect(),
+            objects: self.objects.iter().map(Object::borrow).collect(),
+            rooms: self.rooms.iter().map(Room::borrow).collect(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn borrow(&self) -> crate::Settings<'_> {
+        crate::Settings {
+            fullscreen: self.fullscreen,
+            interpolate: self.interpolate,
+            no_border: self.no_border,
+            show_cursor: self.show_cursor,
+            scaling: self.scaling,
+            resizable: self.resizable,
+            always_on_top: self.always_on_top,
+            clear_color: self.clear_color,
+            set_resolution: self.set_resolution,
+            color_depth: self.color_depth,
+            resolution: self.resolution,
+            frequency: self.frequency,
+            no_buttons: self.no_buttons,
+            vsync: self.vsync,
+            disable_screensaver: self.disable_screensaver,
+            f4_fullscreen: self.f4_fullscreen,
+            f1_information: self.f1_information,
+            escape_ends_game: self.escape_ends_game,
+            f5_save_f6_load: self.f5_save_f6_load,
+            f9_screenshot: self.f9_screenshot,
+            close_as_escape: self.close_as_escape,
+            priority: self.priority,
+            freeze_unfocused: self.freeze_unfocused,
+            loading_bar: self.loading_bar,
+            loading_back: self.loading_back.as_deref(),
+            loading_front: self.loading_front.as_deref(),
+            loading_image: self.loading_image.as_deref(),
+            loading_transparent: self.loading_transparent,
+            loading_alpha: self.loading_alpha,
+            scale_progress_bar: self.scale_progress_bar,
+            icon: &self.icon,
+            show_errors: self.show_errors,
+            log_errors: self.log_errors,
+            abort_on_error: self.abort_on_error,
+            uninitialized_as_zero: self.uninitialized_as_zero,
+            author: &self.author,
+            version: &self.version,
+            information: &self.information,
+            major: self.major,
+            minor: self.minor,
+            release: self.release,
+            build: self.build,
+            company: &self.company,
+            product: &self.product,
+            copyright: &self.copyright,
+            description: &self.description,
+        }
+    }
+}
+
+impl Trigger {
+    pub fn borrow(&self) -> crate::Trigger<'_> {
+        crate::Trigger {
+            name: &self.name,
+            condition: &self.condition,
+            moment: self.moment,
+            constant: &self.constant,
+        }
+    }
+}
+
+impl Constant {
+    pub fn borrow(&self) -> crate::Constant<'_> {
+        crate::Constant { name: &self.name, value: &self.value }
+    }
+}
+
+impl Sound {
+    pub fn borrow(&self) -> crate::Sound<'_> {
+        crate::Sound {
+            name: &self.name,
+            kind: self.kind,
+            extension: &self.extension,
+            file_name: &self.file_name,
+            data: self.data.as_deref(),
+            effects: self.effects,
+            volume: self.volume,
+            pan: self.pan,
+            preload: self.preload,
+        }
+    }
+}
+
+impl Sprite {
+    pub fn borrow(&self) -> crate::Sprite<'_> {
+        crate::Sprite {
+            name: &self.name,
+            origin_x: self.origin_x,
+            origin_y: self.origin_y,
+            frames: self.frames.iter().map(Image::borrow).collect(),
+            shape: self.shape,
+            alpha_tolerance: self.alpha_tolerance,
+            separate_masks: self.separate_masks,
+            bbox_mode: self.bbox_mode,
+            bbox_left: self.bbox_left,
+            bbox_right: self.bbox_right,
+            bbox_bottom: self.bbox_bottom,
+            bbox_top: self.bbox_top,
+            masks: self.masks.clone(),
+        }
+    }
+}
+
+impl Image {
+    pub fn borrow(&self) -> crate::Image<'_> {
+        crate::Image { width: self.width, height: self.height, data: &self.data }
+    }
+}
+
+impl Background {
+    pub fn borrow(&self) -> crate::Background<'_> {
+        crate::Background {
+            name: &self.name,
+            tileset: self.tileset,
+            tile_width: self.tile_width,
+            tile_height: self.tile_height,
+            tile_x_offset: self.tile_x_offset,
+            tile_y_offset: self.tile_y_offset,
+            tile_x_separation: self.tile_x_separation,
+            tile_y_separation: self.tile_y_separation,
+            image: self.image.borrow(),
+        }
+    }
+}
+
+impl Path {
+    pub fn borrow(&self) -> crate::Path<'_> {
+        crate::Path {
+            name: &self.name,
+            kind: self.kind,
+            closed: self.closed,
+            precision: self.precision,
+            points: self.points.clone(),
+        }
+    }
+}
+
+impl Script {
+    pub fn borrow(&self) -> crate::Script<'_> {
+        crate::Script { name: &self.name, body: &self.body }
+    }
+}
+
+impl Font {
+    pub fn borrow(&self) -> crate::Font<'_> {
+        crate::Font {
+            name: &self.name,
+            system_name: &self.system_name,
+            size: self.size,
+            bold: self.bold,
+            italic: self.italic,
+            first: self.first,
+            last: self.last,
+            charset: self.charset,
+            antialias: self.antialias,
+        }
+    }
+}
+
+impl Timeline {
+    pub fn borrow(&self) -> crate::Timeline<'_> {
+        crate::Timeline {
+            name: &self.name,
+            moments: self.moments.iter().map(Moment::borrow).collect(),
+        }
+    }
+}
+
+impl Moment {
+    pub fn borrow(&self) -> crate::Moment<'_> {
+        crate::Moment {
+            position: self.position,
+            actions: self.actions.iter().map(Action::borrow).collect(),
+        }
+    }
+}
+
+impl Object {
+    pub fn borrow(&self) -> crate::Object<'_> {
+        crate::Object {
+            name: &self.name,
+            sprite: self.sprite,
+            solid: self.solid,
+            visible: self.visible,
+            depth: self.depth,
+            persistent: self.persistent,
+            parent: self.parent,
+            mask: self.mask,
+            events: self.events.iter().map(Event::borrow).collect(),
+        }
+    }
+}
+
+impl Event {
+    pub fn borrow(&self) -> crate::Event<'_> {
+        crate::Event {
+            event_type: self.event_type,
+            event_kind: self.event_kind,
+            actions: self.actions.iter().map(Action::borrow).collect(),
+        }
+    }
+}
+
+impl Action {
+    pub fn borrow(&self) -> crate::Action<'_> {
+        crate::Action {
+            library: self.library,
+            action: self.action,
+            action_kind: self.action_kind,
+            has_relative: self.has_relative,
+            is_question: self.is_question,
+            has_target: self.has_target,
+            action_type: self.action_type,
+            name: &self.name,
+            code: &self.code,
+            parameters_used: self.parameters_used,
+            parameters: self.parameters.clone(),
+            target: self.target,
+            relative: self.relative,
+            arguments: self.arguments.iter().map(|argument| &argument[..]).collect(),
+            negate: self.negate,
+        }
+    }
+}
+
+impl Room {
+    pub fn borrow(&self) -> crate::Room<'_> {
+        crate::Room {
+            name: &self.name,
+            caption: &self.caption,
+            width: self.width,
+            height: self.height,
+            speed: self.speed,
+            persistent: self.persistent,
+            background_color: self.background_color,
+            draw_background_color: self.draw_background_color,
+            creation_code: &self.creation_code,
+            backgrounds: self.backgrounds.clone(),
+            enable_views: self.enable_views,
+            views: self.views.clone(),
+            instances: self.instances.iter().map(Instance::borrow).collect(),
+            tiles: self.tiles.clone(),
+        }
+    }
+}
+
+impl Instance {
+    pub fn borrow(&self) -> crate::Instance<'_> {
+        crate::Instance {
+            x: self.x,
+            y: self.y,
+            object: self.object,
+            id: self.id,
+            creation_code: &self.creation_code,
+        }
+    }
+}
+
+impl From<&crate::Game<'_>> for Game {
+    fn from(game: &crate::Game<'_>) -> Game {
+        Game {
+            version: game.version,
+            settings: Settings::from(&game.settings),
+            triggers: game.triggers.iter().map(Trigger::from).collect(),
+            constants: game.constants.iter().map(Constant::from).collect(),
+            sounds: game.sounds.iter().map(Sound::from).collect(),
+            sprites: game.sprites.iter().map(Sprite::from).collect(),
+            backgrounds: game.backgrounds.iter().map(Background::from).collect(),
+            paths: game.paths.iter().map(Path::from).collect(),
+            scripts: game.scripts.iter().map(Script::from).collect(),
+            fonts: game.fonts.iter().map(Font::from).collect(),
+            timelines: game.timelines.iter().map(Timeline::from).collect(),
+            objects: game.objects.iter().map(Object::from).collect(),
+            rooms: game.rooms.iter().map(Room::from).collect(),
+        }
+    }
+}
+
+impl From<&crate::Settings<'_>> for Settings {
+    fn from(settings: &crate::Settings<'_>) -> Settings {
+        Settings {
+            fullscreen: settings.fullscreen,
+            interpolate: settings.interpolate,
+            no_border: settings.no_border,
+            show_cursor: settings.show_cursor,
+            scaling: settings.scaling,
+            resizable: settings.resizable,
+            always_on_top: settings.always_on_top,
+            clear_color: settings.clear_color,
+            set_resolution: settings.set_resolution,
+            color_depth: settings.color_depth,
+            resolution: settings.resolution,
+            frequency: settings.frequency,
+            no_buttons: settings.no_buttons,
+            vsync: settings.vsync,
+            disable_screensaver: settings.disable_screensaver,
+            f4_fullscreen: settings.f4_fullscreen,
+            f1_information: settings.f1_information,
+            escape_ends_game: settings.escape_ends_game,
+            f5_save_f6_load: settings.f5_save_f6_load,
+            f9_screenshot: settings.f9_screenshot,
+            close_as_escape: settings.close_as_escape,
+            priority: settings.priority,
+            freeze_unfocused: settings.freeze_unfocused,
+            loading_bar: settings.loading_bar,
+            loading_back: settings.loading_back.map(<[u8]>::to_vec),
+            loading_front: settings.loading_front.map(<[u8]>::to_vec),
+            loading_image: settings.loading_image.map(<[u8]>::to_vec),
+            loading_transparent: settings.loading_transparent,
+            loading_alpha: settings.loading_alpha,
+            scale_progress_bar: settings.scale_progress_bar,
+            icon: settings.icon.to_vec(),
+            show_errors: settings.show_errors,
+            log_errors: settings.log_errors,
+            abort_on_error: settings.abort_on_error,
+            uninitialized_as_zero: settings.uninitialized_as_zero,
+            author: settings.author.to_vec(),
+            version: settings.version.to_vec(),
+            information: settings.information.to_vec(),
+            major: settings.major,
+            minor: settings.minor,
+            release: settings.release,
+            build: settings.build,
+            company: settings.company.to_vec(),
+            product: settings.product.to_vec(),
+            copyright: settings.copyright.to_vec(),
+            description: settings.description.to_vec(),
+        }
+    }
+}
+
+impl From<&crate::Trigger<'_>> for Trigger {
+    fn from(trigger: &crate::Trigger<'_>) -> Trigger {
+        Trigger {
+            name: trigger.name.to_vec(),
+            condition: trigger.condition.to_vec(),
+            moment: trigger.moment,
+            constant: trigger.constant.to_vec(),
+        }
+    }
+}
+
+impl From<&crate::Constant<'_>> for Constant {
+    fn from(constant: &crate::Constant<'_>) -> Constant {
+        Constant { name: constant.name.to_vec(), value: constant.value.to_vec() }
+    }
+}
+
+impl From<&crate::Sound<'_>> for Sound {
+    fn from(sound: &crate::Sound<'_>) -> Sound {
+        Sound {
+            name: sound.name.to_vec(),
+            kind: sound.kind,
+            extension: sound.extension.to_vec(),
+            file_name: sound.file_name.to_vec(),
+            data: sound.data.map(<[u8]>::to_vec),
+            effects: sound.effects,
+            volume: sound.volume,
+            pan: sound.pan,
+            preload: sound.preload,
+        }
+    }
+}
+
+impl From<&crate::Sprite<'_>> for Sprite {
+    fn from(sprite: &crate::Sprite<'_>) -> Sprite {
+        Sprite {
+            name: sprite.name.to_vec(),
+            origin_x: sprite.origin_x,
+            origin_y: sprite.origin_y,
+            frames: sprite.frames.iter().map(Image::from).collect(),
+            shape: sprite.shape,
+            alpha_tolerance: sprite.alpha_tolerance,
+            separate_masks: sprite.separate_masks,
+            bbox_mode: sprite.bbox_mode,
+            bbox_left: sprite.bbox_left,
+            bbox_right: sprite.bbox_right,
+            bbox_bottom: sprite.bbox_bottom,
+            bbox_top: sprite.bbox_top,
+            masks: sprite.masks.clone(),
+        }
+    }
+}
+
+impl From<&crate::Image<'_>> for Image {
+    fn from(image: &crate::Image<'_>) -> Image {
+        Image { width: image.width, height: image.height, data: image.data.to_vec() }
+    }
+}
+
+impl From<&crate::Background<'_>> for Background {
+    fn from(background: &crate::Background<'_>) -> Background {
+        Background {
+            name: background.name.to_vec(),
+            tileset: background.tileset,
+            tile_width: background.tile_width,
+            tile_height: background.tile_height,
+            tile_x_offset: background.tile_x_offset,
+            tile_y_offset: background.tile_y_offset,
+            tile_x_separation: background.tile_x_separation,
+            tile_y_separation: background.tile_y_separation,
+            image: Image::from(&background.image),
+        }
+    }
+}
+
+impl From<&crate::Path<'_>> for Path {
+    fn from(path: &crate::Path<'_>) -> Path {
+        Path {
+            name: path.name.to_vec(),
+            kind: path.kind,
+            closed: path.closed,
+            precision: path.precision,
+            points: path.points.clone(),
+        }
+    }
+}
+
+impl From<&crate::Script<'_>> for Script {
+    fn from(script: &crate::Script<'_>) -> Script {
+        Script { name: script.name.to_vec(), body: script.body.to_vec() }
+    }
+}
+
+impl From<&crate::Font<'_>> for Font {
+    fn from(font: &crate::Font<'_>) -> Font {
+        Font {
+            name: font.name.to_vec(),
+            system_name: font.system_name.to_vec(),
+            size: font.size,
+            bold: font.bold,
+            italic: font.italic,
+            first: font.first,
+            last: font.last,
+            charset: font.charset,
+            antialias: font.antialias,
+        }
+    }
+}
+
+impl From<&crate::Timeline<'_>> for Timeline {
+    fn from(timeline: &crate::Timeline<'_>) -> Timeline {
+        Timeline {
+            name: timeline.name.to_vec(),
+            moments: timeline.moments.iter().map(Moment::from).collect(),
+        }
+    }
+}
+
+impl From<&crate::Moment<'_>> for Moment {
+    fn from(moment: &crate::Moment<'_>) -> Moment {
+        Moment {
+            position: moment.position,
+            actions: moment.actions.iter().map(Action::from).collect(),
+        }
+    }
+}
+
+impl From<&crate::Object<'_>> for Object {
+    fn from(object: &crate::Object<'_>) -> Object {
+        Object {
+            name: object.name.to_vec(),
+            sprite: object.sprite,
+            solid: object.solid,
+            visible: object.visible,
+            depth: object.depth,
+            persistent: object.persistent,
+            parent: object.parent,
+            mask: object.mask,
+            events: object.events.iter().map(Event::from).collect(),
+        }
+    }
+}
+
+impl From<&crate::Event<'_>> for Event {
+    fn from(event: &crate::Event<'_>) -> Event {
+        Event {
+            event_type: event.event_type,
+            event_kind: event.event_kind,
+            actions: event.actions.iter().map(Action::from).collect(),
+        }
+    }
+}
+
+impl From<&crate::Action<'_>> for Action {
+    fn from(action: &crate::Action<'_>) -> Action {
+        Action {
+            library: action.library,
+            action: action.action,
+            action_kind: action.action_kind,
+            has_relative: action.has_relative,
+            is_question: action.is_question,
+            has_target: action.has_target,
+            action_type: action.action_type,
+            name: action.name.to_vec(),
+            code: action.code.to_vec(),
+            parameters_used: action.parameters_used,
+            parameters: action.parameters.clone(),
+            target: action.target,
+            relative: action.relative,
+            arguments: action.arguments.iter().map(|argument| argument.to_vec()).collect(),
+            negate: action.negate,
+        }
+    }
+}
+
+impl From<&crate::Room<'_>> for Room {
+    fn from(room: &crate::Room<'_>) -> Room {
+        Room {
+            name: room.name.to_vec(),
+            caption: room.caption.to_vec(),
+            width: room.width,
+            height: room.height,
+            speed: room.speed,
+            persistent: room.persistent,
+            background_color: room.background_color,
+            draw_background_color: room.draw_background_color,
+            creation_code: room.creation_code.to_vec(),
+            backgrounds: room.backgrounds.clone(),
+            enable_views: room.enable_views,
+            views: room.views.clone(),
+            instances: room.instances.iter().map(Instance::from).collect(),
+            tiles: room.tiles.clone(),
+        }
+    }
+}
+
+impl From<&crate::Instance<'_>> for Instance {
+    fn from(instance: &crate::Instance<'_>) -> Instance {
+        Instance {
+            x: instance.x,
+            y: instance.y,
+            object: instance.object,
+            id: instance.id,
+            creation_code: instance.creation_code.to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gmk;
+    use super::*;
+
+    #[test]
+    fn build_and_borrow() {
+        let mut game = Game::default();
+        game.settings.icon = b"icon".to_vec();
+        game.scripts.push(Script { name: b"scr_init".to_vec(), body: Vec::default() });
+        game.scripts[0].body.extend_from_slice(b"global.lives = 3");
+
+        let mut object = Object { name: b"obj_player".to_vec(), ..Object::default() };
+        object.events.push(Event {
+            event_type: 0,
+            event_kind: 0,
+            actions: vec![Action { code: b"x = 0".to_vec(), ..Action::default() }],
+        });
+        game.objects.push(object);
+
+        let borrowed = game.borrow();
+        assert_eq!(borrowed.scripts[0].body, b"global.lives = 3");
+        assert_eq!(borrowed.objects[0].events[0].actions[0].code, b"x = 0");
+
+        let data = gmk::write(&borrowed);
+        let blocks = gmk::Blocks::read(&data).unwrap();
+        assert_eq!(Game::from(&blocks.game().unwrap()), game);
+    }
+}
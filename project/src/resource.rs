@@ -0,0 +1,927 @@
+//! The block encoding of individual resources, shared by project files and executables.
+//!
+//! Each resource block starts with a flag that marks deleted resources. Deleted resources are
+//! read as `Default` values, and resources with empty names are written as deleted.
+
+use crate::{
+    Settings, Trigger, Sound, Sprite, Image, Mask, Background, Path, PathPoint, Script, Font,
+    Timeline, Moment, Object, Event, Action, Room, RoomBackground, View, Instance, Tile, Error,
+};
+use crate::stream::{Reader, Writer};
+
+/// The container a set of resource blocks came from.
+///
+/// Executables use the same resource encoding as project files, minus editor-only metadata, plus
+/// some data that the editor computes when building the game.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) enum Format {
+    Gmk,
+    Exe,
+}
+
+/// The highest event type known to Game Maker 8, which it always writes out.
+const LAST_EVENT_TYPE: u32 = 11;
+
+/// The loading bar setting that enables custom loading bar images.
+const LOADING_BAR_CUSTOM: u32 = 2;
+
+/// Read a list of resource blocks, substituting `Default` values for deleted resources.
+pub(crate) fn read_blocks<'a, T: Default>(
+    blocks: &'a [Vec<u8>], format: Format,
+    read: fn(&mut Reader<'a>, Format) -> Result<T, Error>
+) -> Result<Vec<T>, Error> {
+    blocks.iter()
+        .map(|block| {
+            let mut reader = Reader::new(block);
+            if !reader.read_bool()? {
+                return Ok(T::default());
+            }
+            read(&mut reader, format)
+        })
+        .collect()
+}
+
+/// Write a resource block, or a deleted resource if `name` is empty.
+pub(crate) fn write_block<T>(
+    writer: &mut Writer, resource: &T, name: &[u8], format: Format,
+    write: fn(&mut Writer, &T, Format)
+) {
+    let mut block = Writer::default();
+    block.write_bool(!name.is_empty());
+    if !name.is_empty() {
+        write(&mut block, resource, format);
+    }
+    writer.write_block(&block.into_inner());
+}
+
+/// Skip the last-modified timestamp that project files store for each resource.
+fn read_changed(reader: &mut Reader<'_>, format: Format) -> Result<(), Error> {
+    if format == Format::Gmk {
+        let _changed = reader.read_f64()?;
+    }
+    Ok(())
+}
+
+fn write_changed(writer: &mut Writer, format: Format) {
+    if format == Format::Gmk {
+        writer.write_f64(0.0);
+    }
+}
+
+/// Read the global game settings.
+///
+/// GM 8.1 packs additional flags alongside the vertical sync and uninitialized variable settings,
+/// so those are read from the low bit only.
+pub(crate) fn read_settings<'a>(
+    reader: &mut Reader<'a>, format: Format
+) -> Result<Settings<'a>, Error> {
+    let mut settings = Settings {
+        fullscreen: reader.read_bool()?,
+        interpolate: reader.read_bool()?,
+        no_border: reader.read_bool()?,
+        show_cursor: reader.read_bool()?,
+        scaling: reader.read_i32()?,
+        resizable: reader.read_bool()?,
+        always_on_top: reader.read_bool()?,
+        clear_color: reader.read_u32()?,
+        set_resolution: reader.read_bool()?,
+        color_depth: reader.read_u32()?,
+        resolution: reader.read_u32()?,
+        frequency: reader.read_u32()?,
+        no_buttons: reader.read_bool()?,
+        vsync: reader.read_u32()? & 1 != 0,
+        disable_screensaver: reader.read_bool()?,
+        f4_fullscreen: reader.read_bool()?,
+        f1_information: reader.read_bool()?,
+        escape_ends_game: reader.read_bool()?,
+        f5_save_f6_load: reader.read_bool()?,
+        f9_screenshot: reader.read_bool()?,
+        close_as_escape: reader.read_bool()?,
+        priority: reader.read_u32()?,
+        freeze_unfocused: reader.read_bool()?,
+        loading_bar: reader.read_u32()?,
+        ..Settings::default()
+    };
+
+    if settings.loading_bar == LOADING_BAR_CUSTOM {
+        settings.loading_back = read_optional(reader)?;
+        settings.loading_front = read_optional(reader)?;
+    }
+    if reader.read_bool()? {
+        settings.loading_image = read_optional(reader)?;
+    }
+    settings.loading_transparent = reader.read_bool()?;
+    settings.loading_alpha = reader.read_u32()?;
+    settings.scale_progress_bar = reader.read_bool()?;
+
+    // Executables store their icon as a Windows resource instead.
+    if format == Format::Gmk {
+        settings.icon = reader.read_string()?;
+    }
+
+    settings.show_errors = reader.read_bool()?;
+    settings.log_errors = reader.read_bool()?;
+    settings.abort_on_error = reader.read_bool()?;
+    settings.uninitialized_as_zero = reader.read_u32()? & 1 != 0;
+
+    if format == Format::Gmk {
+        settings.author = reader.read_string()?;
+        settings.version = reader.read_string()?;
+        let _changed = reader.read_f64()?;
+        settings.information = reader.read_string()?;
+        settings.major = reader.read_u32()?;
+        settings.minor = reader.read_u32()?;
+        settings.release = reader.read_u32()?;
+        settings.build = reader.read_u32()?;
+        settings.company = reader.read_string()?;
+        settings.product = reader.read_string()?;
+        settings.copyright = reader.read_string()?;
+        settings.description = reader.read_string()?;
+        let _changed = reader.read_f64()?;
+    }
+
+    Ok(settings)
+}
+
+pub(crate) fn write_settings(writer: &mut Writer, settings: &Settings<'_>, format: Format) {
+    writer.write_bool(settings.fullscreen);
+    writer.write_bool(settings.interpolate);
+    writer.write_bool(settings.no_border);
+    writer.write_bool(settings.show_cursor);
+    writer.write_i32(settings.scaling);
+    writer.write_bool(settings.resizable);
+    writer.write_bool(settings.always_on_top);
+    writer.write_u32(settings.clear_color);
+    writer.write_bool(settings.set_resolution);
+    writer.write_u32(settings.color_depth);
+    writer.write_u32(settings.resolution);
+    writer.write_u32(settings.frequency);
+    writer.write_bool(settings.no_buttons);
+    writer.write_bool(settings.vsync);
+    writer.write_bool(settings.disable_screensaver);
+    writer.write_bool(settings.f4_fullscreen);
+    writer.write_bool(settings.f1_information);
+    writer.write_bool(settings.escape_ends_game);
+    writer.write_bool(settings.f5_save_f6_load);
+    writer.write_bool(settings.f9_screenshot);
+    writer.write_bool(settings.close_as_escape);
+    writer.write_u32(settings.priority);
+    writer.write_bool(settings.freeze_unfocused);
+
+    writer.write_u32(settings.loading_bar);
+    if settings.loading_bar == LOADING_BAR_CUSTOM {
+        write_optional(writer, settings.loading_back);
+        write_optional(writer, settings.loading_front);
+    }
+    writer.write_bool(settings.loading_image.is_some());
+    if settings.loading_image.is_some() {
+        write_optional(writer, settings.loading_image);
+    }
+    writer.write_bool(settings.loading_transparent);
+    writer.write_u32(settings.loading_alpha);
+    writer.write_bool(settings.scale_progress_bar);
+
+    if format == Format::Gmk {
+        if settings.icon.is_empty() {
+            writer.write_string(&icon());
+        } else {
+            writer.write_string(settings.icon);
+        }
+    }
+
+    writer.write_bool(settings.show_errors);
+    writer.write_bool(settings.log_errors);
+    writer.write_bool(settings.abort_on_error);
+    writer.write_bool(settings.uninitialized_as_zero);
+
+    if format == Format::Gmk {
+        writer.write_string(settings.author);
+        writer.write_string(settings.version);
+        writer.write_f64(0.0);
+        writer.write_string(settings.information);
+        writer.write_u32(settings.major);
+        writer.write_u32(settings.minor);
+        writer.write_u32(settings.release);
+        writer.write_u32(settings.build);
+        writer.write_string(settings.company);
+        writer.write_string(settings.product);
+        writer.write_string(settings.copyright);
+        writer.write_string(settings.description);
+        writer.write_f64(0.0);
+    }
+}
+
+/// Build a blank 16x16 icon, which Game Maker requires in its settings.
+fn icon() -> Vec<u8> {
+    const SIZE: u32 = 16;
+    let pixels = SIZE * SIZE * 4;
+    let mask = SIZE * 4;
+
+    let mut writer = Writer::default();
+    writer.write_bytes(&[0, 0, 1, 0, 1, 0]);
+    writer.write_bytes(&[SIZE as u8, SIZE as u8, 0, 0, 1, 0, 32, 0]);
+    writer.write_u32(40 + pixels + mask);
+    writer.write_u32(6 + 16);
+
+    writer.write_u32(40);
+    writer.write_u32(SIZE);
+    writer.write_u32(SIZE * 2);
+    writer.write_bytes(&[1, 0, 32, 0]);
+    writer.write_u32(0);
+    writer.write_u32(pixels + mask);
+    writer.write_bytes(&[0; 16]);
+    writer.write_bytes(&vec![0; (pixels + mask) as usize]);
+    writer.into_inner()
+}
+
+fn read_optional<'a>(reader: &mut Reader<'a>) -> Result<Option<&'a [u8]>, Error> {
+    if !reader.read_bool()? {
+        return Ok(None);
+    }
+    Ok(Some(reader.read_string()?))
+}
+
+fn write_optional(writer: &mut Writer, data: Option<&[u8]>) {
+    writer.write_bool(data.is_some());
+    if let Some(data) = data {
+        writer.write_string(data);
+    }
+}
+
+pub(crate) fn read_trigger<'a>(reader: &mut Reader<'a>, _: Format) -> Result<Trigger<'a>, Error> {
+    reader.read_version(&[800])?;
+    let name = reader.read_string()?;
+    let condition = reader.read_string()?;
+    let moment = reader.read_u32()?;
+    let constant = reader.read_string()?;
+    Ok(Trigger { name, condition, moment, constant })
+}
+
+pub(crate) fn write_trigger(writer: &mut Writer, trigger: &Trigger<'_>, _: Format) {
+    writer.write_u32(800);
+    writer.write_string(trigger.name);
+    writer.write_string(trigger.condition);
+    writer.write_u32(trigger.moment);
+    writer.write_string(trigger.constant);
+}
+
+pub(crate) fn read_sound<'a>(reader: &mut Reader<'a>, format: Format) -> Result<Sound<'a>, Error> {
+    let name = reader.read_string()?;
+    read_changed(reader, format)?;
+    reader.read_version(&[800])?;
+    let kind = reader.read_u32()?;
+    let extension = reader.read_string()?;
+    let file_name = reader.read_string()?;
+    let data = read_optional(reader)?;
+    let effects = reader.read_u32()?;
+    let volume = reader.read_f64()?;
+    let pan = reader.read_f64()?;
+    let preload = reader.read_bool()?;
+    Ok(Sound { name, kind, extension, file_name, data, effects, volume, pan, preload })
+}
+
+pub(crate) fn write_sound(writer: &mut Writer, sound: &Sound<'_>, format: Format) {
+    writer.write_string(sound.name);
+    write_changed(writer, format);
+    writer.write_u32(800);
+    writer.write_u32(sound.kind);
+    writer.write_string(sound.extension);
+    writer.write_string(sound.file_name);
+    write_optional(writer, sound.data);
+    writer.write_u32(sound.effects);
+    writer.write_f64(sound.volume);
+    writer.write_f64(sound.pan);
+    writer.write_bool(sound.preload);
+}
+
+/// Read a sprite.
+///
+/// Project files store the settings the editor uses to compute collision masks, while executables
+/// store the computed masks.
+pub(crate) fn read_sprite<'a>(
+    reader: &mut Reader<'a>, format: Format
+) -> Result<Sprite<'a>, Error> {
+    let name = reader.read_string()?;
+    read_changed(reader, format)?;
+    reader.read_version(&[800])?;
+    let origin_x = reader.read_i32()?;
+    let origin_y = reader.read_i32()?;
+
+    let count = reader.read_u32()?;
+    let frames = (0..count).map(|_| read_image(reader)).collect::<Result<_, _>>()?;
+    let mut sprite = Sprite { name, origin_x, origin_y, frames, ..Sprite::default() };
+
+    match format {
+        Format::Gmk => {
+            sprite.shape = reader.read_u32()?;
+            sprite.alpha_tolerance = reader.read_u32()?;
+            sprite.separate_masks = reader.read_bool()?;
+            sprite.bbox_mode = reader.read_u32()?;
+            sprite.bbox_left = reader.read_i32()?;
+            sprite.bbox_right = reader.read_i32()?;
+            sprite.bbox_bottom = reader.read_i32()?;
+            sprite.bbox_top = reader.read_i32()?;
+        }
+
+        Format::Exe => if count > 0 {
+            sprite.separate_masks = reader.read_bool()?;
+            let count = if sprite.separate_masks { count } else { 1 };
+            sprite.masks = (0..count).map(|_| read_mask(reader)).collect::<Result<_, _>>()?;
+        }
+    }
+
+    Ok(sprite)
+}
+
+pub(crate) fn write_sprite(writer: &mut Writer, sprite: &Sprite<'_>, format: Format) {
+    writer.write_string(sprite.name);
+    write_changed(writer, format);
+    writer.write_u32(800);
+    writer.write_i32(sprite.origin_x);
+    writer.write_i32(sprite.origin_y);
+
+    writer.write_u32(sprite.frames.len() as u32);
+    for frame in &sprite.frames {
+        write_image(writer, frame);
+    }
+
+    match format {
+        Format::Gmk => {
+            writer.write_u32(sprite.shape);
+            writer.write_u32(sprite.alpha_tolerance);
+            writer.write_bool(sprite.separate_masks);
+            writer.write_u32(sprite.bbox_mode);
+            writer.write_i32(sprite.bbox_left);
+            writer.write_i32(sprite.bbox_right);
+            writer.write_i32(sprite.bbox_bottom);
+            writer.write_i32(sprite.bbox_top);
+        }
+
+        Format::Exe => if !sprite.frames.is_empty() {
+            writer.write_bool(sprite.separate_masks);
+            let count = if sprite.separate_masks { sprite.frames.len() } else { 1 };
+            let empty = Mask::default();
+            for i in 0..count {
+                write_mask(writer, sprite.masks.get(i).unwrap_or(&empty));
+            }
+        }
+    }
+}
+
+/// Read an image, which omits its pixel data when it is empty.
+fn read_image<'a>(reader: &mut Reader<'a>) -> Result<Image<'a>, Error> {
+    reader.read_version(&[800])?;
+    let width = reader.read_u32()?;
+    let height = reader.read_u32()?;
+    let data = if width != 0 && height != 0 { reader.read_string()? } else { &[] };
+    Ok(Image { width, height, data })
+}
+
+fn write_image(writer: &mut Writer, image: &Image<'_>) {
+    writer.write_u32(800);
+    writer.write_u32(image.width);
+    writer.write_u32(image.height);
+    if image.width != 0 && image.height != 0 {
+        writer.write_string(image.data);
+    }
+}
+
+fn read_mask(reader: &mut Reader<'_>) -> Result<Mask, Error> {
+    reader.read_version(&[800])?;
+    let width = reader.read_u32()?;
+    let height = reader.read_u32()?;
+    let left = reader.read_i32()?;
+    let right = reader.read_i32()?;
+    let bottom = reader.read_i32()?;
+    let top = reader.read_i32()?;
+    let data = (0..width as u64 * height as u64)
+        .map(|_| reader.read_bool())
+        .collect::<Result<_, _>>()?;
+    Ok(Mask { width, height, left, right, bottom, top, data })
+}
+
+fn write_mask(writer: &mut Writer, mask: &Mask) {
+    writer.write_u32(800);
+    writer.write_u32(mask.width);
+    writer.write_u32(mask.height);
+    writer.write_i32(mask.left);
+    writer.write_i32(mask.right);
+    writer.write_i32(mask.bottom);
+    writer.write_i32(mask.top);
+    for &pixel in &mask.data {
+        writer.write_bool(pixel);
+    }
+}
+
+/// Read a background.
+///
+/// Executables omit the tile set settings, which only the room editor uses.
+pub(crate) fn read_background<'a>(
+    reader: &mut Reader<'a>, format: Format
+) -> Result<Background<'a>, Error> {
+    let mut background = Background { name: reader.read_string()?, ..Background::default() };
+    read_changed(reader, format)?;
+    reader.read_version(&[710])?;
+    if format == Format::Gmk {
+        background.tileset = reader.read_bool()?;
+        background.tile_width = reader.read_u32()?;
+        background.tile_height = reader.read_u32()?;
+        background.tile_x_offset = reader.read_u32()?;
+        background.tile_y_offset = reader.read_u32()?;
+        background.tile_x_separation = reader.read_u32()?;
+        background.tile_y_separation = reader.read_u32()?;
+    }
+    background.image = read_image(reader)?;
+    Ok(background)
+}
+
+pub(crate) fn write_background(writer: &mut Writer, background: &Background<'_>, format: Format) {
+    writer.write_string(background.name);
+    write_changed(writer, format);
+    writer.write_u32(710);
+    if format == Format::Gmk {
+        writer.write_bool(background.tileset);
+        writer.write_u32(background.tile_width);
+        writer.write_u32(background.tile_height);
+        writer.write_u32(background.tile_x_offset);
+        writer.write_u32(background.tile_y_offset);
+        writer.write_u32(background.tile_x_separation);
+        writer.write_u32(background.tile_y_separation);
+    }
+    write_image(writer, &background.image);
+}
+
+/// Read a path.
+///
+/// Project files also store the room and grid the path editor shows, which are discarded.
+pub(crate) fn read_path<'a>(reader: &mut Reader<'a>, format: Format) -> Result<Path<'a>, Error> {
+    let name = reader.read_string()?;
+    read_changed(reader, format)?;
+    reader.read_version(&[530])?;
+    let kind = reader.read_u32()?;
+    let closed = reader.read_bool()?;
+    let precision = reader.read_u32()?;
+    if format == Format::Gmk {
+        let _room = reader.read_i32()?;
+        let _snap_x = reader.read_u32()?;
+        let _snap_y = reader.read_u32()?;
+    }
+
+    let count = reader.read_u32()?;
+    let points = (0..count)
+        .map(|_| {
+            let x = reader.read_f64()?;
+            let y = reader.read_f64()?;
+            let speed = reader.read_f64()?;
+            Ok(PathPoint { x, y, speed })
+        })
+        .collect::<Result<_, Error>>()?;
+
+    Ok(Path { name, kind, closed, precision, points })
+}
+
+pub(crate) fn write_path(writer: &mut Writer, path: &Path<'_>, format: Format) {
+    writer.write_string(path.name);
+    write_changed(writer, format);
+    writer.write_u32(530);
+    writer.write_u32(path.kind);
+    writer.write_bool(path.closed);
+    writer.write_u32(path.precision);
+    if format == Format::Gmk {
+        writer.write_i32(-1);
+        writer.write_u32(16);
+        writer.write_u32(16);
+    }
+
+    writer.write_u32(path.points.len() as u32);
+    for point in &path.points {
+        writer.write_f64(point.x);
+        writer.write_f64(point.y);
+        writer.write_f64(point.speed);
+    }
+}
+
+pub(crate) fn read_script<'a>(
+    reader: &mut Reader<'a>, format: Format
+) -> Result<Script<'a>, Error> {
+    let name = reader.read_string()?;
+    read_changed(reader, format)?;
+    reader.read_version(&[800])?;
+    let body = reader.read_string()?;
+    Ok(Script { name, body })
+}
+
+pub(crate) fn write_script(writer: &mut Writer, script: &Script<'_>, format: Format) {
+    writer.write_string(script.name);
+    write_changed(writer, format);
+    writer.write_u32(800);
+    writer.write_string(script.body);
+}
+
+/// Read a font.
+///
+/// GM 8.1 packs the character set and antialiasing level into the high bytes of the first
+/// character. Executables also store the pre-rendered glyphs, which are discarded.
+pub(crate) fn read_font<'a>(reader: &mut Reader<'a>, format: Format) -> Result<Font<'a>, Error> {
+    let name = reader.read_string()?;
+    read_changed(reader, format)?;
+    reader.read_version(&[800])?;
+    let system_name = reader.read_string()?;
+    let size = reader.read_u32()?;
+    let bold = reader.read_bool()?;
+    let italic = reader.read_bool()?;
+    let range = reader.read_u32()?;
+    let (first, charset, antialias) = (range & 0xFFFF, (range >> 16) & 0xFF, range >> 24);
+    let last = reader.read_u32()?;
+
+    if format == Format::Exe {
+        reader.skip_words(256 * 6)?;
+        let _width = reader.read_u32()?;
+        let _height = reader.read_u32()?;
+        let _glyphs = reader.read_string()?;
+    }
+
+    Ok(Font { name, system_name, size, bold, italic, first, last, charset, antialias })
+}
+
+pub(crate) fn write_font(writer: &mut Writer, font: &Font<'_>, format: Format) {
+    writer.write_string(font.name);
+    write_changed(writer, format);
+    writer.write_u32(800);
+    writer.write_string(font.system_name);
+    writer.write_u32(font.size);
+    writer.write_bool(font.bold);
+    writer.write_bool(font.italic);
+    writer.write_u32(font.first & 0xFFFF | (font.charset & 0xFF) << 16 | font.antialias << 24);
+    writer.write_u32(font.last);
+
+    if format == Format::Exe {
+        for _ in 0..256 * 6 {
+            writer.write_u32(0);
+        }
+        writer.write_u32(0);
+        writer.write_u32(0);
+        writer.write_string(&[]);
+    }
+}
+
+pub(crate) fn read_timeline<'a>(
+    reader: &mut Reader<'a>, format: Format
+) -> Result<Timeline<'a>, Error> {
+    let name = reader.read_string()?;
+    read_changed(reader, format)?;
+    reader.read_version(&[500])?;
+    let count = reader.read_u32()?;
+    let moments = (0..count)
+        .map(|_| {
+            let position = reader.read_u32()?;
+            let actions = read_actions(reader)?;
+            Ok(Moment { position, actions })
+        })
+        .collect::<Result<_, Error>>()?;
+    Ok(Timeline { name, moments })
+}
+
+pub(crate) fn write_timeline(writer: &mut Writer, timeline: &Timeline<'_>, format: Format) {
+    writer.write_string(timeline.name);
+    write_changed(writer, format);
+    writer.write_u32(500);
+    writer.write_u32(timeline.moments.len() as u32);
+    for moment in &timeline.moments {
+        writer.write_u32(moment.position);
+        write_actions(writer, &moment.actions);
+    }
+}
+
+pub(crate) fn read_object<'a>(
+    reader: &mut Reader<'a>, format: Format
+) -> Result<Object<'a>, Error> {
+    let name = reader.read_string()?;
+    read_changed(reader, format)?;
+    reader.read_version(&[430])?;
+    let sprite = reader.read_i32()?;
+    let solid = reader.read_bool()?;
+    let visible = reader.read_bool()?;
+    let depth = reader.read_i32()?;
+    let persistent = reader.read_bool()?;
+    let parent = reader.read_i32()?;
+    let mask = reader.read_i32()?;
+
+    // Each event type holds a list of events terminated by -1.
+    let mut events = Vec::default();
+    let last_event_type = reader.read_u32()?;
+    for event_type in 0..=last_event_type {
+        loop {
+            let event_kind = reader.read_i32()?;
+            if event_kind == -1 {
+                break;
+            }
+            let actions = read_actions(reader)?;
+            events.push(Event { event_type, event_kind, actions });
+        }
+    }
+
+    Ok(Object { name, sprite, solid, visible, depth, persistent, parent, mask, events })
+}
+
+pub(crate) fn write_object(writer: &mut Writer, object: &Object<'_>, format: Format) {
+    writer.write_string(object.name);
+    write_changed(writer, format);
+    writer.write_u32(430);
+    writer.write_i32(object.sprite);
+    writer.write_bool(object.solid);
+    writer.write_bool(object.visible);
+    writer.write_i32(object.depth);
+    writer.write_bool(object.persistent);
+    writer.write_i32(object.parent);
+    writer.write_i32(object.mask);
+
+    let last_event_type = object.events.iter()
+        .map(|event| event.event_type)
+        .fold(LAST_EVENT_TYPE, u32::max);
+    writer.write_u32(last_event_type);
+    for event_type in 0..=last_event_type {
+        for event in object.events.iter().filter(|event| event.event_type == event_type) {
+            writer.write_i32(event.event_kind);
+            write_actions(writer, &event.actions);
+        }
+        writer.write_i32(-1);
+    }
+}
+
+fn read_actions<'a>(reader: &mut Reader<'a>) -> Result<Vec<Action<'a>>, Error> {
+    reader.read_version(&[400])?;
+    let count = reader.read_u32()?;
+    (0..count).map(|_| read_action(reader)).collect()
+}
+
+fn read_action<'a>(reader: &mut Reader<'a>) -> Result<Action<'a>, Error> {
+    reader.read_version(&[440])?;
+    let library = reader.read_u32()?;
+    let action = reader.read_u32()?;
+    let action_kind = reader.read_u32()?;
+    let has_relative = reader.read_bool()?;
+    let is_question = reader.read_bool()?;
+    let has_target = reader.read_bool()?;
+    let action_type = reader.read_u32()?;
+    let name = reader.read_string()?;
+    let code = reader.read_string()?;
+
+    let parameters_used = reader.read_u32()?;
+    let count = reader.read_u32()?;
+    let parameters = (0..count).map(|_| reader.read_u32()).collect::<Result<_, _>>()?;
+
+    let target = reader.read_i32()?;
+    let relative = reader.read_bool()?;
+
+    let count = reader.read_u32()?;
+    let arguments = (0..count).map(|_| reader.read_string()).collect::<Result<_, _>>()?;
+
+    let negate = reader.read_bool()?;
+
+    Ok(Action {
+        library, action, action_kind, has_relative, is_question, has_target, action_type,
+        name, code, parameters_used, parameters, target, relative, arguments, negate,
+    })
+}
+
+fn write_actions(writer: &mut Writer, actions: &[Action<'_>]) {
+    writer.write_u32(400);
+    writer.write_u32(actions.len() as u32);
+    for action in actions {
+        write_action(writer, action);
+    }
+}
+
+fn write_action(writer: &mut Writer, action: &Action<'_>) {
+    writer.write_u32(440);
+    writer.write_u32(action.library);
+    writer.write_u32(action.action);
+    writer.write_u32(action.action_kind);
+    writer.write_bool(action.has_relative);
+    writer.write_bool(action.is_question);
+    writer.write_bool(action.has_target);
+    writer.write_u32(action.action_type);
+    writer.write_string(action.name);
+    writer.write_string(action.code);
+
+    writer.write_u32(action.parameters_used);
+    writer.write_u32(action.parameters.len() as u32);
+    for &parameter in &action.parameters {
+        writer.write_u32(parameter);
+    }
+
+    writer.write_i32(action.target);
+    writer.write_bool(action.relative);
+
+    writer.write_u32(action.arguments.len() as u32);
+    for argument in &action.arguments {
+        writer.write_string(argument);
+    }
+
+    writer.write_bool(action.negate);
+}
+
+/// Read a room.
+///
+/// Project files also store grid settings, lock flags, and the state of the room editor, which
+/// are discarded.
+pub(crate) fn read_room<'a>(reader: &mut Reader<'a>, format: Format) -> Result<Room<'a>, Error> {
+    let mut room = Room { name: reader.read_string()?, ..Room::default() };
+    read_changed(reader, format)?;
+    reader.read_version(&[541])?;
+    room.caption = reader.read_string()?;
+    room.width = reader.read_u32()?;
+    room.height = reader.read_u32()?;
+    if format == Format::Gmk {
+        let _snap_x = reader.read_u32()?;
+        let _snap_y = reader.read_u32()?;
+        let _isometric = reader.read_bool()?;
+    }
+    room.speed = reader.read_u32()?;
+    room.persistent = reader.read_bool()?;
+    room.background_color = reader.read_u32()?;
+    room.draw_background_color = reader.read_bool()?;
+    room.creation_code = reader.read_string()?;
+
+    let count = reader.read_u32()?;
+    room.backgrounds = (0..count)
+        .map(|_| Ok(RoomBackground {
+            visible: reader.read_bool()?,
+            foreground: reader.read_bool()?,
+            background: reader.read_i32()?,
+            x: reader.read_i32()?,
+            y: reader.read_i32()?,
+            tile_x: reader.read_bool()?,
+            tile_y: reader.read_bool()?,
+            speed_x: reader.read_i32()?,
+            speed_y: reader.read_i32()?,
+            stretch: reader.read_bool()?,
+        }))
+        .collect::<Result<_, Error>>()?;
+
+    room.enable_views = reader.read_bool()?;
+    let count = reader.read_u32()?;
+    room.views = (0..count)
+        .map(|_| Ok(View {
+            visible: reader.read_bool()?,
+            x: reader.read_i32()?,
+            y: reader.read_i32()?,
+            width: reader.read_u32()?,
+            height: reader.read_u32()?,
+            port_x: reader.read_i32()?,
+            port_y: reader.read_i32()?,
+            port_width: reader.read_u32()?,
+            port_height: reader.read_u32()?,
+            border_x: reader.read_u32()?,
+            border_y: reader.read_u32()?,
+            speed_x: reader.read_i32()?,
+            speed_y: reader.read_i32()?,
+            target: reader.read_i32()?,
+        }))
+        .collect::<Result<_, Error>>()?;
+
+    let count = reader.read_u32()?;
+    room.instances = (0..count)
+        .map(|_| {
+            let instance = Instance {
+                x: reader.read_i32()?,
+                y: reader.read_i32()?,
+                object: reader.read_i32()?,
+                id: reader.read_i32()?,
+                creation_code: reader.read_string()?,
+            };
+            if format == Format::Gmk {
+                let _locked = reader.read_bool()?;
+            }
+            Ok(instance)
+        })
+        .collect::<Result<_, Error>>()?;
+
+    let count = reader.read_u32()?;
+    room.tiles = (0..count)
+        .map(|_| {
+            let tile = Tile {
+                x: reader.read_i32()?,
+                y: reader.read_i32()?,
+                background: reader.read_i32()?,
+                tile_x: reader.read_i32()?,
+                tile_y: reader.read_i32()?,
+                width: reader.read_u32()?,
+                height: reader.read_u32()?,
+                depth: reader.read_i32()?,
+                id: reader.read_i32()?,
+            };
+            if format == Format::Gmk {
+                let _locked = reader.read_bool()?;
+            }
+            Ok(tile)
+        })
+        .collect::<Result<_, Error>>()?;
+
+    if format == Format::Gmk {
+        let _remember_editor = reader.read_bool()?;
+        let _editor_width = reader.read_u32()?;
+        let _editor_height = reader.read_u32()?;
+        // Grid, objects, tiles, backgrounds, foregrounds, views, and delete underlying objects
+        // and tiles.
+        reader.skip_words(8)?;
+        let _tab = reader.read_u32()?;
+        let _scroll_x = reader.read_i32()?;
+        let _scroll_y = reader.read_i32()?;
+    }
+
+    Ok(room)
+}
+
+pub(crate) fn write_room(writer: &mut Writer, room: &Room<'_>, format: Format) {
+    writer.write_string(room.name);
+    write_changed(writer, format);
+    writer.write_u32(541);
+    writer.write_string(room.caption);
+    writer.write_u32(room.width);
+    writer.write_u32(room.height);
+    if format == Format::Gmk {
+        writer.write_u32(16);
+        writer.write_u32(16);
+        writer.write_bool(false);
+    }
+    writer.write_u32(room.speed);
+    writer.write_bool(room.persistent);
+    writer.write_u32(room.background_color);
+    writer.write_bool(room.draw_background_color);
+    writer.write_string(room.creation_code);
+
+    writer.write_u32(room.backgrounds.len() as u32);
+    for background in &room.backgrounds {
+        writer.write_bool(background.visible);
+        writer.write_bool(background.foreground);
+        writer.write_i32(background.background);
+        writer.write_i32(background.x);
+        writer.write_i32(background.y);
+        writer.write_bool(background.tile_x);
+        writer.write_bool(background.tile_y);
+        writer.write_i32(background.speed_x);
+        writer.write_i32(background.speed_y);
+        writer.write_bool(background.stretch);
+    }
+
+    writer.write_bool(room.enable_views);
+    writer.write_u32(room.views.len() as u32);
+    for view in &room.views {
+        writer.write_bool(view.visible);
+        writer.write_i32(view.x);
+        writer.write_i32(view.y);
+        writer.write_u32(view.width);
+        writer.write_u32(view.height);
+        writer.write_i32(view.port_x);
+        writer.write_i32(view.port_y);
+        writer.write_u32(view.port_width);
+        writer.write_u32(view.port_height);
+        writer.write_u32(view.border_x);
+        writer.write_u32(view.border_y);
+        writer.write_i32(view.speed_x);
+        writer.write_i32(view.speed_y);
+        writer.write_i32(view.target);
+    }
+
+    writer.write_u32(room.instances.len() as u32);
+    for instance in &room.instances {
+        writer.write_i32(instance.x);
+        writer.write_i32(instance.y);
+        writer.write_i32(instance.object);
+        writer.write_i32(instance.id);
+        writer.write_string(instance.creation_code);
+        if format == Format::Gmk {
+            writer.write_bool(false);
+        }
+    }
+
+    writer.write_u32(room.tiles.len() as u32);
+    for tile in &room.tiles {
+        writer.write_i32(tile.x);
+        writer.write_i32(tile.y);
+        writer.write_i32(tile.background);
+        writer.write_i32(tile.tile_x);
+        writer.write_i32(tile.tile_y);
+        writer.write_u32(tile.width);
+        writer.write_u32(tile.height);
+        writer.write_i32(tile.depth);
+        writer.write_i32(tile.id);
+        if format == Format::Gmk {
+            writer.write_bool(false);
+        }
+    }
+
+    if format == Format::Gmk {
+        writer.write_bool(false);
+        writer.write_u32(0);
+        writer.write_u32(0);
+        for &flag in &[true, true, true, true, true, true, true, true] {
+            writer.write_bool(flag);
+        }
+        writer.write_u32(0);
+        writer.write_i32(0);
+        writer.write_i32(0);
+    }
+}
@@ -0,0 +1,114 @@
+use std::convert::TryInto;
+
+use miniz_oxide::{deflate, inflate};
+
+use crate::Error;
+
+/// A cursor over the little-endian encoding used by Game Maker files.
+pub struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Reader<'a> {
+        Reader { data, position: 0 }
+    }
+
+    pub fn position(&self) -> usize { self.position }
+
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let end = self.position.checked_add(len).ok_or(Error::UnexpectedEof)?;
+        let bytes = self.data.get(self.position..end).ok_or(Error::UnexpectedEof)?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, Error> {
+        let bytes = self.read_bytes(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub fn read_i32(&mut self) -> Result<i32, Error> {
+        Ok(self.read_u32()? as i32)
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, Error> {
+        Ok(self.read_u32()? != 0)
+    }
+
+    pub fn read_f64(&mut self) -> Result<f64, Error> {
+        let bytes = self.read_bytes(8)?;
+        Ok(f64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    /// Read a length-prefixed string.
+    pub fn read_string(&mut self) -> Result<&'a [u8], Error> {
+        let len = self.read_u32()? as usize;
+        self.read_bytes(len)
+    }
+
+    /// Read a section version number, checking that it is one of `versions`.
+    pub fn read_version(&mut self, versions: &[u32]) -> Result<u32, Error> {
+        let version = self.read_u32()?;
+        if !versions.contains(&version) {
+            return Err(Error::Version(version));
+        }
+        Ok(version)
+    }
+
+    /// Skip over `count` little-endian words.
+    pub fn skip_words(&mut self, count: u32) -> Result<(), Error> {
+        self.read_bytes(count as usize * 4)?;
+        Ok(())
+    }
+
+    /// Read a length-prefixed zlib stream and inflate it.
+    pub fn read_block(&mut self) -> Result<Vec<u8>, Error> {
+        let len = self.read_u32()? as usize;
+        let data = self.read_bytes(len)?;
+        inflate::decompress_to_vec_zlib(data).map_err(|_| Error::Inflate)
+    }
+}
+
+/// A buffer that builds up the little-endian encoding used by Game Maker files.
+#[derive(Default)]
+pub struct Writer {
+    data: Vec<u8>,
+}
+
+impl Writer {
+    pub fn into_inner(self) -> Vec<u8> { self.data }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_i32(&mut self, value: i32) {
+        self.write_u32(value as u32);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_u32(value as u32);
+    }
+
+    pub fn write_f64(&mut self, value: f64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    /// Write a length-prefixed string.
+    pub fn write_string(&mut self, value: &[u8]) {
+        self.write_u32(value.len() as u32);
+        self.write_bytes(value);
+    }
+
+    /// Deflate `data` and write it as a length-prefixed zlib stream.
+    pub fn write_block(&mut self, data: &[u8]) {
+        let data = deflate::compress_to_vec_zlib(data, 6);
+        self.write_string(&data);
+    }
+}
@@ -1,12 +1,45 @@
 use std::collections::HashMap;
-use std::io;
+use std::{env, fmt, fs, io, process};
 
-use gml::{Function, ErrorPrinter};
-use gml::front::Span;
+use gml::{Function, ErrorPrinter, FunctionDisplay, EventDisplay};
+use gml::diagnostic::{Printer, Renderer};
+use gml::front::{Lines, Span};
+use gml::back::ssa;
+use gml::symbol::Symbol;
 use engine::World;
 
 fn main() {
+    let args: Vec<_> = env::args().collect();
+    if let [_, command, path] = &args[..] {
+        if command == "fmt" {
+            return format(path);
+        }
+    }
+
+    let mut options = gml::Options::default();
+    let mut dump_code = false;
+    let mut cache = None;
+    let mut args = args[1..].iter();
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--dump-ssa" => options.dump_ssa = Some(dump_ssa),
+            "--dump-code" => dump_code = true,
+            "--cache" => match args.next() {
+                Some(path) => cache = Some(path),
+                None => {
+                    eprintln!("error: --cache requires a path");
+                    process::exit(1);
+                }
+            },
+            _ => {
+                eprintln!("error: unknown option {}", arg);
+                process::exit(1);
+            }
+        }
+    }
+
     let mut game = project::Game::default();
+    game.version = project::Version::GameMakerStudio;
     let mut items = HashMap::default();
     World::register(&mut items);
 
@@ -18,9 +51,11 @@ fn main() {
         list = ds_list_create()
         ds_list_add(list, 3, "foo")
         ds_list_add(list, 5)
-        show_debug_message("list[| 0] =", ds_list_find_value(list, 0))
-        show_debug_message("list[| 1] =", ds_list_find_value(list, 1))
-        show_debug_message("list[| 2] =", ds_list_find_value(list, 2))
+        list[| 3] = "bar"
+        show_debug_message("list[| 0] =", list[| 0])
+        show_debug_message("list[| 1] =", list[| 1])
+        show_debug_message("list[| 2] =", list[| 2])
+        show_debug_message("list[| 3] =", list[| 3])
         ds_list_destroy(list)
 
         var map, key1, key2;
@@ -29,20 +64,21 @@ fn main() {
         ds_map_add(map, "abc", "bar")
         key1 = ds_map_find_first(map)
         key2 = ds_map_find_next(map, key1)
-        show_debug_message("map[?", key1, "] =", ds_map_find_value(map, key1))
-        show_debug_message("map[?", key2, "] =", ds_map_find_value(map, key2))
+        map[? key2] = "baz"
+        show_debug_message("map[?", key1, "] =", map[? key1])
+        show_debug_message("map[?", key2, "] =", map[? key2])
         ds_map_destroy(map)
 
         var grid;
         grid = ds_grid_create(2, 2)
-        ds_grid_set(grid, 0, 0, 1)
-        ds_grid_set(grid, 1, 0, 2)
-        ds_grid_set(grid, 0, 1, 3)
-        ds_grid_set(grid, 1, 1, 4)
-        show_debug_message("grid[# 0, 0] =", ds_grid_get(grid, 0, 0))
-        show_debug_message("grid[# 1, 0] =", ds_grid_get(grid, 1, 0))
-        show_debug_message("grid[# 0, 1] =", ds_grid_get(grid, 0, 1))
-        show_debug_message("grid[# 1, 1] =", ds_grid_get(grid, 1, 1))
+        grid[# 0, 0] = 1
+        grid[# 1, 0] = 2
+        grid[# 0, 1] = 3
+        grid[# 1, 1] = 4
+        show_debug_message("grid[# 0, 0] =", grid[# 0, 0])
+        show_debug_message("grid[# 1, 0] =", grid[# 1, 0])
+        show_debug_message("grid[# 0, 1] =", grid[# 0, 1])
+        show_debug_message("grid[# 1, 1] =", grid[# 1, 1])
         ds_grid_destroy(grid)
 
         repeat (2) {
@@ -52,7 +88,7 @@ fn main() {
             persistent = true
             show_debug_message("persistent =", persistent)
 
-            for (i = 0; i < instance_count; i += 1) {
+            for (i = 0; i < instance_count; i++) {
                 if instance_exists(instance_id[i]) {
                     show_debug_message(instance_id[i], "=>", instance_id[i].object_index)
                 }
@@ -69,8 +105,32 @@ fn main() {
         }
     }"# });
 
-    let (mut assets, debug) = engine::build(&game, &items, io::stderr).unwrap_or_else(|_| panic!());
-    let mut world = World::default();
+    let renderer = Renderer { snippets: true };
+    // Code loaded from the cache has no SSA to dump, so rebuild it when that is asked for.
+    let cached = cache
+        .filter(|_| options.dump_ssa.is_none())
+        .and_then(|path| load_cache(path, &game, &items, &options));
+    let (mut assets, debug) = cached.unwrap_or_else(|| {
+        let build = engine::build_with(&game, &items, &options);
+        let diagnostics = match build {
+            Ok((_, _, ref diagnostics)) | Err(ref diagnostics) => diagnostics,
+        };
+        for diagnostic in diagnostics {
+            let _ = renderer.render(diagnostic, &mut io::stderr());
+        }
+        let (assets, debug, _) = build.unwrap_or_else(|_| panic!());
+        if let Some(path) = cache {
+            let data = gml::save(&assets.code, &debug, &game, &items, &options);
+            if let Err(error) = fs::write(path, data) {
+                eprintln!("warning: {}: {}", path, error);
+            }
+        }
+        (assets, debug)
+    });
+    if dump_code {
+        self::dump_code(&assets.code.code, &debug);
+    }
+    let mut world = World::new(&game.objects);
     let mut thread = gml::vm::Thread::default();
 
     let id = world.instance.instance_create(&mut world.world, &mut world.motion, 0.0, 0.0, 0)
@@ -81,7 +141,8 @@ fn main() {
         .unwrap_or_else(|_| panic!("object does not exist"));
 
     if let Err(error) = thread.execute(&mut world, &mut assets, main, vec![]) {
-        let mut errors = ErrorPrinter::from_debug(&debug, error.function, io::stderr());
+        let printer = Printer { renderer, write: io::stderr() };
+        let mut errors = ErrorPrinter::from_debug(&debug, error.function, printer);
         let offset = error.instruction as u32;
         let location = debug.locations[&error.function].locations.get_location(offset);
         let span = Span { low: location as usize, high: location as usize };
@@ -90,3 +151,134 @@ fn main() {
 
     world.instance.free_destroyed(&mut world.world, &mut world.motion);
 }
+
+/// Print a function's SSA as it is about to be lowered to bytecode.
+fn dump_ssa(name: FunctionDisplay, program: &ssa::Function) {
+    println!("{}:\n{}", name, program);
+}
+
+/// Load the code saved by an earlier run, unless it is missing or out of date.
+fn load_cache(
+    path: &str, game: &project::Game, items: &HashMap<Symbol, gml::Item<World, engine::Assets>>,
+    options: &gml::Options
+) -> Option<(engine::Assets, gml::vm::Debug)> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(ref error) if error.kind() == io::ErrorKind::NotFound => return None,
+        Err(error) => {
+            eprintln!("warning: {}: {}", path, error);
+            return None;
+        }
+    };
+    match engine::load(&data, game, items, options) {
+        Ok(loaded) => Some(loaded),
+        Err(gml::vm::serialize::Error::Stale) => None,
+        Err(error) => {
+            eprintln!("warning: {}: {}", path, error);
+            None
+        }
+    }
+}
+
+/// Print the bytecode of every script and event, ordered by name.
+fn dump_code(code: &HashMap<Function, gml::vm::code::Function>, debug: &gml::vm::Debug) {
+    let mut functions: Vec<_> = code.iter()
+        .map(|(&function, code)| {
+            let name = FunctionDisplay::from_debug(debug, function).to_string();
+            (name, function, code)
+        })
+        .collect();
+    functions.sort_by(|(a, ..), (b, ..)| a.cmp(b));
+    for (name, function, code) in functions {
+        println!("{}:\n{}", name, code.disassemble(debug, function));
+    }
+}
+
+/// Reformat the scripts and code actions of a project file in place.
+///
+/// Only .gmk files are supported. Executables and .gmx projects can be read but not written back.
+fn format(path: &str) {
+    let fail = |error: &dyn fmt::Display| -> ! {
+        eprintln!("error: {}: {}", path, error);
+        process::exit(1);
+    };
+    if !path.ends_with(".gmk") {
+        fail(&"only .gmk files can be formatted");
+    }
+
+    let data = fs::read(path).unwrap_or_else(|error| fail(&error));
+    let blocks = project::gmk::Blocks::read(&data).unwrap_or_else(|error| fail(&error));
+    let game = blocks.game().unwrap_or_else(|error| fail(&error));
+
+    let mut printer = Printer { renderer: Renderer { snippets: true }, write: io::stderr() };
+    let mut format_code = |name, code: &[u8]| {
+        let lines = Lines::from_code(code);
+        let mut errors = ErrorPrinter::new(name, &lines, &mut printer);
+        gml::front::format(code, game.version, &mut errors)
+    };
+
+    let scripts: Vec<_> = game.scripts.iter()
+        .map(|script| {
+            let name = FunctionDisplay::Script { script: Symbol::intern(script.name) };
+            format_code(name, script.body)
+        })
+        .collect();
+
+    let mut actions = Vec::new();
+    for object in &game.objects {
+        for event in &object.events {
+            let object = Symbol::intern(object.name);
+            let event_kind = EventDisplay::from_game(&game, event.event_type, event.event_kind);
+            let name = FunctionDisplay::Event { object, event_type: event.event_type, event_kind };
+            for action in &event.actions {
+                if action.action_kind == project::action_kind::CODE {
+                    actions.push(format_code(name, action.arguments[0]));
+                }
+            }
+        }
+    }
+    for timeline in &game.timelines {
+        for moment in &timeline.moments {
+            let timeline = Symbol::intern(timeline.name);
+            let name = FunctionDisplay::Moment { timeline, moment: moment.position };
+            for action in &moment.actions {
+                if action.action_kind == project::action_kind::CODE {
+                    actions.push(format_code(name, action.arguments[0]));
+                }
+            }
+        }
+    }
+
+    // The formatted code outlives the original `Game`, so rebind it to borrow from both.
+    let mut game = game;
+    for (script, body) in Iterator::zip(game.scripts.iter_mut(), &scripts) {
+        if let Some(body) = body {
+            script.body = body;
+        }
+    }
+    let mut actions = actions.iter();
+    for object in &mut game.objects {
+        for event in &mut object.events {
+            for action in &mut event.actions {
+                if action.action_kind == project::action_kind::CODE {
+                    if let Some(Some(code)) = actions.next() {
+                        action.arguments[0] = code;
+                    }
+                }
+            }
+        }
+    }
+    for timeline in &mut game.timelines {
+        for moment in &mut timeline.moments {
+            for action in &mut moment.actions {
+                if action.action_kind == project::action_kind::CODE {
+                    if let Some(Some(code)) = actions.next() {
+                        action.arguments[0] = code;
+                    }
+                }
+            }
+        }
+    }
+
+    fs::write(path, project::gmk::write(&game)).unwrap_or_else(|error| fail(&error));
+}